@@ -0,0 +1,219 @@
+//! Brier score and reliability of precipitation probability for each lead time
+
+use crate::analysis::{PredictionPair, RainEvent, MAX_HOUR_DIFF, write_csv};
+use crate::Error;
+use serde::{Serialize, Deserialize};
+use std::io::Write;
+
+/// Number of probability buckets (0-10%, 10-20%, ..., 90-100%)
+pub const BIN_COUNT: usize = 10;
+
+/// Predictions grouped by predicted probability of precipitation
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Default, PartialEq)]
+pub struct ReliabilityBin {
+    /// Lowest predicted probability in bucket (between 0 and 1, inclusive)
+    pub lower: f64,
+    /// Highest predicted probability in bucket (between 0 and 1, exclusive unless 1)
+    pub upper: f64,
+    /// Number of predictions in bucket
+    pub count: usize,
+    /// Average predicted probability of predictions in bucket
+    pub mean_forecast: f64,
+    /// Fraction of predictions in bucket where precipitation was observed
+    pub observed_frequency: f64,
+}
+
+/// Brier score, its decomposition and reliability table for a single lead time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LeadTimeCalibration {
+    /// How many hours forward the predictions were made
+    pub hour_diff: u8,
+    /// Number of predictions that had a matching reading
+    pub count: usize,
+    /// Mean squared error of predicted probability against observed outcome (0 is perfect)
+    pub brier: f64,
+    /// How far predicted probabilities are from observed frequencies (lower is better)
+    pub reliability: f64,
+    /// How far observed frequencies per bucket are from the overall frequency (higher is better)
+    pub resolution: f64,
+    /// Variance of the observed outcome, independent of the predictions
+    pub uncertainty: f64,
+    /// Always has `BIN_COUNT` entries, from 0-10% to 90-100%
+    pub bins: Vec<ReliabilityBin>,
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct BinAccumulator {
+    count: usize,
+    forecast_sum: f64,
+    observed_sum: f64,
+}
+
+#[derive(Debug, Default, Clone)]
+struct LeadTimeAccumulator {
+    count: usize,
+    observed_sum: f64,
+    squared_error_sum: f64,
+    bins: [BinAccumulator; BIN_COUNT],
+}
+
+fn bin_index(probability: f64) -> usize {
+    return ((probability * BIN_COUNT as f64).floor().max(0.) as usize).min(BIN_COUNT - 1);
+}
+
+/// Calculate the Brier score and its decomposition (reliability - resolution + uncertainty) for each lead time
+///
+/// Decomposition uses the mean forecast of each bucket, so `reliability - resolution + uncertainty` will be
+/// close to but not always exactly the same as `brier`
+///
+/// # Returns
+/// One entry per lead time that has at least one pair, sorted by hour diff
+///
+pub fn calculate_calibration(pairs: &[PredictionPair], event: RainEvent) -> Vec<LeadTimeCalibration> {
+    let mut accumulators = vec![LeadTimeAccumulator::default(); MAX_HOUR_DIFF as usize];
+
+    pairs.iter()
+        .filter(|(prediction, _)| prediction.hour_diff >= 1 && prediction.hour_diff <= MAX_HOUR_DIFF)
+        .for_each(|(prediction, observed)| {
+            let outcome = if event.occurred(observed) { 1. } else { 0. };
+            let acc = &mut accumulators[prediction.hour_diff as usize - 1];
+            acc.count += 1;
+            acc.observed_sum += outcome;
+            acc.squared_error_sum += (prediction.precip_probability - outcome).powi(2);
+            let bin = &mut acc.bins[bin_index(prediction.precip_probability)];
+            bin.count += 1;
+            bin.forecast_sum += prediction.precip_probability;
+            bin.observed_sum += outcome;
+        });
+
+    return accumulators.into_iter()
+        .enumerate()
+        .filter(|(_, acc)| acc.count > 0)
+        .map(|(i, acc)| {
+            let count = acc.count as f64;
+            let climatology = acc.observed_sum / count;
+
+            let bins: Vec<ReliabilityBin> = acc.bins.iter()
+                .enumerate()
+                .map(|(b, bin)| {
+                    let (mean_forecast, observed_frequency) = if bin.count == 0 {
+                        (0., 0.)
+                    } else {
+                        (bin.forecast_sum / bin.count as f64, bin.observed_sum / bin.count as f64)
+                    };
+                    ReliabilityBin {
+                        lower: b as f64 / BIN_COUNT as f64,
+                        upper: (b + 1) as f64 / BIN_COUNT as f64,
+                        count: bin.count,
+                        mean_forecast,
+                        observed_frequency,
+                    }
+                })
+                .collect();
+
+            let reliability = bins.iter()
+                .map(|bin| bin.count as f64 * (bin.mean_forecast - bin.observed_frequency).powi(2))
+                .sum::<f64>() / count;
+            let resolution = bins.iter()
+                .map(|bin| bin.count as f64 * (bin.observed_frequency - climatology).powi(2))
+                .sum::<f64>() / count;
+
+            LeadTimeCalibration {
+                hour_diff: i as u8 + 1,
+                count: acc.count,
+                brier: acc.squared_error_sum / count,
+                reliability,
+                resolution,
+                uncertainty: climatology * (1. - climatology),
+                bins,
+            }
+        })
+        .collect();
+}
+
+/// CSV of calibration results, one row per lead time and bucket
+pub struct CalibrationCsv<'a>(pub &'a [LeadTimeCalibration]);
+
+#[derive(Serialize)]
+struct CalibrationRow {
+    hour_diff: u8,
+    bin_lower: f64,
+    bin_upper: f64,
+    bin_count: usize,
+    mean_forecast: f64,
+    observed_frequency: f64,
+    count: usize,
+    brier: f64,
+    reliability: f64,
+    resolution: f64,
+    uncertainty: f64,
+}
+
+impl CalibrationCsv<'_> {
+    /// # Errors
+    /// Failed to write
+    ///
+    pub fn write<W: Write>(&self, out: W) -> Result<(), Error> {
+        let rows = self.0.iter().flat_map(|row| row.bins.iter().map(move |bin| CalibrationRow {
+            hour_diff: row.hour_diff,
+            bin_lower: bin.lower,
+            bin_upper: bin.upper,
+            bin_count: bin.count,
+            mean_forecast: bin.mean_forecast,
+            observed_frequency: bin.observed_frequency,
+            count: row.count,
+            brier: row.brier,
+            reliability: row.reliability,
+            resolution: row.resolution,
+            uncertainty: row.uncertainty,
+        }));
+        return write_csv(rows, out);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::test_pairs::rain_pair;
+
+    #[test]
+    fn test_bin_index() {
+        assert_eq!(bin_index(0.), 0);
+        assert_eq!(bin_index(0.09), 0);
+        assert_eq!(bin_index(0.1), 1);
+        assert_eq!(bin_index(0.95), 9);
+        assert_eq!(bin_index(1.), 9, "100% goes in last bucket");
+    }
+
+    #[test]
+    fn test_calibration() {
+        let pairs = vec![rain_pair(1, 0.05, 0.), rain_pair(1, 0.05, 0.), rain_pair(1, 0.85, 1.), rain_pair(1, 0.85, 0.)];
+
+        let results = calculate_calibration(&pairs, RainEvent::Intensity(0.1));
+
+        assert_eq!(results.len(), 1);
+        let result = &results[0];
+        assert_eq!(result.count, 4);
+        assert_eq!(result.bins.len(), BIN_COUNT);
+        assert_eq!(result.bins[0].count, 2);
+        assert_eq!(result.bins[0].observed_frequency, 0.);
+        assert_eq!(result.bins[8].count, 2);
+        assert_eq!(result.bins[8].observed_frequency, 0.5);
+
+        let brier = (0.05_f64.powi(2) * 2. + 0.15_f64.powi(2) + 0.85_f64.powi(2)) / 4.;
+        assert!((result.brier - brier).abs() < 1e-9, "brier");
+        assert!((result.uncertainty - 0.1875).abs() < 1e-9, "uncertainty");
+        assert!((result.resolution - 0.0625).abs() < 1e-9, "resolution");
+        assert!((result.reliability - result.brier - result.resolution + result.uncertainty).abs() < 1e-9, "all forecasts in a bucket are the same so decomposition is exact");
+    }
+
+    #[test]
+    fn test_precip_type_event() {
+        let (prediction, mut observed) = rain_pair(2, 0.5, 0.);
+        observed.precip_type = Some(String::from("rain"));
+
+        let results = calculate_calibration(&[(prediction, observed)], RainEvent::PrecipType);
+
+        assert_eq!(results[0].bins[5].observed_frequency, 1.);
+    }
+}
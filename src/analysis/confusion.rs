@@ -0,0 +1,119 @@
+//! Predicted vs observed icon counts
+
+use crate::analysis::PredictionPair;
+use crate::models::Icon;
+
+const ICON_COUNT: usize = Icon::ALL.len();
+
+/// Counts of predicted icon against observed icon
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfusionMatrix {
+    /// Indexed by `[predicted.index()][observed.index()]`
+    counts: [[usize; ICON_COUNT]; ICON_COUNT],
+}
+
+impl ConfusionMatrix {
+    /// Count one prediction
+    pub fn add(&mut self, predicted: Icon, observed: Icon) {
+        self.counts[predicted.index()][observed.index()] += 1;
+    }
+
+    /// Number of times `predicted` was forecast and `observed` happened
+    pub fn count(&self, predicted: Icon, observed: Icon) -> usize {
+        return self.counts[predicted.index()][observed.index()];
+    }
+
+    /// Number of predictions counted
+    pub fn total(&self) -> usize {
+        return self.counts.iter().map(|row| row.iter().sum::<usize>()).sum();
+    }
+
+    /// Number of times `icon` was forecast
+    pub fn predicted_total(&self, icon: Icon) -> usize {
+        return self.counts[icon.index()].iter().sum();
+    }
+
+    /// Number of times `icon` was observed
+    pub fn observed_total(&self, icon: Icon) -> usize {
+        return self.counts.iter().map(|row| row[icon.index()]).sum();
+    }
+
+    /// Fraction of all predictions where the icon matched, or `None` if empty
+    pub fn accuracy(&self) -> Option<f64> {
+        let correct: usize = Icon::ALL.iter().map(|icon| self.count(*icon, *icon)).sum();
+        return ratio(correct, self.total());
+    }
+
+    /// Fraction of times `icon` was forecast that it was observed, or `None` if never forecast
+    pub fn precision(&self, icon: Icon) -> Option<f64> {
+        return ratio(self.count(icon, icon), self.predicted_total(icon));
+    }
+
+    /// Fraction of times `icon` was observed that it was forecast, or `None` if never observed
+    pub fn recall(&self, icon: Icon) -> Option<f64> {
+        return ratio(self.count(icon, icon), self.observed_total(icon));
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> Option<f64> {
+    if denominator == 0 {
+        return None;
+    }
+    return Some(numerator as f64 / denominator as f64);
+}
+
+/// Build a confusion matrix of predicted icon against observed icon
+///
+/// Only predictions made between `min_hour_diff` and `max_hour_diff` (inclusive, inclusive) hours before are used
+pub fn calculate_confusion_matrix(pairs: &[PredictionPair], min_hour_diff: u8, max_hour_diff: u8) -> ConfusionMatrix {
+    let mut matrix = ConfusionMatrix::default();
+
+    pairs.iter()
+        .filter(|(prediction, _)| prediction.hour_diff >= min_hour_diff && prediction.hour_diff <= max_hour_diff)
+        .for_each(|(prediction, observed)| matrix.add(prediction.icon, observed.icon));
+
+    return matrix;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::test_pairs;
+
+    fn pair(hour_diff: u8, predicted: Icon, observed: Icon) -> PredictionPair {
+        let (mut prediction, mut reading) = test_pairs::pair((2019, 326, 0), hour_diff);
+        prediction.icon = predicted;
+        reading.icon = observed;
+        return (prediction, reading);
+    }
+
+    #[test]
+    fn test_confusion_matrix() {
+        let pairs = vec![
+            pair(1, Icon::PartlyCloudy, Icon::Rain),
+            pair(2, Icon::PartlyCloudy, Icon::PartlyCloudy),
+            pair(3, Icon::Rain, Icon::Rain),
+            pair(4, Icon::Rain, Icon::Rain),
+            pair(5, Icon::Clear, Icon::Rain),
+            pair(30, Icon::Clear, Icon::Clear),
+        ];
+
+        let matrix = calculate_confusion_matrix(&pairs, 1, 24);
+
+        assert_eq!(matrix.total(), 5, "lead time outside of window is ignored");
+        assert_eq!(matrix.count(Icon::PartlyCloudy, Icon::Rain), 1);
+        assert_eq!(matrix.count(Icon::Rain, Icon::Rain), 2);
+        assert_eq!(matrix.accuracy(), Some(0.6));
+        assert_eq!(matrix.precision(Icon::PartlyCloudy), Some(0.5));
+        assert_eq!(matrix.precision(Icon::Clear), Some(0.));
+        assert_eq!(matrix.recall(Icon::Rain), Some(0.5));
+        assert_eq!(matrix.recall(Icon::Clear), None, "clear was never observed in window");
+    }
+
+    #[test]
+    fn test_icon_index() {
+        Icon::ALL.iter()
+            .enumerate()
+            .for_each(|(i, icon)| assert_eq!(icon.index(), i));
+    }
+}
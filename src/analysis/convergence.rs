@@ -0,0 +1,270 @@
+//! How the forecast for a single hour changed as it was reissued, from 48 hours before to the reading
+//!
+//! Volatility is measured by flip-flops (how often the forecast changed direction) and total variation (sum of every change).
+//! The lead time the forecast came within a tolerance of the reading shows how early it was worth acting on
+
+use crate::models::{Prediction, Weather};
+use crate::analysis::write_csv;
+use crate::Error;
+use serde::{Serialize, Deserialize};
+use std::fmt::{Display, Formatter};
+use std::fmt;
+use std::io::Write;
+
+/// A predicted variable that can be followed across forecasts
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub enum Variable {
+    /// 'Feels like' temperature in celsius
+    Temp,
+    /// Probability of precipitation (between 0 and 1)
+    PrecipProbability,
+    /// Precipitation in millimeters per hour
+    PrecipIntensity,
+    /// Average wind speed in meters per second
+    WindSpeed,
+    /// Wind gust speed in meters per second
+    WindGust,
+    /// Relative humidity (between 0 and 1)
+    Humidity,
+}
+
+impl Variable {
+    /// Every variable, in declaration order
+    pub const ALL: [Variable; 6] = [Variable::Temp, Variable::PrecipProbability, Variable::PrecipIntensity, Variable::WindSpeed, Variable::WindGust, Variable::Humidity];
+
+    /// Name of the field, used in CSV output
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Variable::Temp => "temp",
+            Variable::PrecipProbability => "precip_probability",
+            Variable::PrecipIntensity => "precip_intensity",
+            Variable::WindSpeed => "wind_speed",
+            Variable::WindGust => "wind_gust",
+            Variable::Humidity => "humidity",
+        };
+    }
+
+    /// How close a forecast has to be to the reading to count as right
+    pub fn default_tolerance(&self) -> f64 {
+        return match self {
+            Variable::Temp => 1.,
+            Variable::PrecipProbability => 0.1,
+            Variable::PrecipIntensity => 0.5,
+            Variable::WindSpeed => 2.,
+            Variable::WindGust => 3.,
+            Variable::Humidity => 0.05,
+        };
+    }
+
+    /// Value of this variable in `weather`
+    pub fn of_reading(&self, weather: &Weather) -> f64 {
+        return match self {
+            Variable::Temp => weather.temp,
+            Variable::PrecipProbability => weather.precip_probability,
+            Variable::PrecipIntensity => weather.precip_intensity,
+            Variable::WindSpeed => weather.wind_speed,
+            Variable::WindGust => weather.wind_gust,
+            Variable::Humidity => weather.humidity,
+        };
+    }
+
+    /// Value of this variable in `prediction`
+    pub fn of_prediction(&self, prediction: &Prediction) -> f64 {
+        return match self {
+            Variable::Temp => prediction.temp,
+            Variable::PrecipProbability => prediction.precip_probability,
+            Variable::PrecipIntensity => prediction.precip_intensity,
+            Variable::WindSpeed => prediction.wind_speed,
+            Variable::WindGust => prediction.wind_gust,
+            Variable::Humidity => prediction.humidity,
+        };
+    }
+}
+
+/// One issue of the forecast for the target hour
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq)]
+pub struct ForecastPoint {
+    /// How many hours before the target hour the forecast was issued
+    pub hour_diff: u8,
+    /// Unix timestamp of the hour the forecast was issued
+    pub issued: i64,
+    /// Forecast value of the variable
+    pub value: f64,
+}
+
+/// Every forecast of one variable for the target hour and how it converged on the reading
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Convergence {
+    /// Variable that was followed
+    pub variable: Variable,
+    /// How close a forecast had to be to the reading to count as within it
+    pub tolerance: f64,
+    /// Unix timestamp of the target hour
+    pub timestamp: i64,
+    /// Value of the reading for the target hour
+    pub observed: f64,
+    /// Oldest issue first
+    pub forecasts: Vec<ForecastPoint>,
+    /// Number of times the forecast changed direction (e.g. rising then falling)
+    pub flip_flops: usize,
+    /// Sum of the size of every change between issues
+    pub total_variation: f64,
+    /// Longest lead time where the forecast was within tolerance of the reading
+    pub first_within: Option<u8>,
+    /// Longest lead time from which every later forecast was within tolerance of the reading
+    pub stayed_within: Option<u8>,
+}
+
+/// Follow `variable` across every prediction for the hour of `observed`
+///
+/// # Params
+/// Predictions: every prediction for the hour of `observed`, in any order
+///
+pub fn calculate_convergence(observed: &Weather, predictions: &[Prediction], variable: Variable, tolerance: f64) -> Convergence {
+    let mut forecasts: Vec<ForecastPoint> = predictions.iter()
+        .map(|prediction| ForecastPoint {
+            hour_diff: prediction.hour_diff,
+            issued: prediction.reading_timestamp,
+            value: variable.of_prediction(prediction),
+        })
+        .collect();
+    forecasts.sort_by_key(|point| point.issued);
+
+    let observed_value = variable.of_reading(observed);
+    let changes: Vec<f64> = forecasts.windows(2)
+        .map(|pair| pair[1].value - pair[0].value)
+        .collect();
+    let directions: Vec<bool> = changes.iter()
+        .filter(|change| **change != 0.)
+        .map(|change| *change > 0.)
+        .collect();
+    let within = |point: &ForecastPoint| (point.value - observed_value).abs() <= tolerance;
+
+    let stayed_from = forecasts.iter().rposition(|point| !within(point)).map_or(0, |index| index + 1);
+
+    return Convergence {
+        variable,
+        tolerance,
+        timestamp: observed.timestamp,
+        observed: observed_value,
+        flip_flops: directions.windows(2).filter(|pair| pair[0] != pair[1]).count(),
+        total_variation: changes.iter().fold(0., |total, change| total + change.abs()),
+        first_within: forecasts.iter().find(|point| within(point)).map(|point| point.hour_diff),
+        stayed_within: forecasts.get(stayed_from).map(|point| point.hour_diff),
+        forecasts,
+    };
+}
+
+/// Follow every variable (using its default tolerance) across the predictions for the hour of `observed`
+pub fn calculate_all_convergence(observed: &Weather, predictions: &[Prediction]) -> Vec<Convergence> {
+    return Variable::ALL.iter()
+        .map(|variable| calculate_convergence(observed, predictions, *variable, variable.default_tolerance()))
+        .collect();
+}
+
+/// Summary table of volatility and convergence, one row per variable
+pub struct ConvergenceTable<'a>(pub &'a [Convergence]);
+
+impl Display for ConvergenceTable<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let lead_time = |hour_diff: Option<u8>| hour_diff.map(|hour_diff| hour_diff.to_string()).unwrap_or_else(|| String::from("-"));
+        writeln!(f, "{: <20}{: >10}{: >10}{: >8}{: >12}{: >10}{: >10}", "variable", "observed", "tolerance", "flips", "variation", "within", "stayed")?;
+        for row in self.0 {
+            writeln!(f, "{: <20}{: >10.2}{: >10.2}{: >8}{: >12.2}{: >10}{: >10}",
+                     row.variable.name(),
+                     row.observed,
+                     row.tolerance,
+                     row.flip_flops,
+                     row.total_variation,
+                     lead_time(row.first_within),
+                     lead_time(row.stayed_within))?;
+        }
+        Ok(())
+    }
+}
+
+/// CSV of every forecast for each variable, oldest first and ending with the reading (hour diff 0)
+pub struct ConvergenceCsv<'a>(pub &'a [Convergence]);
+
+#[derive(Serialize)]
+struct ConvergenceRow {
+    variable: &'static str,
+    hour_diff: u8,
+    issued_timestamp: i64,
+    value: f64,
+    error: f64,
+}
+
+impl ConvergenceCsv<'_> {
+    /// # Errors
+    /// Failed to write
+    ///
+    pub fn write<W: Write>(&self, out: W) -> Result<(), Error> {
+        let rows = self.0.iter().flat_map(|row| {
+            let forecasts = row.forecasts.iter().map(move |point| ConvergenceRow {
+                variable: row.variable.name(),
+                hour_diff: point.hour_diff,
+                issued_timestamp: point.issued,
+                value: point.value,
+                error: point.value - row.observed,
+            });
+            let reading = ConvergenceRow { variable: row.variable.name(), hour_diff: 0, issued_timestamp: row.timestamp, value: row.observed, error: 0. };
+            forecasts.chain(std::iter::once(reading))
+        });
+        return write_csv(rows, out);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Icon;
+
+    fn prediction(hour_diff: u8, temp: f64) -> Prediction {
+        return Prediction::new(2019, 326, 12 - hour_diff, 2019, 326, 12, hour_diff, Icon::Clear, 0., 0., temp, 0., 0., 0., None);
+    }
+
+    #[test]
+    fn test_convergence() {
+        let observed = Weather::new(2019, 326, 12, Icon::Clear, 0., 0., 10., 0., 0., 0., None);
+        let predictions = vec![prediction(1, 10.), prediction(2, 9.5), prediction(3, 12.), prediction(4, 9.), prediction(5, 14.), prediction(6, 14.)];
+
+        let convergence = calculate_convergence(&observed, &predictions, Variable::Temp, 1.);
+
+        let hour_diffs: Vec<u8> = convergence.forecasts.iter().map(|point| point.hour_diff).collect();
+        assert_eq!(hour_diffs, vec![6, 5, 4, 3, 2, 1], "oldest issue first");
+        assert_eq!(convergence.observed, 10.);
+        assert_eq!(convergence.flip_flops, 3, "falls, rises, falls, rises, the unchanged issue doesn't count");
+        assert_eq!(convergence.total_variation, 5. + 3. + 2.5 + 0.5);
+        assert_eq!(convergence.first_within, Some(4));
+        assert_eq!(convergence.stayed_within, Some(2));
+    }
+
+    #[test]
+    fn test_never_within() {
+        let observed = Weather::new(2019, 326, 12, Icon::Clear, 0., 0., 10., 0., 0., 0., None);
+        let predictions = vec![prediction(1, 12.), prediction(2, 14.)];
+
+        let convergence = calculate_convergence(&observed, &predictions, Variable::Temp, 1.);
+
+        assert_eq!(convergence.first_within, None);
+        assert_eq!(convergence.stayed_within, None);
+        assert_eq!(convergence.flip_flops, 0);
+        assert_eq!(calculate_convergence(&observed, &[], Variable::Temp, 1.).total_variation, 0.);
+    }
+
+    #[test]
+    fn test_csv() {
+        let observed = Weather::new(2019, 326, 12, Icon::Clear, 0., 0., 10., 0., 0., 0., None);
+        let results = calculate_all_convergence(&observed, &[prediction(2, 11.), prediction(1, 10.5)]);
+        let mut csv = vec![];
+        ConvergenceCsv(&results).write(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 1 + Variable::ALL.len() * 3);
+        assert_eq!(lines[0], "variable,hour_diff,issued_timestamp,value,error");
+        assert_eq!(lines[1], format!("temp,2,{},11.0,1.0", observed.timestamp - 2 * 3600));
+        assert_eq!(lines[3], format!("temp,0,{},10.0,0.0", observed.timestamp));
+    }
+}
@@ -0,0 +1,183 @@
+//! MAE, RMSE and bias of predictions for each lead time
+
+use crate::analysis::{PredictionPair, MAX_HOUR_DIFF};
+use serde::{Serialize, Deserialize};
+use std::fmt::{Display, Formatter};
+use std::fmt;
+
+/// Summary of how far off a set of predictions were for one variable
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Default, PartialEq)]
+pub struct ErrorMetrics {
+    /// Mean absolute error
+    pub mae: f64,
+    /// Root mean squared error
+    pub rmse: f64,
+    /// Mean error (`predicted - observed`), positive means predictions were too high
+    pub bias: f64,
+}
+
+/// Error metrics for every variable at a single lead time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LeadTimeErrors {
+    /// How many hours forward the predictions were made
+    pub hour_diff: u8,
+    /// Number of predictions that had a matching reading
+    pub count: usize,
+    /// 'Feels like' temperature in celsius
+    pub temp: ErrorMetrics,
+    /// Average wind speed in meters per second
+    pub wind_speed: ErrorMetrics,
+    /// Wind gust speed in meters per second
+    pub wind_gust: ErrorMetrics,
+    /// Relative humidity (between 0 and 1)
+    pub humidity: ErrorMetrics,
+    /// Precipitation in millimeters per hour
+    pub precip_intensity: ErrorMetrics,
+}
+
+/// Running totals used to build `ErrorMetrics` without keeping every error in memory
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ErrorAccumulator {
+    count: usize,
+    sum: f64,
+    sum_abs: f64,
+    sum_sq: f64,
+}
+
+impl ErrorAccumulator {
+    /// Add the error of one prediction
+    pub fn add(&mut self, predicted: f64, observed: f64) {
+        let err = predicted - observed;
+        self.count += 1;
+        self.sum += err;
+        self.sum_abs += err.abs();
+        self.sum_sq += err * err;
+    }
+
+    /// Metrics of every error added so far, all 0 if none were added
+    pub fn metrics(&self) -> ErrorMetrics {
+        if self.count == 0 {
+            return ErrorMetrics::default();
+        }
+        let count = self.count as f64;
+        return ErrorMetrics {
+            mae: self.sum_abs / count,
+            rmse: (self.sum_sq / count).sqrt(),
+            bias: self.sum / count,
+        };
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct LeadTimeAccumulator {
+    temp: ErrorAccumulator,
+    wind_speed: ErrorAccumulator,
+    wind_gust: ErrorAccumulator,
+    humidity: ErrorAccumulator,
+    precip_intensity: ErrorAccumulator,
+}
+
+/// Calculate MAE, RMSE and bias for each lead time (1 to `MAX_HOUR_DIFF`)
+///
+/// Pairs with a lead time outside of that range are ignored
+///
+/// # Returns
+/// One entry per lead time that has at least one pair, sorted by hour diff
+///
+pub fn calculate_error_metrics(pairs: &[PredictionPair]) -> Vec<LeadTimeErrors> {
+    let mut accumulators = vec![LeadTimeAccumulator::default(); MAX_HOUR_DIFF as usize];
+
+    pairs.iter()
+        .filter(|(prediction, _)| prediction.hour_diff >= 1 && prediction.hour_diff <= MAX_HOUR_DIFF)
+        .for_each(|(prediction, observed)| {
+            let acc = &mut accumulators[prediction.hour_diff as usize - 1];
+            acc.temp.add(prediction.temp, observed.temp);
+            acc.wind_speed.add(prediction.wind_speed, observed.wind_speed);
+            acc.wind_gust.add(prediction.wind_gust, observed.wind_gust);
+            acc.humidity.add(prediction.humidity, observed.humidity);
+            acc.precip_intensity.add(prediction.precip_intensity, observed.precip_intensity);
+        });
+
+    return accumulators.into_iter()
+        .enumerate()
+        .filter(|(_, acc)| acc.temp.count > 0)
+        .map(|(i, acc)| LeadTimeErrors {
+            hour_diff: i as u8 + 1,
+            count: acc.temp.count,
+            temp: acc.temp.metrics(),
+            wind_speed: acc.wind_speed.metrics(),
+            wind_gust: acc.wind_gust.metrics(),
+            humidity: acc.humidity.metrics(),
+            precip_intensity: acc.precip_intensity.metrics(),
+        })
+        .collect();
+}
+
+/// Plain text table of error metrics, one row per lead time
+pub struct ErrorMetricsTable<'a>(pub &'a [LeadTimeErrors]);
+
+impl Display for ErrorMetricsTable<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{: <6}{: <8}{: <21}{: <21}{: <21}{: <21}{: <21}", "Hours", "Count", "Temp (°C)", "Wnd Spd (m/s)", "Wnd Gst (m/s)", "Humidity (%)", "P. Amt (mm/h)")?;
+        writeln!(f, "{: <14}{}", "", "MAE   RMSE  Bias     ".repeat(5))?;
+        for row in self.0 {
+            write!(f, "{: <6}{: <8}", row.hour_diff, row.count)?;
+            let humidity = ErrorMetrics {
+                mae: row.humidity.mae * 100.,
+                rmse: row.humidity.rmse * 100.,
+                bias: row.humidity.bias * 100.,
+            };
+            for metrics in &[row.temp, row.wind_speed, row.wind_gust, humidity, row.precip_intensity] {
+                write!(f, "{: <6.2}{: <6.2}{: <+7.2}  ", metrics.mae, metrics.rmse, metrics.bias)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::test_pairs;
+
+    /// Wind speed is always predicted 1 too low and humidity 0.1 too high
+    fn pair(hour_diff: u8, predicted_temp: f64, observed_temp: f64) -> PredictionPair {
+        let (mut prediction, mut observed) = test_pairs::pair((2019, 326, 4), hour_diff);
+        prediction.temp = predicted_temp;
+        prediction.wind_speed = 2.;
+        prediction.humidity = 0.5;
+        observed.temp = observed_temp;
+        observed.wind_speed = 3.;
+        observed.humidity = 0.4;
+        return (prediction, observed);
+    }
+
+    #[test]
+    fn test_error_metrics() {
+        let pairs = vec![pair(1, 5., 4.), pair(1, 2., 3.), pair(3, 10., 6.), pair(3, 9., 6.), pair(60, 0., 100.)];
+
+        let results = calculate_error_metrics(&pairs);
+
+        assert_eq!(results.len(), 2, "only lead times with data (and in range)");
+
+        assert_eq!(results[0].hour_diff, 1);
+        assert_eq!(results[0].count, 2);
+        assert_eq!(results[0].temp, ErrorMetrics { mae: 1., rmse: 1., bias: 0. });
+        assert_eq!(results[0].wind_speed, ErrorMetrics { mae: 1., rmse: 1., bias: -1. });
+        assert_eq!(results[0].wind_gust, ErrorMetrics::default());
+
+        assert_eq!(results[1].hour_diff, 3);
+        assert_eq!(results[1].temp.mae, 3.5);
+        assert_eq!(results[1].temp.bias, 3.5);
+        assert!((results[1].temp.rmse - 12.5_f64.sqrt()).abs() < 1e-9, "rmse");
+        assert!((results[1].humidity.bias - 0.1).abs() < 1e-9, "humidity bias");
+    }
+
+    #[test]
+    fn test_empty_accumulator() {
+        let acc = ErrorAccumulator::default();
+
+        assert_eq!(acc.metrics(), ErrorMetrics::default());
+    }
+}
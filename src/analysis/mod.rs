@@ -0,0 +1,88 @@
+//! Verification of predictions against the readings for the hours they predicted
+
+use crate::models::{Prediction, Weather};
+use crate::Error;
+use serde::Serialize;
+use std::io::Write;
+
+pub mod error_metrics;
+pub mod calibration;
+pub mod confusion;
+pub mod skill;
+pub mod rain_events;
+pub mod convergence;
+
+/// Write `rows` to `out` as CSV, the header comes from the field names of the row type
+///
+/// # Errors
+/// Failed to write
+///
+pub(crate) fn write_csv<R: Serialize, W: Write>(rows: impl IntoIterator<Item = R>, out: W) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(out);
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    return Ok(());
+}
+
+/// A prediction paired with the reading that was observed for the hour it predicted
+pub type PredictionPair = (Prediction, Weather);
+
+/// Longest lead time (in hours) that predictions are stored for
+pub const MAX_HOUR_DIFF: u8 = 48;
+
+/// Default precipitation intensity (mm/h) above which an hour counts as rainy
+pub const DEFAULT_RAIN_THRESHOLD: f64 = 0.1;
+
+/// How to decide if it rained in an observed hour
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RainEvent {
+    /// Observed precipitation intensity (mm/h) is above this value
+    Intensity(f64),
+    /// Observed precipitation type is set (rain, snow or sleet)
+    PrecipType,
+}
+
+impl RainEvent {
+    /// True if it rained in the `observed` hour
+    pub fn occurred(&self, observed: &Weather) -> bool {
+        return match self {
+            RainEvent::Intensity(threshold) => observed.precip_intensity > *threshold,
+            RainEvent::PrecipType => observed.precip_type.is_some(),
+        };
+    }
+}
+
+impl Default for RainEvent {
+    fn default() -> Self {
+        return RainEvent::Intensity(DEFAULT_RAIN_THRESHOLD);
+    }
+}
+
+/// Predictions paired with readings for the tests of each analysis
+#[cfg(test)]
+pub(crate) mod test_pairs {
+    use super::PredictionPair;
+    use crate::models::{Prediction, Weather, Icon, SimpleDate};
+    use chrono::{NaiveDateTime, Duration};
+
+    /// Pair for the hour `hour_diff` hours after a reading at `reading` (year, day of year, hour)
+    ///
+    /// Every value is 0 and the icon is clear, set the values being tested on the returned prediction and reading
+    pub fn pair(reading: (u16, u16, u8), hour_diff: u8) -> PredictionPair {
+        let (year, day, hour) = reading;
+        let predicted = SimpleDate::from(NaiveDateTime::from(SimpleDate::new(year, day, hour)) + Duration::hours(hour_diff as i64));
+        let prediction = Prediction::new(year, day, hour, predicted.year, predicted.day, predicted.hour, hour_diff, Icon::Clear, 0., 0., 0., 0., 0., 0., None);
+        let observed = Weather::new(predicted.year, predicted.day, predicted.hour, Icon::Clear, 0., 0., 0., 0., 0., 0., None);
+        return (prediction, observed);
+    }
+
+    /// Pair with a predicted precipitation probability and an observed precipitation intensity
+    pub fn rain_pair(hour_diff: u8, probability: f64, observed_intensity: f64) -> PredictionPair {
+        let (mut prediction, mut observed) = pair((2019, 326, 0), hour_diff);
+        prediction.precip_probability = probability;
+        observed.precip_intensity = observed_intensity;
+        return (prediction, observed);
+    }
+}
@@ -0,0 +1,275 @@
+//! Verification of "will it rain this hour" as a yes/no forecast
+//!
+//! A prediction says yes if its precipitation probability is at or above a threshold, the observed answer comes from a [`RainEvent`].
+//! Contingency tables for each threshold give POD, FAR, CSI and ETS, and together make the ROC curve
+
+use crate::analysis::{PredictionPair, RainEvent, MAX_HOUR_DIFF, write_csv};
+use crate::Error;
+use serde::{Serialize, Deserialize};
+use std::io::Write;
+
+/// Number of probability thresholds (0%, 10%, ..., 100%)
+pub const THRESHOLD_COUNT: usize = 11;
+
+/// Default probability at or above which a prediction counts as saying it will rain
+pub const DEFAULT_PROBABILITY_THRESHOLD: f64 = 0.5;
+
+/// Probability for threshold `index` (0 to `THRESHOLD_COUNT - 1`)
+pub fn probability_threshold(index: usize) -> f64 {
+    return index as f64 / (THRESHOLD_COUNT - 1) as f64;
+}
+
+/// Counts of predicted yes/no against observed yes/no
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Default, PartialEq)]
+pub struct ContingencyTable {
+    /// Predicted rain and it rained
+    pub hits: usize,
+    /// Predicted no rain but it rained
+    pub misses: usize,
+    /// Predicted rain but it didn't
+    pub false_alarms: usize,
+    /// Predicted no rain and it didn't
+    pub correct_negatives: usize,
+}
+
+impl ContingencyTable {
+    /// Count one prediction
+    pub fn add(&mut self, predicted: bool, observed: bool) {
+        match (predicted, observed) {
+            (true, true) => self.hits += 1,
+            (false, true) => self.misses += 1,
+            (true, false) => self.false_alarms += 1,
+            (false, false) => self.correct_negatives += 1,
+        }
+    }
+
+    /// Number of predictions counted
+    pub fn total(&self) -> usize {
+        return self.hits + self.misses + self.false_alarms + self.correct_negatives;
+    }
+
+    /// Probability of detection, fraction of rainy hours that were predicted (1 is perfect)
+    pub fn pod(&self) -> Option<f64> {
+        return ratio(self.hits, self.hits + self.misses);
+    }
+
+    /// False alarm ratio, fraction of predicted rainy hours that were dry (0 is perfect)
+    pub fn far(&self) -> Option<f64> {
+        return ratio(self.false_alarms, self.hits + self.false_alarms);
+    }
+
+    /// Probability of false detection, fraction of dry hours that were predicted to be rainy (0 is perfect)
+    pub fn pofd(&self) -> Option<f64> {
+        return ratio(self.false_alarms, self.false_alarms + self.correct_negatives);
+    }
+
+    /// Critical success index, hits out of every hour that was predicted or observed to be rainy (1 is perfect)
+    pub fn csi(&self) -> Option<f64> {
+        return ratio(self.hits, self.hits + self.misses + self.false_alarms);
+    }
+
+    /// Equitable threat score, CSI adjusted for hits expected by chance (1 is perfect, 0 is no skill)
+    pub fn ets(&self) -> Option<f64> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let random_hits = (self.hits + self.misses) as f64 * (self.hits + self.false_alarms) as f64 / total as f64;
+        let denominator = (self.hits + self.misses + self.false_alarms) as f64 - random_hits;
+        if denominator == 0. {
+            return None;
+        }
+        return Some((self.hits as f64 - random_hits) / denominator);
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> Option<f64> {
+    if denominator == 0 {
+        return None;
+    }
+    return Some(numerator as f64 / denominator as f64);
+}
+
+/// Contingency tables for every probability threshold at a single lead time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LeadTimeRainEvents {
+    /// How many hours forward the predictions were made
+    pub hour_diff: u8,
+    /// Number of predictions that had a matching reading
+    pub count: usize,
+    /// Always has `THRESHOLD_COUNT` entries, table `i` is for `probability_threshold(i)`
+    pub tables: Vec<ContingencyTable>,
+}
+
+impl LeadTimeRainEvents {
+    /// Table for the threshold closest to `probability`
+    pub fn table_for(&self, probability: f64) -> &ContingencyTable {
+        let index = (probability * (THRESHOLD_COUNT - 1) as f64).round().max(0.) as usize;
+        return &self.tables[index.min(THRESHOLD_COUNT - 1)];
+    }
+
+    /// Points of the ROC curve as (POFD, POD), from (0, 0) to (1, 1)
+    ///
+    /// # Returns
+    /// Empty if it always or never rained, as one axis is undefined
+    ///
+    pub fn roc(&self) -> Vec<(f64, f64)> {
+        let mut points: Vec<(f64, f64)> = self.tables.iter()
+            .filter_map(|table| Some((table.pofd()?, table.pod()?)))
+            .collect();
+        if points.is_empty() {
+            return points;
+        }
+        points.push((0., 0.));
+        points.push((1., 1.));
+        points.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).expect("NaN in ROC"));
+        points.dedup();
+        return points;
+    }
+
+    /// Area under the ROC curve (trapezoidal), 1 is perfect and 0.5 is no better than guessing
+    pub fn auc(&self) -> Option<f64> {
+        let points = self.roc();
+        if points.is_empty() {
+            return None;
+        }
+        return Some(points.windows(2)
+            .map(|pair| (pair[1].0 - pair[0].0) * (pair[1].1 + pair[0].1) / 2.)
+            .sum());
+    }
+}
+
+/// Build contingency tables for each lead time (1 to `MAX_HOUR_DIFF`) and probability threshold
+///
+/// Pairs with a lead time outside of that range are ignored
+///
+/// # Returns
+/// One entry per lead time that has at least one pair, sorted by hour diff
+///
+pub fn calculate_rain_events(pairs: &[PredictionPair], event: RainEvent) -> Vec<LeadTimeRainEvents> {
+    let mut accumulators = vec![[ContingencyTable::default(); THRESHOLD_COUNT]; MAX_HOUR_DIFF as usize];
+
+    pairs.iter()
+        .filter(|(prediction, _)| prediction.hour_diff >= 1 && prediction.hour_diff <= MAX_HOUR_DIFF)
+        .for_each(|(prediction, observed)| {
+            let occurred = event.occurred(observed);
+            let tables = &mut accumulators[prediction.hour_diff as usize - 1];
+            for (i, table) in tables.iter_mut().enumerate() {
+                table.add(prediction.precip_probability >= probability_threshold(i), occurred);
+            }
+        });
+
+    return accumulators.iter()
+        .enumerate()
+        .filter(|(_, tables)| tables[0].total() > 0)
+        .map(|(i, tables)| LeadTimeRainEvents {
+            hour_diff: i as u8 + 1,
+            count: tables[0].total(),
+            tables: tables.to_vec(),
+        })
+        .collect();
+}
+
+/// CSV of contingency tables and scores, one row per lead time and probability threshold
+pub struct RainEventsCsv<'a>(pub &'a [LeadTimeRainEvents]);
+
+/// Scores that can't be calculated are left empty
+#[derive(Serialize)]
+struct RainEventsRow {
+    hour_diff: u8,
+    probability_threshold: f64,
+    hits: usize,
+    misses: usize,
+    false_alarms: usize,
+    correct_negatives: usize,
+    pod: Option<f64>,
+    far: Option<f64>,
+    pofd: Option<f64>,
+    csi: Option<f64>,
+    ets: Option<f64>,
+    auc: Option<f64>,
+}
+
+impl RainEventsCsv<'_> {
+    /// # Errors
+    /// Failed to write
+    ///
+    pub fn write<W: Write>(&self, out: W) -> Result<(), Error> {
+        let rows = self.0.iter().flat_map(|row| {
+            let auc = row.auc();
+            row.tables.iter().enumerate().map(move |(i, table)| RainEventsRow {
+                hour_diff: row.hour_diff,
+                probability_threshold: probability_threshold(i),
+                hits: table.hits,
+                misses: table.misses,
+                false_alarms: table.false_alarms,
+                correct_negatives: table.correct_negatives,
+                pod: table.pod(),
+                far: table.far(),
+                pofd: table.pofd(),
+                csi: table.csi(),
+                ets: table.ets(),
+                auc,
+            })
+        });
+        return write_csv(rows, out);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::analysis::test_pairs::rain_pair;
+
+    #[test]
+    fn test_scores() {
+        let table = ContingencyTable { hits: 50, misses: 20, false_alarms: 30, correct_negatives: 100 };
+
+        assert_eq!(table.pod(), Some(50. / 70.));
+        assert_eq!(table.far(), Some(30. / 80.));
+        assert_eq!(table.pofd(), Some(30. / 130.));
+        assert_eq!(table.csi(), Some(0.5));
+        assert!((table.ets().unwrap() - 22. / 72.).abs() < 1e-9, "random hits are 70 * 80 / 200 = 28");
+        assert_eq!(ContingencyTable::default().pod(), None);
+        assert_eq!(ContingencyTable::default().ets(), None);
+    }
+
+    #[test]
+    fn test_rain_events() {
+        let pairs = vec![rain_pair(1, 0.9, 1.), rain_pair(1, 0.6, 0.), rain_pair(1, 0.3, 1.), rain_pair(1, 0.1, 0.), rain_pair(2, 0.5, 0.)];
+
+        let results = calculate_rain_events(&pairs, RainEvent::Intensity(0.1));
+
+        assert_eq!(results.len(), 2);
+        let result = &results[0];
+        assert_eq!(result.count, 4);
+        assert_eq!(result.tables.len(), THRESHOLD_COUNT);
+        assert_eq!(result.tables[0], ContingencyTable { hits: 2, misses: 0, false_alarms: 2, correct_negatives: 0 }, "0% always says yes");
+        assert_eq!(*result.table_for(0.5), ContingencyTable { hits: 1, misses: 1, false_alarms: 1, correct_negatives: 1 });
+        assert_eq!(result.tables[10], ContingencyTable { hits: 0, misses: 2, false_alarms: 0, correct_negatives: 2 });
+
+        assert_eq!(result.roc(), vec![(0., 0.), (0., 0.5), (0.5, 0.5), (0.5, 1.), (1., 1.)]);
+        assert_eq!(result.auc(), Some(0.75));
+        assert_eq!(results[1].auc(), None, "never rained");
+    }
+
+    #[test]
+    fn test_perfect_auc() {
+        let pairs = vec![rain_pair(1, 1., 1.), rain_pair(1, 0., 0.)];
+
+        assert_eq!(calculate_rain_events(&pairs, RainEvent::Intensity(0.1))[0].auc(), Some(1.));
+    }
+
+    #[test]
+    fn test_csv() {
+        let results = calculate_rain_events(&[rain_pair(1, 0.9, 1.), rain_pair(1, 0.1, 0.)], RainEvent::Intensity(0.1));
+        let mut csv = vec![];
+        RainEventsCsv(&results).write(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+
+        assert_eq!(csv.lines().count(), THRESHOLD_COUNT + 1);
+        assert_eq!(csv.lines().next().unwrap(), "hour_diff,probability_threshold,hits,misses,false_alarms,correct_negatives,pod,far,pofd,csi,ets,auc");
+        assert_eq!(csv.lines().nth(6).unwrap(), "1,0.5,1,0,0,1,1.0,0.0,0.0,1.0,1.0,1.0");
+        assert_eq!(csv.lines().nth(11).unwrap(), "1,1.0,0,1,0,1,0.0,,0.0,0.0,0.0,1.0", "no false alarm rate without a predicted yes");
+    }
+}
@@ -0,0 +1,374 @@
+//! Skill scores of predictions against persistence and climatology baselines
+//!
+//! Skill is `1 - MSE_forecast / MSE_baseline`, 1 is a perfect forecast, 0 is no better than the baseline and below 0 is worse
+
+use crate::analysis::{PredictionPair, MAX_HOUR_DIFF};
+use crate::models::{Prediction, Weather, SimpleDate};
+use chrono::{NaiveDateTime, Datelike, Timelike};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fmt;
+
+/// Forecast that predictions are compared against
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Baseline {
+    /// Reading at the time the prediction was made, carried forward by the lead time
+    Persistence,
+    /// Mean reading for the same UTC calendar day (month and day of month) and hour in previous years
+    Climatology,
+}
+
+/// Values of every scored variable for one hour, from a reading or a baseline
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Default, PartialEq)]
+pub struct BaselineValues {
+    /// 'Feels like' temperature in celsius
+    pub temp: f64,
+    /// Average wind speed in meters per second
+    pub wind_speed: f64,
+    /// Wind gust speed in meters per second
+    pub wind_gust: f64,
+    /// Relative humidity (between 0 and 1)
+    pub humidity: f64,
+    /// Precipitation in millimeters per hour
+    pub precip_intensity: f64,
+}
+
+impl From<&Weather> for BaselineValues {
+    fn from(weather: &Weather) -> Self {
+        return BaselineValues {
+            temp: weather.temp,
+            wind_speed: weather.wind_speed,
+            wind_gust: weather.wind_gust,
+            humidity: weather.humidity,
+            precip_intensity: weather.precip_intensity,
+        };
+    }
+}
+
+/// Persistence and climatology forecasts built from every reading for a location
+#[derive(Debug, Default)]
+pub struct Baselines {
+    /// Reading for each hour, by unix timestamp
+    readings: HashMap<i64, BaselineValues>,
+    /// Year and reading for each UTC (month, day of month, hour)
+    ///
+    /// Day of year isn't used as it's one later after February 29th in leap years
+    by_hour_of_year: HashMap<(u32, u32, u32), Vec<(i32, BaselineValues)>>,
+}
+
+impl Baselines {
+    /// Index `readings` by hour and by calendar hour
+    pub fn new(readings: &[Weather]) -> Baselines {
+        let mut baselines = Baselines::default();
+        for weather in readings {
+            let values = BaselineValues::from(weather);
+            baselines.readings.insert(weather.timestamp, values);
+            let date = weather.date();
+            baselines.by_hour_of_year.entry(calendar_hour(&date)).or_default().push((date.year(), values));
+        }
+        return baselines;
+    }
+
+    /// Baseline forecast for the hour `prediction` is for
+    ///
+    /// # Returns
+    /// `None` if there's no reading for when the prediction was made (persistence) or no readings in earlier years (climatology)
+    ///
+    pub fn forecast(&self, baseline: Baseline, prediction: &Prediction) -> Option<BaselineValues> {
+        return match baseline {
+            Baseline::Persistence => self.readings.get(&prediction.reading_timestamp).copied(),
+            Baseline::Climatology => self.climatology(&SimpleDate::new(prediction.prediction_year, prediction.prediction_day, prediction.prediction_hour).into())
+        };
+    }
+
+    /// Mean of readings for the calendar day and hour of `date` in earlier years
+    fn climatology(&self, date: &NaiveDateTime) -> Option<BaselineValues> {
+        let previous: Vec<&BaselineValues> = self.by_hour_of_year.get(&calendar_hour(date))?
+            .iter()
+            .filter(|(reading_year, _)| *reading_year < date.year())
+            .map(|(_, values)| values)
+            .collect();
+        if previous.is_empty() {
+            return None;
+        }
+        let count = previous.len() as f64;
+        let mean = |getter: fn(&BaselineValues) -> f64| previous.iter().map(|values| getter(values)).sum::<f64>() / count;
+        return Some(BaselineValues {
+            temp: mean(|values| values.temp),
+            wind_speed: mean(|values| values.wind_speed),
+            wind_gust: mean(|values| values.wind_gust),
+            humidity: mean(|values| values.humidity),
+            precip_intensity: mean(|values| values.precip_intensity),
+        });
+    }
+}
+
+/// Month, day of month and hour of `date`
+fn calendar_hour(date: &NaiveDateTime) -> (u32, u32, u32) {
+    return (date.month(), date.day(), date.hour());
+}
+
+/// Mean squared error of predictions and a baseline over the same hours, for one variable
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Default, PartialEq)]
+pub struct Skill {
+    /// Number of predictions that had a baseline forecast
+    pub count: usize,
+    /// Mean squared error of the predictions
+    pub mse: f64,
+    /// Mean squared error of the baseline for the same hours
+    pub baseline_mse: f64,
+}
+
+impl Skill {
+    /// `1 - mse / baseline_mse`
+    ///
+    /// # Returns
+    /// `None` if there's no data or the baseline was perfect
+    ///
+    pub fn score(&self) -> Option<f64> {
+        if self.count == 0 || self.baseline_mse == 0. {
+            return None;
+        }
+        return Some(1. - self.mse / self.baseline_mse);
+    }
+}
+
+/// Skill of every variable against one baseline
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Default, PartialEq)]
+pub struct VariableSkill {
+    /// 'Feels like' temperature in celsius
+    pub temp: Skill,
+    /// Average wind speed in meters per second
+    pub wind_speed: Skill,
+    /// Wind gust speed in meters per second
+    pub wind_gust: Skill,
+    /// Relative humidity (between 0 and 1)
+    pub humidity: Skill,
+    /// Precipitation in millimeters per hour
+    pub precip_intensity: Skill,
+}
+
+/// Skill against both baselines at a single lead time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LeadTimeSkill {
+    /// How many hours forward the predictions were made
+    pub hour_diff: u8,
+    /// Number of predictions that had a matching reading
+    pub count: usize,
+    /// Skill against [`Baseline::Persistence`]
+    pub persistence: VariableSkill,
+    /// Skill against [`Baseline::Climatology`]
+    pub climatology: VariableSkill,
+}
+
+impl LeadTimeSkill {
+    /// Skill against `baseline`
+    pub fn against(&self, baseline: Baseline) -> &VariableSkill {
+        return match baseline {
+            Baseline::Persistence => &self.persistence,
+            Baseline::Climatology => &self.climatology,
+        };
+    }
+}
+
+/// Running totals used to build `Skill`
+#[derive(Debug, Default, Copy, Clone)]
+struct SkillAccumulator {
+    count: usize,
+    sum_sq: f64,
+    baseline_sum_sq: f64,
+}
+
+impl SkillAccumulator {
+    fn add(&mut self, predicted: f64, baseline: f64, observed: f64) {
+        self.count += 1;
+        self.sum_sq += (predicted - observed).powi(2);
+        self.baseline_sum_sq += (baseline - observed).powi(2);
+    }
+
+    fn skill(&self) -> Skill {
+        if self.count == 0 {
+            return Skill::default();
+        }
+        let count = self.count as f64;
+        return Skill {
+            count: self.count,
+            mse: self.sum_sq / count,
+            baseline_mse: self.baseline_sum_sq / count,
+        };
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct VariableAccumulator {
+    temp: SkillAccumulator,
+    wind_speed: SkillAccumulator,
+    wind_gust: SkillAccumulator,
+    humidity: SkillAccumulator,
+    precip_intensity: SkillAccumulator,
+}
+
+impl VariableAccumulator {
+    fn add(&mut self, prediction: &Prediction, baseline: &BaselineValues, observed: &Weather) {
+        self.temp.add(prediction.temp, baseline.temp, observed.temp);
+        self.wind_speed.add(prediction.wind_speed, baseline.wind_speed, observed.wind_speed);
+        self.wind_gust.add(prediction.wind_gust, baseline.wind_gust, observed.wind_gust);
+        self.humidity.add(prediction.humidity, baseline.humidity, observed.humidity);
+        self.precip_intensity.add(prediction.precip_intensity, baseline.precip_intensity, observed.precip_intensity);
+    }
+
+    fn skill(&self) -> VariableSkill {
+        return VariableSkill {
+            temp: self.temp.skill(),
+            wind_speed: self.wind_speed.skill(),
+            wind_gust: self.wind_gust.skill(),
+            humidity: self.humidity.skill(),
+            precip_intensity: self.precip_intensity.skill(),
+        };
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct LeadTimeAccumulator {
+    count: usize,
+    persistence: VariableAccumulator,
+    climatology: VariableAccumulator,
+}
+
+/// Calculate skill against persistence and climatology for each lead time (1 to `MAX_HOUR_DIFF`)
+///
+/// Each baseline only uses the pairs it has a forecast for, so the counts may differ
+/// Pairs with a lead time outside of that range are ignored
+///
+/// # Returns
+/// One entry per lead time that has at least one pair, sorted by hour diff
+///
+pub fn calculate_skill_scores(pairs: &[PredictionPair], baselines: &Baselines) -> Vec<LeadTimeSkill> {
+    let mut accumulators = vec![LeadTimeAccumulator::default(); MAX_HOUR_DIFF as usize];
+
+    pairs.iter()
+        .filter(|(prediction, _)| prediction.hour_diff >= 1 && prediction.hour_diff <= MAX_HOUR_DIFF)
+        .for_each(|(prediction, observed)| {
+            let acc = &mut accumulators[prediction.hour_diff as usize - 1];
+            acc.count += 1;
+            if let Some(baseline) = baselines.forecast(Baseline::Persistence, prediction) {
+                acc.persistence.add(prediction, &baseline, observed);
+            }
+            if let Some(baseline) = baselines.forecast(Baseline::Climatology, prediction) {
+                acc.climatology.add(prediction, &baseline, observed);
+            }
+        });
+
+    return accumulators.into_iter()
+        .enumerate()
+        .filter(|(_, acc)| acc.count > 0)
+        .map(|(i, acc)| LeadTimeSkill {
+            hour_diff: i as u8 + 1,
+            count: acc.count,
+            persistence: acc.persistence.skill(),
+            climatology: acc.climatology.skill(),
+        })
+        .collect();
+}
+
+/// Plain text table of skill scores, one row per lead time
+pub struct SkillTable<'a>(pub &'a [LeadTimeSkill]);
+
+impl Display for SkillTable<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{: <6}{: <8}{: <14}{: <14}{: <14}{: <14}{: <14}", "Hours", "Count", "Temp", "Wnd Spd", "Wnd Gst", "Humidity", "P. Amt")?;
+        writeln!(f, "{: <14}{}", "", "Pers  Clim    ".repeat(5))?;
+        for row in self.0 {
+            write!(f, "{: <6}{: <8}", row.hour_diff, row.count)?;
+            let columns = |skill: &VariableSkill| [skill.temp, skill.wind_speed, skill.wind_gust, skill.humidity, skill.precip_intensity];
+            for (persistence, climatology) in columns(&row.persistence).iter().zip(columns(&row.climatology).iter()) {
+                write!(f, "{}{}  ", format_score(persistence), format_score(climatology))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn format_score(skill: &Skill) -> String {
+    return match skill.score() {
+        Some(score) => format!("{: <+6.2}", score),
+        None => format!("{: <6}", "-")
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Icon;
+
+    use crate::analysis::test_pairs;
+
+    fn reading(year: u16, day: u16, hour: u8, temp: f64) -> Weather {
+        return Weather::new(year, day, hour, Icon::Clear, 0., 0., temp, 0., 0., 0., None);
+    }
+
+    fn pair(hour_diff: u8, predicted_temp: f64, observed_temp: f64) -> PredictionPair {
+        let (mut prediction, mut observed) = test_pairs::pair((2020, 100, 0), hour_diff);
+        prediction.temp = predicted_temp;
+        observed.temp = observed_temp;
+        return (prediction, observed);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let baselines = Baselines::new(&[reading(2020, 100, 0, 10.)]);
+        let pairs = vec![pair(1, 11., 12.), pair(2, 14., 12.), pair(3, 12., 12.)];
+
+        let results = calculate_skill_scores(&pairs, &baselines);
+
+        assert_eq!(results.len(), 3);
+        let skill = results[0].persistence.temp;
+        assert_eq!((skill.count, skill.mse, skill.baseline_mse), (1, 1., 4.));
+        assert_eq!(skill.score(), Some(0.75));
+        assert_eq!(results[1].persistence.temp.score(), Some(0.), "as far off as the baseline");
+        assert_eq!(results[2].persistence.temp.score(), Some(1.), "perfect");
+        assert_eq!(results[0].persistence.wind_speed.score(), None, "baseline was perfect");
+    }
+
+    #[test]
+    fn test_climatology() {
+        // Day 100 of 2020 is April 9th, which is day 99 in other years
+        let readings = vec![reading(2018, 99, 1, 5.), reading(2019, 99, 1, 7.), reading(2019, 100, 1, 100.), reading(2020, 100, 1, 100.), reading(2021, 99, 1, 100.)];
+        let baselines = Baselines::new(&readings);
+        let pairs = vec![pair(1, 9., 8.), pair(2, 9., 8.)];
+
+        let results = calculate_skill_scores(&pairs, &baselines);
+
+        let skill = results[0].climatology.temp;
+        assert_eq!((skill.count, skill.mse, skill.baseline_mse), (1, 1., 4.), "mean of 2018 and 2019 only");
+        assert_eq!(results[0].persistence.temp.count, 0, "no reading when the prediction was made");
+        assert_eq!(results[1].count, 1);
+        assert_eq!(results[1].climatology.temp.count, 0, "no earlier years for that hour");
+        assert_eq!(results[1].climatology.temp.score(), None);
+    }
+
+    #[test]
+    fn test_climatology_in_leap_years() {
+        // March 1st is day 60 in 2019 and 2023, day 61 in 2020, February 29th is day 60 in 2016 and 2020
+        let readings = vec![reading(2016, 60, 12, 1.), reading(2019, 60, 12, 5.), reading(2019, 61, 12, 50.), reading(2023, 60, 12, 100.)];
+        let baselines = Baselines::new(&readings);
+        let forecast = |year: u16, day: u16| {
+            let prediction = Prediction::new(year, day, 11, year, day, 12, 1, Icon::Clear, 0., 0., 0., 0., 0., 0., None);
+            return baselines.forecast(Baseline::Climatology, &prediction).map(|values| values.temp);
+        };
+
+        assert_eq!(forecast(2020, 61), Some(5.), "March 1st of a leap year uses March 1st of earlier years");
+        assert_eq!(forecast(2020, 60), Some(1.), "February 29th only uses earlier leap years");
+        assert_eq!(forecast(2024, 61), Some(52.5), "March 1st of 2019 and 2023");
+    }
+
+    #[test]
+    fn test_table() {
+        let baselines = Baselines::new(&[reading(2020, 100, 0, 10.)]);
+        let table = SkillTable(&calculate_skill_scores(&[pair(1, 11., 12.)], &baselines)).to_string();
+
+        assert_eq!(table.lines().nth(2).unwrap(), "1     1       +0.75 -       -     -       -     -       -     -       -     -       ");
+    }
+}
@@ -1,46 +1,198 @@
+//! [`WeatherApp`], date based queries and imports for the selected location
+
 use crate::db_manager::DbManager;
 use crate::Error;
-use std::path::PathBuf;
-use std::fs;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::time::UNIX_EPOCH;
+use sha2::{Digest, Sha256};
 use log::{error, trace};
-use crate::templates::DarkSkyReading;
-use crate::models::{Weather, Prediction, SimpleDate};
-use chrono::NaiveDateTime;
+use crate::templates::{Importer, ImportedWeather, importer_named, detect_importer};
+use crate::models::{Weather, Prediction, SimpleDate, PredictionDiff, Location, Coordinates};
+use chrono::{NaiveDate, NaiveDateTime};
+use crate::calendar::Calendar;
 use crate::extensions::Utils;
+use crate::analysis::error_metrics::{LeadTimeErrors, calculate_error_metrics};
+use crate::analysis::calibration::{LeadTimeCalibration, calculate_calibration};
+use crate::analysis::confusion::{ConfusionMatrix, calculate_confusion_matrix};
+use crate::analysis::skill::{LeadTimeSkill, Baselines, calculate_skill_scores};
+use crate::analysis::rain_events::{LeadTimeRainEvents, calculate_rain_events};
+use crate::analysis::convergence::{Convergence, calculate_all_convergence};
+use crate::analysis::RainEvent;
+use crate::import::{ImportOptions, ImportReport, ImportCounts, FileOutcome, IngestedFile, ImportedFile, Payload, lead_time};
+use crate::import::source::{for_each_source, read_json_file, PathFilter, Source};
+use rayon::prelude::*;
+use crate::export::{ExportKind, ExportFormat, ReadingRow, PredictionRow, JoinedRow, write_rows};
+use std::io::Write;
+
+/// Number of files parsed and written to the database together when importing
+pub const IMPORT_BATCH_SIZE: usize = 256;
 
+/// Reads, imports and analyses weather data for one location at a time
 pub struct WeatherApp {
-    db_manager: DbManager
+    db_manager: DbManager,
+    /// Location all readings and predictions are for, if `None` then the first location is used
+    location_id: Option<i64>,
+    /// Split days at midnight in the location's time zone rather than UTC
+    local_time: bool,
 }
 
 impl WeatherApp {
+    /// `db_manager` must already be initialised (see `DbManager::init`)
     pub fn new(db_manager: DbManager) -> WeatherApp {
         return WeatherApp {
-            db_manager
+            db_manager,
+            location_id: None,
+            local_time: false,
         };
     }
 }
 
 impl WeatherApp {
+    /// Get all locations
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    pub fn get_locations(&mut self) -> Result<Vec<Location>, Error> {
+        return self.db_manager.get_locations();
+    }
+
+    /// Get the currently selected location
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Selected location, or the first location if none selected
+    /// `None` if there are no locations
+    ///
+    pub fn get_location(&mut self) -> Result<Option<Location>, Error> {
+        let locations = self.get_locations()?;
+        return Ok(match self.location_id {
+            Some(id) => locations.into_iter().find(|location| location.id == id),
+            None => locations.into_iter().next()
+        });
+    }
+
+    /// Find location by id or name
+    ///
+    /// # Errors
+    /// Database errors
+    /// No location has that id or name
+    ///
+    pub fn find_location(&mut self, id_or_name: &str) -> Result<Location, Error> {
+        let id = id_or_name.parse::<i64>().ok();
+        return self.get_locations()?
+            .into_iter()
+            .find(|location| Some(location.id) == id || location.name == id_or_name)
+            .ok_or_else(|| Error::from(format!("No location with id or name '{}'", id_or_name)));
+    }
+
+    /// Select the location used for all readings and predictions
+    pub fn set_location(&mut self, location_id: i64) {
+        self.location_id = Some(location_id);
+    }
+
+    /// # Errors
+    /// Database errors
+    ///
+    pub fn rename_location(&mut self, location_id: i64, name: &str) -> Result<(), Error> {
+        return self.db_manager.rename_location(location_id, name);
+    }
+
+    /// Use the selected location's time zone (true) or UTC (false) for days
+    pub fn set_local_time(&mut self, local_time: bool) {
+        self.local_time = local_time;
+    }
+
+    /// True if days are in the location's timezone rather than UTC
+    pub fn is_local_time(&self) -> bool {
+        return self.local_time;
+    }
+
+    /// Calendar used for days, see `set_local_time`
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// UTC if not using local time or the location has no time zone
+    ///
+    pub fn calendar(&mut self) -> Result<Calendar, Error> {
+        if !self.local_time {
+            return Ok(Calendar::Utc);
+        }
+        return Ok(Calendar::for_location(self.get_location()?.as_ref()));
+    }
+
+    /// ID of selected location, or 0 (which is never used) if there are no locations
+    fn location_id(&mut self) -> Result<i64, Error> {
+        return Ok(self.get_location()?.map(|location| location.id).unwrap_or(0));
+    }
+}
+
+impl WeatherApp {
+    /// Get the reading for an hour
+    ///
+    /// # Errors
+    /// Database errors
+    /// No reading for hour
+    ///
     pub fn get_reading(&mut self, year: u16, day: u16, hour: u8) -> Result<Weather, Error> {
-        return self.db_manager.get_specific_reading(year, day, hour)
-            .map_err(|err| err.into());
+        let location_id = self.location_id()?;
+        return self.db_manager.get_specific_reading(location_id, year, day, hour);
     }
 
+    /// Get the reading for an hour and every prediction made for that hour
+    ///
+    /// # Errors
+    /// Database errors
+    /// No reading for hour
+    ///
+    /// # Returns
+    /// Reading and list of predictions (sorted by hour diff)
+    ///
     pub fn get_reading_with_predictions(&mut self, year: u16, day: u16, hour: u8) -> Result<(Weather, Vec<Prediction>), Error> {
-        let weather = self.db_manager.get_specific_reading(year, day, hour)
-            .map_err(|err| err.into());
-        let predictions = self.db_manager.get_predictions_for(year, day, hour)
-            .map_err(|err| err.into());
-
-        return if let Ok(weather) = weather {
-            if let Ok(predictions) = predictions {
-                Ok((weather, predictions))
-            } else {
-                Err(predictions.unwrap_err())
-            }
-        } else {
-            Err(weather.unwrap_err())
-        }
+        let location_id = self.location_id()?;
+        let weather = self.db_manager.get_specific_reading(location_id, year, day, hour)?;
+        let predictions = self.db_manager.get_predictions_for(location_id, year, day, hour)?;
+
+        return Ok((weather, predictions));
+    }
+
+    /// Get the reading for an hour and how far off each prediction for that hour was
+    ///
+    /// # Errors
+    /// Database errors
+    /// No reading for hour
+    ///
+    /// # Returns
+    /// Reading and list of prediction differences (sorted by hour diff)
+    ///
+    pub fn get_reading_with_prediction_diffs(&mut self, year: u16, day: u16, hour: u8) -> Result<(Weather, Vec<PredictionDiff>), Error> {
+        let (weather, predictions) = self.get_reading_with_predictions(year, day, hour)?;
+
+        let diffs = predictions.iter()
+            .map(|prediction| prediction.diff(&weather))
+            .collect();
+
+        return Ok((weather, diffs));
+    }
+
+    /// Get how the forecast of each variable for an hour changed as it was reissued, ending at the reading
+    ///
+    /// # Errors
+    /// Database errors
+    /// No reading for hour
+    ///
+    /// # Returns
+    /// Convergence of every variable, using its default tolerance
+    ///
+    pub fn get_convergence(&mut self, year: u16, day: u16, hour: u8) -> Result<Vec<Convergence>, Error> {
+        let (weather, predictions) = self.get_reading_with_predictions(year, day, hour)?;
+
+        return Ok(calculate_all_convergence(&weather, &predictions));
     }
 
     /// Get hourly reads from start to end (inclusive, inclusive)
@@ -53,40 +205,241 @@ impl WeatherApp {
     /// List of readings
     ///
     pub fn get_readings_over_range(&mut self, start: SimpleDate, end: SimpleDate) -> Result<Vec<Weather>, Error> {
-        let start = Into::<NaiveDateTime>::into(start).timestamp();
-        let end = Into::<NaiveDateTime>::into(end).timestamp();
-        return self.db_manager.get_readings_over_range(start, end)
-            .map_err(|err| err.into());
+        let location_id = self.location_id()?;
+        return self.db_manager.get_readings_over_range(location_id, start.timestamp(), end.timestamp());
+    }
+
+    /// First and last hour of the days `first` to `last` (inclusive, inclusive) in the current calendar
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    pub fn day_range(&mut self, first: NaiveDate, last: NaiveDate) -> Result<(SimpleDate, SimpleDate), Error> {
+        return Ok(self.calendar()?.day_range(first, last));
+    }
+
+    /// Get every hour of `date` in the current calendar and the reading for it
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Local time and reading (if there is one) for each hour, 23 or 25 hours on daylight saving changes
+    ///
+    pub fn get_readings_for_day(&mut self, date: NaiveDate) -> Result<Vec<(NaiveDateTime, Option<Weather>)>, Error> {
+        let calendar = self.calendar()?;
+        let (start, end) = calendar.day_range(date, date);
+        let mut readings = self.get_readings_over_range(start, end)?.into_iter().peekable();
+
+        let hours = calendar.hours_of_day(date).into_iter()
+            .map(|timestamp| (calendar.local_time(timestamp), readings.next_if(|weather| weather.timestamp == timestamp)))
+            .collect();
+
+        return Ok(hours);
+    }
+
+    /// Get readings for each day from `first` to `last` (inclusive, inclusive) in the current calendar
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Every day in the range with its readings (oldest to newest), days without readings have an empty list
+    ///
+    pub fn get_readings_by_day(&mut self, first: NaiveDate, last: NaiveDate) -> Result<Vec<(NaiveDate, Vec<Weather>)>, Error> {
+        let calendar = self.calendar()?;
+        let (start, end) = calendar.day_range(first, last);
+        let mut days: Vec<(NaiveDate, Vec<Weather>)> = first.iter_days()
+            .take_while(|date| *date <= last)
+            .map(|date| (date, vec![]))
+            .collect();
+
+        for weather in self.get_readings_over_range(start, end)? {
+            let index = (calendar.date_of(weather.timestamp) - first).num_days() as usize;
+            days[index].1.push(weather);
+        }
+
+        return Ok(days);
+    }
+
+    /// Get predictions for hours from start to end (inclusive, inclusive)
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// List of predictions, sorted by predicted hour then hour diff
+    ///
+    pub fn get_predictions_over_range(&mut self, start: SimpleDate, end: SimpleDate) -> Result<Vec<Prediction>, Error> {
+        let location_id = self.location_id()?;
+        return self.db_manager.get_predictions_over_range(location_id, start.timestamp(), end.timestamp());
+    }
+
+    /// Get predictions for hours from start to end (inclusive, inclusive) with the reading for the predicted hour
+    ///
+    /// Predictions for hours without a reading are skipped
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// List of predictions and readings, sorted by predicted hour then hour diff
+    ///
+    pub fn get_predictions_with_readings(&mut self, start: SimpleDate, end: SimpleDate) -> Result<Vec<(Prediction, Weather)>, Error> {
+        let location_id = self.location_id()?;
+        return self.db_manager.get_predictions_with_readings(location_id, start.timestamp(), end.timestamp());
+    }
+
+    /// Get MAE, RMSE and bias of predictions for each lead time
+    ///
+    /// Only predictions for hours from start to end (inclusive, inclusive) that have a reading are used
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Error metrics for each lead time that has data, sorted by hour diff
+    ///
+    pub fn get_error_metrics(&mut self, start: SimpleDate, end: SimpleDate) -> Result<Vec<LeadTimeErrors>, Error> {
+        let pairs = self.get_predictions_with_readings(start, end)?;
+
+        return Ok(calculate_error_metrics(&pairs));
+    }
+
+    /// Get Brier score and reliability table of precipitation probability for each lead time
+    ///
+    /// Only predictions for hours from start to end (inclusive, inclusive) that have a reading are used
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Calibration for each lead time that has data, sorted by hour diff
+    ///
+    pub fn get_calibration(&mut self, start: SimpleDate, end: SimpleDate, event: RainEvent) -> Result<Vec<LeadTimeCalibration>, Error> {
+        let pairs = self.get_predictions_with_readings(start, end)?;
+
+        return Ok(calculate_calibration(&pairs, event));
+    }
+
+    /// Get contingency tables of rain/no rain for each lead time and precipitation probability threshold
+    ///
+    /// Only predictions for hours from start to end (inclusive, inclusive) that have a reading are used
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Tables for each lead time that has data, sorted by hour diff
+    ///
+    pub fn get_rain_events(&mut self, start: SimpleDate, end: SimpleDate, event: RainEvent) -> Result<Vec<LeadTimeRainEvents>, Error> {
+        let pairs = self.get_predictions_with_readings(start, end)?;
+
+        return Ok(calculate_rain_events(&pairs, event));
+    }
+
+    /// Get skill of predictions against persistence and climatology for each lead time
+    ///
+    /// Only predictions for hours from start to end (inclusive, inclusive) that have a reading are scored,
+    /// baselines are built from every reading for the location
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Skill for each lead time that has data, sorted by hour diff
+    ///
+    pub fn get_skill_scores(&mut self, start: SimpleDate, end: SimpleDate) -> Result<Vec<LeadTimeSkill>, Error> {
+        let pairs = self.get_predictions_with_readings(start, end)?;
+        let location_id = self.location_id()?;
+        let baselines = Baselines::new(&self.db_manager.get_all_readings(location_id)?);
+
+        return Ok(calculate_skill_scores(&pairs, &baselines));
+    }
+
+    /// Get confusion matrix of predicted icon against observed icon
+    ///
+    /// Only predictions for hours from start to end (inclusive, inclusive) that have a reading
+    /// and were made between `min_hour_diff` and `max_hour_diff` (inclusive, inclusive) hours before are used
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    pub fn get_confusion_matrix(&mut self, start: SimpleDate, end: SimpleDate, min_hour_diff: u8, max_hour_diff: u8) -> Result<ConfusionMatrix, Error> {
+        let pairs = self.get_predictions_with_readings(start, end)?;
+
+        return Ok(calculate_confusion_matrix(&pairs, min_hour_diff, max_hour_diff));
+    }
+
+    /// Write readings, predictions or both for hours from start to end (inclusive, inclusive) to `out`
+    ///
+    /// # Errors
+    /// Database errors
+    /// Failed to write
+    ///
+    /// # Returns
+    /// Number of rows written
+    ///
+    pub fn export<W: Write + Send>(&mut self, kind: ExportKind, format: ExportFormat, start: SimpleDate, end: SimpleDate, out: W) -> Result<usize, Error> {
+        let location_id = self.location_id()?;
+        return match kind {
+            ExportKind::Readings => {
+                let rows: Vec<ReadingRow> = self.get_readings_over_range(start, end)?
+                    .iter()
+                    .map(|weather| ReadingRow::new(location_id, weather))
+                    .collect();
+                write_rows(&rows, format, out)?;
+                Ok(rows.len())
+            }
+            ExportKind::Predictions => {
+                let rows: Vec<PredictionRow> = self.get_predictions_over_range(start, end)?
+                    .iter()
+                    .map(|prediction| PredictionRow::new(location_id, prediction))
+                    .collect();
+                write_rows(&rows, format, out)?;
+                Ok(rows.len())
+            }
+            ExportKind::Joined => {
+                let rows: Vec<JoinedRow> = self.get_predictions_with_readings(start, end)?
+                    .iter()
+                    .map(|(prediction, observed)| JoinedRow::new(location_id, prediction, observed))
+                    .collect();
+                write_rows(&rows, format, out)?;
+                Ok(rows.len())
+            }
+        };
     }
 
     /// Get the first reading
     ///
     /// # Errors
     /// Database errors
-    /// No readings in database
+    /// No readings in database (`Error::EmptyDatabase`)
     ///
     /// # Returns
     /// First reading (current, not predication)
     ///
     pub fn get_first_reading(&mut self) -> Result<Weather, Error> {
-        return self.db_manager.get_readings("ASC", 1)
-            .map(|mut list| list.pop().expect("No readings"))
-            .map_err(|err| err.into());
+        let location_id = self.location_id()?;
+        return self.db_manager.get_readings(location_id, "ASC", 1)?
+            .pop()
+            .ok_or(Error::EmptyDatabase);
     }
 
     /// Get the last reading
     ///
     /// # Errors
     /// Database errors
-    /// No readings in database
+    /// No readings in database (`Error::EmptyDatabase`)
     ///
     /// # Returns
     /// Last reading (current, not predication)
     ///
     pub fn get_last_reading(&mut self) -> Result<Weather, Error> {
-        return self.db_manager.get_readings("DESC", 1)
-            .map(|mut list| list.pop().expect("No readings"))
-            .map_err(|err| err.into())
+        let location_id = self.location_id()?;
+        return self.db_manager.get_readings(location_id, "DESC", 1)?
+            .pop()
+            .ok_or(Error::EmptyDatabase)
     }
 
     /// Gets the first and last record and returns a list of all missing hour slot between them
@@ -99,7 +452,8 @@ impl WeatherApp {
     /// Will be empty if no missing slots
     ///
     pub fn check_for_missing_data(&mut self) -> Result<Vec<NaiveDateTime>, Error> {
-        let readings = self.db_manager.get_all_readings()?;
+        let location_id = self.location_id()?;
+        let readings = self.db_manager.get_all_readings(location_id)?;
 
         let mut date_times = readings.into_iter()
             .map(|weather|  weather.date())
@@ -116,11 +470,11 @@ impl WeatherApp {
         let start = date_times.first().expect("No first date time");
         let end = date_times.last().expect("No last date time");
         let mut results = vec![];
-        let mut current = start.clone();
+        let mut current = *start;
 
         while &current < end {
             if !date_times.contains(&current) {
-                results.push(current.clone())
+                results.push(current)
             }
             current = current.plus_one_hour();
         }
@@ -128,89 +482,517 @@ impl WeatherApp {
         Ok(results)
     }
 
-    /// Import all json files from a directory
+    /// Hours with no reading for a location
+    ///
+    /// Hours before the location's first reading aren't counted as missing
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Each missing hour from `start` up to (not including) `end`, oldest first
+    ///
+    pub fn get_missing_hours(&mut self, location_id: i64, start: NaiveDateTime, end: NaiveDateTime) -> Result<Vec<NaiveDateTime>, Error> {
+        let readings = self.db_manager.get_all_readings(location_id)?
+            .iter()
+            .map(|weather| weather.date())
+            .collect::<HashSet<NaiveDateTime>>();
+
+        let mut current = match readings.iter().min() {
+            Some(first) => start.max(*first),
+            None => return Ok(vec![])
+        };
+        let mut results = vec![];
+        while current < end {
+            if !readings.contains(&current) {
+                results.push(current);
+            }
+            current = current.plus_one_hour();
+        }
+
+        return Ok(results);
+    }
+
+    /// Find the location with `coordinates`, adding it if there isn't one
+    ///
+    /// # Errors
+    /// Database errors
+    ///
+    /// # Returns
+    /// Id of the location
+    ///
+    pub fn add_location(&mut self, coordinates: &Coordinates) -> Result<i64, Error> {
+        return self.db_manager.find_or_add_location(coordinates);
+    }
+
+    /// Import all json documents from a directory
+    ///
+    /// Files that fail to import are rejected and listed in the report, the rest are still imported
+    /// See `ImportOptions` for format, location, dry run, recursion and globs,
+    /// and `for_each_source` for supported files and archives
     ///
     /// # Errors
     /// Directory is inaccessible
     /// Path is not a directory
+    /// Unknown format
+    /// Invalid glob
+    /// Failed to write a batch to the database
+    ///
+    /// # Returns
+    /// Outcome of each file (and archive entry)
+    ///
+    pub fn import_data(&mut self, dir: String, options: &ImportOptions) -> Result<ImportReport, Error> {
+        return self.import_data_with_progress(dir, options, |_| {});
+    }
+
+    /// Import a single json file (or `.json.gz`), see `import_data`
+    ///
+    /// # Errors
+    /// File metadata is inaccessible
+    /// Unknown format
+    /// Failed to write to the database
+    ///
+    /// # Returns
+    /// Outcome of the file
+    ///
+    pub fn import_data_from_file(&mut self, path: &Path, options: &ImportOptions) -> Result<ImportReport, Error> {
+        let importer = match &options.format {
+            Some(name) => Some(importer_named(name)?),
+            None => None
+        };
+        let path = path.canonicalize()?;
+        let ingested = if options.force {
+            HashMap::new()
+        } else {
+            self.db_manager.get_ingested_files()?
+        };
+        let metadata = std::fs::metadata(&path)?;
+        let modified = metadata.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs() as i64);
+
+        let mut report = ImportReport::new(options.dry_run);
+        if ingested.get(&path).is_some_and(|file| file.matches(metadata.len(), modified)) {
+            report.files.push((path, FileOutcome::Skipped));
+        } else {
+            let text = read_json_file(&path);
+            let source = Source { path, size: metadata.len(), modified, text };
+            self.import_batch(vec![source], importer.as_deref(), &ingested, options, &mut report)?;
+        }
+
+        return Ok(report);
+    }
+
+    /// Same as `import_data`, calling `progress` with the report so far after each batch of files
+    ///
+    /// Files are read in batches of `IMPORT_BATCH_SIZE`, each batch is parsed on a thread pool
+    /// then written to the database in a single transaction
+    /// Unless `force` is set, files in the ingest ledger with the same size and modified time (or the same contents)
+    /// are skipped, files that are imported are added to the ledger
+    ///
+    /// # Errors
+    /// See `import_data`
+    ///
+    /// # Returns
+    /// Outcome of each file (and archive entry)
     ///
-    pub fn import_data(&mut self, dir: String) -> Result<(), Error> {
+    pub fn import_data_with_progress<P: FnMut(&ImportReport)>(&mut self, dir: String, options: &ImportOptions, mut progress: P) -> Result<ImportReport, Error> {
+        let importer = match &options.format {
+            Some(name) => Some(importer_named(name)?),
+            None => None
+        };
+        let filter = PathFilter::new(&options.include, &options.exclude)?;
         let path = PathBuf::from(dir);
-        if path.is_dir() {
-            let (files, errors) = self.list_files(path)?;
-            for error in errors {
-                error!("{}", error);
+        if !path.is_dir() {
+            return Err(Error::from("Not a directory"));
+        }
+        // Ledger paths are absolute so the same file matches however the directory is given
+        let path = path.canonicalize()?;
+        let ingested = if options.force {
+            HashMap::new()
+        } else {
+            self.db_manager.get_ingested_files()?
+        };
+
+        let mut report = ImportReport::new(options.dry_run);
+        let mut skipped = vec![];
+        let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+        let should_read = |path: &Path, size: u64, modified: Option<i64>| {
+            let unchanged = ingested.get(path).is_some_and(|file| file.matches(size, modified));
+            if unchanged {
+                trace!("Skipping unchanged {}", path.to_string_lossy());
+                skipped.push(path.to_path_buf());
             }
-            for file in files {
-                if let Err(err) = self.import_data_from_file(&file) {
-                    error!("Failed to import from {:?}: {}", file, err);
-                }
+            return !unchanged;
+        };
+        for_each_source(&path, options.recursive, &filter, should_read, |source| {
+            batch.push(source);
+            if batch.len() == IMPORT_BATCH_SIZE {
+                self.import_batch(std::mem::take(&mut batch), importer.as_deref(), &ingested, options, &mut report)?;
+                progress(&report);
             }
-        } else {
-            return Err(Error::from("Not a directory"));
+            Ok(())
+        })?;
+        if !batch.is_empty() {
+            self.import_batch(batch, importer.as_deref(), &ingested, options, &mut report)?;
         }
+        report.files.extend(skipped.into_iter().map(|path| (path, FileOutcome::Skipped)));
+        progress(&report);
 
-        Ok(())
+        return Ok(report);
     }
 
-    /// Import data from specific file
+    /// Parse `sources` in parallel and write them in one transaction, adding the outcome of each to `report`
+    ///
+    /// Sources with the same contents as their entry in `ingested` are skipped
     ///
     /// # Errors
-    /// Failed to read file
-    /// Failed to parse json
-    /// Failed to insert into database
+    /// Failed to start or commit the transaction
     ///
-    fn import_data_from_file(&mut self, file: &PathBuf) -> Result<(), Error> {
-        let json = fs::read_to_string(file)?;
-        let dark_sky_weather: DarkSkyReading = serde_json::from_str(&json)?;
-        let (current, future) = dark_sky_weather.get_weather();
-        let current_weather = current.into();
-        let future_weathers = future.into_iter().map(|weather| Into::<Weather>::into(weather).update_id(&current_weather)).collect();
+    fn import_batch(&mut self, sources: Vec<Source>, importer: Option<&dyn Importer>, ingested: &HashMap<PathBuf, IngestedFile>, options: &ImportOptions, report: &mut ImportReport) -> Result<(), Error> {
+        let parsed = sources.into_par_iter()
+            .map(|source| {
+                let hash = source.text.as_ref().map(|text| format!("{:x}", Sha256::digest(text.as_bytes()))).unwrap_or_default();
+                let file = IngestedFile { path: source.path, size: source.size, modified: source.modified, hash };
+                let unchanged = ingested.get(&file.path).is_some_and(|existing| existing.hash == file.hash);
+                let imported = match source.text {
+                    Ok(_) if unchanged => Ok(None),
+                    Ok(text) => parse_text(&text, importer)
+                        .and_then(|(format, imported)| Ok(Some((imported, Payload::new(format, &file.path, file.hash.clone(), &text)?)))),
+                    Err(err) => Err(err)
+                };
+                (file, imported)
+            })
+            .collect::<Vec<_>>();
 
-        self.db_manager.add_weather(current_weather, future_weathers)?;
+        // Locations are resolved in order so a new location is only added once
+        let mut pending = Vec::with_capacity(parsed.len());
+        let mut ledger = Vec::with_capacity(parsed.len());
+        for (file, imported) in parsed {
+            let outcome = match imported {
+                Ok(Some((imported, payload))) => match self.resolve_location(&imported, options) {
+                    Ok(Some(location_id)) => Pending::Write(ImportedFile { location_id, readings: imported.readings, payload: Some(payload) }),
+                    // Location would be new so everything would be inserted
+                    Ok(None) => Pending::Done(FileOutcome::Imported(imported.readings.iter().fold(ImportCounts::default(), |mut counts, (weather, predictions)| {
+                        let rejected = predictions.iter().filter(|prediction| lead_time(weather, prediction).is_none()).count();
+                        counts += ImportCounts { readings_inserted: 1, predictions_inserted: predictions.len() - rejected, predictions_rejected: rejected, ..ImportCounts::default() };
+                        counts
+                    }))),
+                    Err(err) => Pending::Done(FileOutcome::Rejected(err))
+                },
+                // Contents are unchanged, only the modified time needs updating in the ledger
+                Ok(None) => Pending::Done(FileOutcome::Skipped),
+                Err(err) => Pending::Done(FileOutcome::Rejected(err))
+            };
+            pending.push((file.path.clone(), outcome));
+            ledger.push(file);
+        }
 
-        trace!("Imported {}", file.to_string_lossy().into_owned());
+        let start = report.files.len();
+        self.write_batch(pending, options.dry_run, report)?;
 
-        Ok(())
+        if !options.dry_run {
+            let imported_files = ledger.into_iter()
+                .zip(&report.files[start..])
+                .filter(|(_, (_, outcome))| !matches!(outcome, FileOutcome::Rejected(_)))
+                .map(|(file, _)| file)
+                .collect::<Vec<IngestedFile>>();
+            self.db_manager.add_ingested_files(&imported_files)?;
+        }
+
+        return Ok(());
     }
 
-    /// Gets a list of valid json files in `path`.
+    /// Write `files` in one transaction and add the outcome of each to `report`
     ///
     /// # Errors
-    /// Directory is inaccessible
+    /// Failed to start or commit the transaction
     ///
-    /// # Returns
-    /// List of paths to valid json files and file access errors
-    /// If the results are entirely errors then check:
-    /// - Permissions of files within the dir
-    /// - Filenames (and all parent directories names) are valid UTF-8
-    ///
-    fn list_files(&mut self, path: PathBuf) -> Result<(Vec<PathBuf>, Vec<String>), Error> {
-        let dir = fs::read_dir(path)?;
+    fn write_batch(&mut self, files: Vec<(PathBuf, Pending)>, dry_run: bool, report: &mut ImportReport) -> Result<(), Error> {
+        let mut paths = Vec::with_capacity(files.len());
+        let mut outcomes = Vec::with_capacity(files.len());
+        let mut to_write = vec![];
+        let mut written = vec![];
+        for (path, file) in files {
+            paths.push(path);
+            match file {
+                Pending::Write(file) => {
+                    // Placeholder, replaced by the result of the batch insert
+                    written.push(outcomes.len());
+                    outcomes.push(FileOutcome::Skipped);
+                    to_write.push(file);
+                }
+                Pending::Done(outcome) => outcomes.push(outcome)
+            }
+        }
 
-        let mut files = vec![];
-        let mut errors = vec![];
+        let results = self.db_manager.add_weather_batch(to_write, dry_run)?;
+        for (i, result) in written.into_iter().zip(results) {
+            outcomes[i] = match result {
+                Ok(counts) => FileOutcome::Imported(counts),
+                Err(err) => FileOutcome::Rejected(err)
+            };
+        }
 
-        for file in dir {
-            match file {
-                Ok(entry) => {
-                    files.push(entry.path());
+        for (path, outcome) in paths.into_iter().zip(outcomes) {
+            let outcome = match outcome {
+                FileOutcome::Rejected(err) => {
+                    let err = err.in_file(&path);
+                    error!("Failed to import from {:?}: {}", path, err);
+                    FileOutcome::Rejected(err)
                 }
-                Err(err) => {
-                    errors.push(format!("{:?}: {}", err.kind(), err));
+                FileOutcome::Imported(counts) => {
+                    trace!("Imported {}", path.to_string_lossy());
+                    FileOutcome::Imported(counts)
                 }
+                FileOutcome::Skipped => {
+                    trace!("Skipping unchanged {}", path.to_string_lossy());
+                    FileOutcome::Skipped
+                }
+            };
+            report.files.push((path, outcome));
+        }
+
+        return Ok(());
+    }
+
+    /// Rebuild readings and predictions from their stored payloads, for the selected location or all locations if none is selected
+    ///
+    /// Payloads are parsed again with the importer that first imported them and the rows replaced,
+    /// so fields added to an importer are filled in without the original files
+    /// Readings imported before payloads were stored are left unchanged
+    /// `progress` is called with the report so far after each batch
+    ///
+    /// # Errors
+    /// Failed to read payloads or write a batch to the database
+    ///
+    /// # Returns
+    /// Outcome of each payload, by the path it was originally imported from
+    ///
+    pub fn reparse<P: FnMut(&ImportReport)>(&mut self, mut progress: P) -> Result<ImportReport, Error> {
+        let mut report = ImportReport::new(false);
+        let mut after_id = 0;
+        loop {
+            let payloads = self.db_manager.get_payloads(self.location_id, after_id, IMPORT_BATCH_SIZE)?;
+            match payloads.last() {
+                Some((id, _, _)) => after_id = *id,
+                None => break
             }
+
+            let pending = payloads.into_par_iter()
+                .map(|(_, location_id, payload)| {
+                    let path = PathBuf::from(&payload.source);
+                    let imported = importer_named(&payload.format)
+                        .and_then(|importer| importer.import(&payload.text()?));
+                    let outcome = match imported {
+                        Ok(imported) => Pending::Write(ImportedFile { location_id, readings: imported.readings, payload: Some(payload) }),
+                        Err(err) => Pending::Done(FileOutcome::Rejected(err))
+                    };
+                    (path, outcome)
+                })
+                .collect();
+            self.write_batch(pending, false, &mut report)?;
+            progress(&report);
         }
 
-        let mut filtered: Vec<PathBuf> = files.into_iter()
-            .filter(|entry| entry.is_file())
-            .filter(|entry| entry.file_name().is_some())
-            .filter(|entry| entry.file_name().unwrap().to_str().is_some())
-            .filter(|entry| entry.file_name().unwrap().to_str().unwrap().ends_with(".json"))
-            .collect();
+        return Ok(report);
+    }
+
+    /// Location to import `imported` into
+    ///
+    /// # Errors
+    /// File has no coordinates (if `location_id` is not set)
+    /// Failed to read or add location
+    ///
+    /// # Returns
+    /// Location id, or None if it would be a new location in a dry run
+    ///
+    fn resolve_location(&mut self, imported: &ImportedWeather, options: &ImportOptions) -> Result<Option<i64>, Error> {
+        return match (options.location_id, &imported.coordinates) {
+            (Some(id), _) => Ok(Some(id)),
+            // A new location isn't created in a dry run
+            (None, Some(coordinates)) if options.dry_run => self.db_manager.find_location(coordinates),
+            (None, Some(coordinates)) => Ok(Some(self.db_manager.find_or_add_location(coordinates)?)),
+            (None, None) => Err(Error::from("File has no coordinates, select a location to import into"))
+        };
+    }
+}
+
+/// File waiting to be written by `WeatherApp::write_batch`
+enum Pending {
+    Write(ImportedFile),
+    /// Nothing to write, e.g. it was rejected or skipped
+    Done(FileOutcome),
+}
+
+/// Parse the contents of a json file
+///
+/// # Errors
+/// Failed to parse json (`Error::Parse` with the line, if known)
+/// Format could not be detected (if `importer` is not set)
+///
+/// # Returns
+/// Name of the importer used and what it imported
+///
+fn parse_text(text: &str, importer: Option<&dyn Importer>) -> Result<(&'static str, ImportedWeather), Error> {
+    return match importer {
+        Some(importer) => Ok((importer.name(), importer.import(text)?)),
+        None => {
+            let json: serde_json::Value = serde_json::from_str(text)?;
+            let importer = detect_importer(&json)?;
+            Ok((importer.name(), importer.import(text)?))
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use crate::test_support::{TempDir, dark_sky_at, DARK_SKY_HOUR};
+
+    /// Write `hours` consecutive hourly DarkSky files to `data` in `dir`
+    fn write_hourly_files(dir: &TempDir, hours: usize) -> PathBuf {
+        for hour in 0..hours {
+            dir.write(&format!("data/{:05}.json", hour), dark_sky_at(DARK_SKY_HOUR + hour as i64 * 3600).to_string().as_bytes());
+        }
+        return dir.path().join("data");
+    }
+
+    fn import_hourly_files(name: &str, hours: usize) -> (ImportReport, Duration) {
+        let dir = TempDir::new(name);
+        let data = write_hourly_files(&dir, hours);
+        let mut db_manager = DbManager::new(dir.path().join("weather.db").to_string_lossy()).unwrap();
+        db_manager.init().unwrap();
+        let mut app = WeatherApp::new(db_manager);
 
-        filtered.sort();
+        let start = Instant::now();
+        let report = app.import_data(data.to_string_lossy().into_owned(), &ImportOptions::default()).unwrap();
+        return (report, start.elapsed());
+    }
+
+    #[test]
+    fn test_local_days() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let location_id = db_manager.find_or_add_location(&Coordinates { latitude: 51.4, longitude: -2.6, timezone: Some(String::from("Europe/London")) }).unwrap();
+        let start = NaiveDate::from_ymd_opt(2019, 10, 26).unwrap().and_time(chrono::NaiveTime::MIN);
+        for hour in 0..48 {
+            let date: SimpleDate = (start + chrono::Duration::hours(hour)).into();
+            db_manager.add_weather(location_id, Weather::new(date.year, date.day, date.hour, crate::models::Icon::Clear, 0., 0., hour as f64, 0., 0., 0., None), vec![], false).unwrap();
+        }
+        let mut app = WeatherApp::new(db_manager);
+        let day = |day: u32| NaiveDate::from_ymd_opt(2019, 10, day).unwrap();
+
+        assert_eq!(app.get_readings_for_day(day(27)).unwrap().len(), 24, "UTC days are always 24 hours");
+
+        app.set_local_time(true);
+        let hours = app.get_readings_for_day(day(27)).unwrap();
+        assert_eq!(hours.len(), 25, "clocks go back");
+        assert_eq!(hours.iter().filter(|(time, _)| time.format("%H").to_string() == "01").count(), 2, "1am happens twice");
+        assert_eq!(hours[0].1.as_ref().unwrap().temp, 23., "BST midnight is 23:00 UTC");
+        assert!(hours.iter().all(|(_, weather)| weather.is_some()));
+
+        let days = app.get_readings_by_day(day(25), day(27)).unwrap();
+        let counts: Vec<usize> = days.iter().map(|(_, readings)| readings.len()).collect();
+        assert_eq!(counts, vec![0, 23, 25], "first reading is at 1am on the 26th");
+        assert_eq!(<(u16, u16, u8)>::from(app.day_range(day(27), day(27)).unwrap().1), (2019, 300, 23));
+    }
+
+    #[test]
+    fn test_import_batches() {
+        let dir = TempDir::new("import_batches");
+        let hours = IMPORT_BATCH_SIZE + 10;
+        let data = write_hourly_files(&dir, hours);
+        dir.write("data/broken.json", b"{");
+        let mut bad_time = dark_sky_at(DARK_SKY_HOUR);
+        bad_time["currently"]["time"] = u64::MAX.into();
+        dir.write("data/bad_time.json", bad_time.to_string().as_bytes());
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let mut app = WeatherApp::new(db_manager);
+        let data = data.to_string_lossy().into_owned();
+
+        let mut updates = vec![];
+        let report = app.import_data_with_progress(data.clone(), &ImportOptions::default(), |report| updates.push(report.files.len())).unwrap();
+        assert_eq!(updates, vec![IMPORT_BATCH_SIZE, hours + 2]);
+        assert_eq!(report.parsed(), hours);
+        assert_eq!(report.rejected().len(), 2, "unparseable json and invalid times are rejected");
+        let totals = report.totals();
+        assert_eq!(totals.readings_inserted, hours);
+        assert_eq!(totals.readings_replaced, 0);
+        assert_eq!(totals.predictions_replaced, 0);
+
+        let totals = app.import_data(data, &ImportOptions { force: true, ..ImportOptions::default() }).unwrap().totals();
+        assert_eq!(totals.readings_inserted, 0);
+        assert_eq!(totals.readings_replaced, hours);
+    }
+
+    #[test]
+    fn test_import_skips_unchanged_files() {
+        let dir = TempDir::new("import_skips_unchanged");
+        let data = write_hourly_files(&dir, 3);
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let mut app = WeatherApp::new(db_manager);
+        let options = ImportOptions::default();
+        let import = |app: &mut WeatherApp, options: &ImportOptions| app.import_data(data.to_string_lossy().into_owned(), options).unwrap();
+
+        let report = import(&mut app, &options);
+        assert_eq!((report.parsed(), report.skipped()), (3, 0));
+
+        let report = import(&mut app, &options);
+        assert_eq!((report.parsed(), report.skipped()), (0, 3));
+
+        // Same contents with a new modified time is skipped after comparing the hash
+        let file = std::fs::File::options().write(true).open(data.join("00000.json")).unwrap();
+        file.set_modified(std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000)).unwrap();
+        // Changed contents are imported
+        let text = std::fs::read_to_string(data.join("00001.json")).unwrap();
+        std::fs::write(data.join("00001.json"), format!("{}\n", text)).unwrap();
+        let report = import(&mut app, &options);
+        assert_eq!((report.parsed(), report.skipped()), (1, 2));
+        assert_eq!(report.totals().readings_replaced, 1);
+
+        let report = import(&mut app, &options);
+        assert_eq!((report.parsed(), report.skipped()), (0, 3));
+
+        let report = import(&mut app, &ImportOptions { force: true, ..ImportOptions::default() });
+        assert_eq!((report.parsed(), report.skipped()), (3, 0));
+    }
+
+    #[test]
+    fn test_reparse() {
+        let dir = TempDir::new("reparse");
+        let data = write_hourly_files(&dir, 3);
+        dir.write("data/broken.json", b"{");
+        let mut bad_time = dark_sky_at(DARK_SKY_HOUR);
+        bad_time["currently"]["time"] = u64::MAX.into();
+        dir.write("data/bad_time.json", bad_time.to_string().as_bytes());
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let mut app = WeatherApp::new(db_manager);
+        let imported = app.import_data(data.to_string_lossy().into_owned(), &ImportOptions::default()).unwrap().totals();
+        std::fs::remove_dir_all(&data).unwrap();
+
+        let report = app.reparse(|_| {}).unwrap();
+
+        assert_eq!(report.parsed(), 3, "rejected files aren't stored");
+        assert!(report.files[0].0.ends_with("data/00000.json"));
+        let totals = report.totals();
+        assert_eq!(totals.readings_replaced, imported.readings_inserted);
+        assert_eq!(totals.predictions_replaced, imported.predictions_inserted);
+        let location_id = app.get_location().unwrap().unwrap().id;
+        assert_eq!(app.db_manager.get_all_readings(location_id).unwrap().len(), 3);
+    }
 
-        return Ok((filtered, errors));
+    /// Run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn bench_import_year() {
+        let (report, elapsed) = import_hourly_files("bench_import_year", 365 * 24);
+        assert_eq!(report.parsed(), 365 * 24);
+        assert_eq!(report.totals().readings_inserted, 365 * 24);
+        assert!(elapsed < Duration::from_secs(60), "Import took {:?}", elapsed);
     }
 }
@@ -0,0 +1,178 @@
+//! Calendar days in UTC or a location's local time
+//!
+//! Readings are stored by UTC hour, a [`Calendar`] finds which of those hours make up a day.
+//! Local days are not always 24 hours, on daylight saving transitions they are 23 or 25
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use log::warn;
+use crate::models::{Location, SimpleDate};
+
+const HOUR: i64 = 3600;
+
+/// How UTC hours are grouped into days
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Calendar {
+    /// Days from midnight UTC, always 24 hours
+    Utc,
+    /// Days in an IANA time zone (e.g. Europe/London)
+    Local(Tz),
+}
+
+impl Calendar {
+    /// Local calendar of `location`
+    ///
+    /// # Returns
+    /// UTC if the location has no time zone, or it isn't a known IANA name
+    ///
+    pub fn for_location(location: Option<&Location>) -> Calendar {
+        let name = match location.and_then(|location| location.coordinates.as_ref()).and_then(|coordinates| coordinates.timezone.as_deref()) {
+            Some(name) => name,
+            None => return Calendar::Utc
+        };
+        return match name.parse::<Tz>() {
+            Ok(tz) => Calendar::Local(tz),
+            Err(err) => {
+                warn!("Unknown time zone '{}', using UTC: {}", name, err);
+                Calendar::Utc
+            }
+        };
+    }
+
+    /// Time zone name, e.g. `UTC` or `Europe/London`
+    pub fn name(&self) -> &str {
+        return match self {
+            Calendar::Utc => "UTC",
+            Calendar::Local(tz) => tz.name()
+        };
+    }
+
+    /// Unix timestamp of the start of `date`
+    ///
+    /// If midnight is skipped by a daylight saving change the day starts at the first hour that exists
+    pub fn start_of_day(&self, date: NaiveDate) -> i64 {
+        return match self {
+            Calendar::Utc => date.and_time(NaiveTime::MIN).and_utc().timestamp(),
+            Calendar::Local(tz) => (0..24)
+                .find_map(|hour| date.and_hms_opt(hour, 0, 0).and_then(|time| tz.from_local_datetime(&time).earliest()))
+                .expect("Every day has an hour")
+                .timestamp()
+        };
+    }
+
+    /// Unix timestamps of every UTC hour that starts during `date`
+    ///
+    /// # Returns
+    /// 24 hours, or 23/25 on daylight saving changes
+    ///
+    pub fn hours_of_day(&self, date: NaiveDate) -> Vec<i64> {
+        let start = ceil_hour(self.start_of_day(date));
+        let end = self.start_of_day(date + Duration::days(1));
+        return (start..end).step_by(HOUR as usize).collect();
+    }
+
+    /// First and last UTC hour of every day from `first` to `last` (inclusive, inclusive)
+    pub fn day_range(&self, first: NaiveDate, last: NaiveDate) -> (SimpleDate, SimpleDate) {
+        let start = ceil_hour(self.start_of_day(first));
+        let end = self.start_of_day(last + Duration::days(1)) - 1;
+        return (to_simple_date(start), to_simple_date(end - end.rem_euclid(HOUR)));
+    }
+
+    /// Date and time of `timestamp` in this calendar
+    pub fn local_time(&self, timestamp: i64) -> NaiveDateTime {
+        let utc = DateTime::from_timestamp(timestamp, 0).expect("Invalid timestamp");
+        return match self {
+            Calendar::Utc => utc.naive_utc(),
+            Calendar::Local(tz) => utc.with_timezone(tz).naive_local()
+        };
+    }
+
+    /// Day `timestamp` is in
+    pub fn date_of(&self, timestamp: i64) -> NaiveDate {
+        return self.local_time(timestamp).date();
+    }
+}
+
+/// Round `timestamp` up to the start of a UTC hour
+fn ceil_hour(timestamp: i64) -> i64 {
+    return timestamp + (HOUR - timestamp.rem_euclid(HOUR)) % HOUR;
+}
+
+fn to_simple_date(timestamp: i64) -> SimpleDate {
+    return DateTime::from_timestamp(timestamp, 0).expect("Invalid timestamp").naive_utc().into();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::Coordinates;
+
+    fn london() -> Calendar {
+        return Calendar::Local(chrono_tz::Europe::London);
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        return NaiveDate::from_ymd_opt(year, month, day).unwrap();
+    }
+
+    #[test]
+    fn test_hours_in_day() {
+        assert_eq!(london().hours_of_day(date(2019, 3, 30)).len(), 24);
+        assert_eq!(london().hours_of_day(date(2019, 3, 31)).len(), 23, "clocks go forward");
+        assert_eq!(london().hours_of_day(date(2019, 10, 27)).len(), 25, "clocks go back");
+        assert_eq!(Calendar::Utc.hours_of_day(date(2019, 3, 31)).len(), 24);
+    }
+
+    #[test]
+    fn test_local_day_range() {
+        let summer = london().day_range(date(2019, 7, 1), date(2019, 7, 1));
+        assert_eq!(<(u16, u16, u8)>::from(summer.0), (2019, 181, 23), "BST midnight is 23:00 UTC the day before");
+        assert_eq!(<(u16, u16, u8)>::from(summer.1), (2019, 182, 22));
+
+        let winter = london().day_range(date(2019, 12, 31), date(2020, 1, 1));
+        assert_eq!(<(u16, u16, u8)>::from(winter.0), (2019, 365, 0));
+        assert_eq!(<(u16, u16, u8)>::from(winter.1), (2020, 1, 23));
+
+        let utc = Calendar::Utc.day_range(date(2019, 7, 1), date(2019, 7, 1));
+        assert_eq!(<(u16, u16, u8)>::from(utc.0), (2019, 182, 0));
+        assert_eq!(<(u16, u16, u8)>::from(utc.1), (2019, 182, 23));
+    }
+
+    #[test]
+    fn test_half_hour_offset() {
+        let kolkata = Calendar::Local(chrono_tz::Asia::Kolkata);
+        let (start, end) = kolkata.day_range(date(2020, 1, 2), date(2020, 1, 2));
+
+        assert_eq!(<(u16, u16, u8)>::from(start), (2020, 1, 19), "midnight is 18:30 UTC so the first whole hour is 19:00");
+        assert_eq!(<(u16, u16, u8)>::from(end), (2020, 2, 18));
+        assert_eq!(kolkata.hours_of_day(date(2020, 1, 2)).len(), 24);
+    }
+
+    #[test]
+    fn test_midnight_skipped() {
+        //Clocks went forward at midnight in Sao Paulo on 2018-11-04
+        let sao_paulo = Calendar::Local(chrono_tz::America::Sao_Paulo);
+        let start = sao_paulo.start_of_day(date(2018, 11, 4));
+
+        assert_eq!(sao_paulo.local_time(start), date(2018, 11, 4).and_hms_opt(1, 0, 0).unwrap());
+        assert_eq!(sao_paulo.hours_of_day(date(2018, 11, 4)).len(), 23);
+    }
+
+    #[test]
+    fn test_date_of() {
+        let timestamp = date(2019, 6, 30).and_hms_opt(23, 0, 0).unwrap().and_utc().timestamp();
+
+        assert_eq!(Calendar::Utc.date_of(timestamp), date(2019, 6, 30));
+        assert_eq!(london().date_of(timestamp), date(2019, 7, 1));
+    }
+
+    #[test]
+    fn test_for_location() {
+        let location = |timezone: Option<&str>| Location { id: 1, name: String::new(), coordinates: Some(Coordinates { latitude: 0., longitude: 0., timezone: timezone.map(String::from) }) };
+
+        assert_eq!(Calendar::for_location(Some(&location(Some("Europe/London")))), london());
+        assert_eq!(Calendar::for_location(Some(&location(Some("Mars/Olympus_Mons")))), Calendar::Utc);
+        assert_eq!(Calendar::for_location(Some(&location(None))), Calendar::Utc);
+        assert_eq!(Calendar::for_location(None), Calendar::Utc);
+    }
+}
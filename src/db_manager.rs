@@ -1,21 +1,149 @@
-use rusqlite::{params, Connection, NO_PARAMS, Error, Transaction, Row};
-use crate::Error as CrateError;
-use log::{trace, debug, error};
-use crate::models::{Weather, Icon, Prediction};
+//! SQLite storage of locations, readings and predictions
 
-const CREATE_WEATHER_TABLE: &str = "CREATE TABLE IF NOT EXISTS weather (id TEXT PRIMARY KEY, timestamp REAL, year INTEGER, day INTEGER, hour INTEGER, icon TEXT, precip_intensity REAL, precip_probability REAL, temp REAL, wind_speed REAL, wind_gust REAL, humidity REAL, precip_type TEXT)";
-const CREATE_WEATHER_UNIQUE_INDEX: &str = "CREATE UNIQUE INDEX IF NOT EXISTS year_day_hour ON weather (year, day, hour)";
-const CREATE_PREDICATION_TABLE: &str = "CREATE TABLE IF NOT EXISTS prediction (id TEXT PRIMARY KEY, reading_year INTEGER, reading_day INTEGER, reading_hour INTEGER, prediction_year INTEGER, prediction_day INTEGER, prediction_hour INTEGER, hour_diff INTEGER, icon TEXT, precip_intensity REAL, precip_probability REAL, temp REAL, wind_speed REAL, wind_gust REAL, humidity REAL, precip_type TEXT)";
-const CREATE_PREDICATION_UNIQUE_INDEX: &str = "CREATE UNIQUE INDEX IF NOT EXISTS year_day_hour_diff ON prediction (reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour)";
+use rusqlite::{params, Connection, NO_PARAMS, Row};
+use crate::Error;
+use log::{trace, debug};
+use chrono::Utc;
+use crate::models::{Weather, Icon, Prediction, Location, Coordinates, WeatherDetails, SimpleDate};
+use crate::import::{ImportCounts, IngestedFile, ImportedFile, Payload, lead_time};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
-const DB_VERSION: usize = 1;
+const CREATE_MIGRATION_TABLE: &str = "CREATE TABLE IF NOT EXISTS migration (version INTEGER PRIMARY KEY, description TEXT NOT NULL, applied_at INTEGER)";
 
+/// Schema change that moves the database from `version - 1` to `version`
+struct Migration {
+    version: usize,
+    description: &'static str,
+    /// Statements to run, separated by `;`
+    sql: &'static str,
+}
+
+/// Every schema change, in the order they must be applied
+///
+/// Never edit a migration once released (existing databases have already applied it), add a new one instead
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create weather and prediction tables",
+        sql: "
+            CREATE TABLE weather (id TEXT PRIMARY KEY, timestamp REAL, year INTEGER, day INTEGER, hour INTEGER, icon TEXT, precip_intensity REAL, precip_probability REAL, temp REAL, wind_speed REAL, wind_gust REAL, humidity REAL, precip_type TEXT);
+            CREATE UNIQUE INDEX year_day_hour ON weather (year, day, hour);
+            CREATE TABLE prediction (id TEXT PRIMARY KEY, reading_year INTEGER, reading_day INTEGER, reading_hour INTEGER, prediction_year INTEGER, prediction_day INTEGER, prediction_hour INTEGER, hour_diff INTEGER, icon TEXT, precip_intensity REAL, precip_probability REAL, temp REAL, wind_speed REAL, wind_gust REAL, humidity REAL, precip_type TEXT);
+            CREATE UNIQUE INDEX year_day_hour_diff ON prediction (reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour);
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "Add locations, existing data is moved to a location with no coordinates",
+        sql: "
+            CREATE TABLE location (id INTEGER PRIMARY KEY, name TEXT NOT NULL, latitude REAL, longitude REAL, timezone TEXT);
+            CREATE UNIQUE INDEX latitude_longitude ON location (latitude, longitude);
+            INSERT INTO location (id, name) SELECT 1, 'Unknown' WHERE EXISTS (SELECT 1 FROM weather) OR EXISTS (SELECT 1 FROM prediction);
+            ALTER TABLE weather RENAME TO weather_v1;
+            ALTER TABLE prediction RENAME TO prediction_v1;
+            CREATE TABLE weather (id TEXT, location_id INTEGER NOT NULL REFERENCES location(id), timestamp REAL, year INTEGER, day INTEGER, hour INTEGER, icon TEXT, precip_intensity REAL, precip_probability REAL, temp REAL, wind_speed REAL, wind_gust REAL, humidity REAL, precip_type TEXT);
+            CREATE TABLE prediction (id TEXT, location_id INTEGER NOT NULL REFERENCES location(id), reading_year INTEGER, reading_day INTEGER, reading_hour INTEGER, prediction_year INTEGER, prediction_day INTEGER, prediction_hour INTEGER, hour_diff INTEGER, icon TEXT, precip_intensity REAL, precip_probability REAL, temp REAL, wind_speed REAL, wind_gust REAL, humidity REAL, precip_type TEXT);
+            INSERT INTO weather (id, location_id, timestamp, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type)
+                SELECT id, 1, timestamp, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type FROM weather_v1;
+            INSERT INTO prediction (id, location_id, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type)
+                SELECT id, 1, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type FROM prediction_v1;
+            DROP TABLE weather_v1;
+            DROP TABLE prediction_v1;
+            CREATE UNIQUE INDEX location_year_day_hour ON weather (location_id, year, day, hour);
+            CREATE UNIQUE INDEX location_year_day_hour_diff ON prediction (location_id, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour);
+        ",
+    },
+    Migration {
+        version: 3,
+        description: "Add air temp, dew point, pressure, cloud cover, wind bearing, UV index, visibility, ozone and summary",
+        sql: "
+            ALTER TABLE weather ADD COLUMN air_temp REAL;
+            ALTER TABLE weather ADD COLUMN dew_point REAL;
+            ALTER TABLE weather ADD COLUMN pressure REAL;
+            ALTER TABLE weather ADD COLUMN cloud_cover REAL;
+            ALTER TABLE weather ADD COLUMN wind_bearing REAL;
+            ALTER TABLE weather ADD COLUMN uv_index REAL;
+            ALTER TABLE weather ADD COLUMN visibility REAL;
+            ALTER TABLE weather ADD COLUMN ozone REAL;
+            ALTER TABLE weather ADD COLUMN summary TEXT;
+            ALTER TABLE prediction ADD COLUMN air_temp REAL;
+            ALTER TABLE prediction ADD COLUMN dew_point REAL;
+            ALTER TABLE prediction ADD COLUMN pressure REAL;
+            ALTER TABLE prediction ADD COLUMN cloud_cover REAL;
+            ALTER TABLE prediction ADD COLUMN wind_bearing REAL;
+            ALTER TABLE prediction ADD COLUMN uv_index REAL;
+            ALTER TABLE prediction ADD COLUMN visibility REAL;
+            ALTER TABLE prediction ADD COLUMN ozone REAL;
+            ALTER TABLE prediction ADD COLUMN summary TEXT;
+        ",
+    },
+    Migration {
+        version: 4,
+        description: "Add ingest ledger of imported files",
+        sql: "
+            CREATE TABLE ingested_file (path TEXT PRIMARY KEY, size INTEGER NOT NULL, modified INTEGER, hash TEXT NOT NULL, ingested_at INTEGER NOT NULL);
+        ",
+    },
+    Migration {
+        version: 5,
+        description: "Store the raw document each reading was imported from",
+        sql: "
+            CREATE TABLE payload (id INTEGER PRIMARY KEY, location_id INTEGER NOT NULL REFERENCES location(id), format TEXT NOT NULL, source TEXT NOT NULL, hash TEXT NOT NULL, data BLOB NOT NULL);
+            CREATE UNIQUE INDEX payload_location_hash ON payload (location_id, hash);
+            ALTER TABLE weather ADD COLUMN payload_id INTEGER REFERENCES payload(id);
+            CREATE INDEX weather_payload ON weather (payload_id);
+        ",
+    },
+    Migration {
+        version: 6,
+        description: "Key readings and predictions by location and UTC timestamp instead of text ids",
+        sql: "
+            ALTER TABLE weather RENAME TO weather_v5;
+            ALTER TABLE prediction RENAME TO prediction_v5;
+            DROP INDEX location_year_day_hour;
+            DROP INDEX location_year_day_hour_diff;
+            DROP INDEX weather_payload;
+            CREATE TABLE weather (location_id INTEGER NOT NULL REFERENCES location(id), timestamp INTEGER NOT NULL, year INTEGER NOT NULL, day INTEGER NOT NULL, hour INTEGER NOT NULL, icon TEXT, precip_intensity REAL, precip_probability REAL, temp REAL, wind_speed REAL, wind_gust REAL, humidity REAL, precip_type TEXT,
+                air_temp REAL, dew_point REAL, pressure REAL, cloud_cover REAL, wind_bearing REAL, uv_index REAL, visibility REAL, ozone REAL, summary TEXT, payload_id INTEGER REFERENCES payload(id), PRIMARY KEY (location_id, timestamp));
+            CREATE TABLE prediction (location_id INTEGER NOT NULL REFERENCES location(id), reading_timestamp INTEGER NOT NULL, prediction_timestamp INTEGER NOT NULL, reading_year INTEGER NOT NULL, reading_day INTEGER NOT NULL, reading_hour INTEGER NOT NULL, prediction_year INTEGER NOT NULL, prediction_day INTEGER NOT NULL, prediction_hour INTEGER NOT NULL, hour_diff INTEGER NOT NULL,
+                icon TEXT, precip_intensity REAL, precip_probability REAL, temp REAL, wind_speed REAL, wind_gust REAL, humidity REAL, precip_type TEXT,
+                air_temp REAL, dew_point REAL, pressure REAL, cloud_cover REAL, wind_bearing REAL, uv_index REAL, visibility REAL, ozone REAL, summary TEXT, PRIMARY KEY (location_id, reading_timestamp, prediction_timestamp));
+            INSERT OR REPLACE INTO weather (location_id, timestamp, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type, air_temp, dew_point, pressure, cloud_cover, wind_bearing, uv_index, visibility, ozone, summary, payload_id)
+                SELECT location_id, CAST(strftime('%s', printf('%04d-01-01', year)) AS INTEGER) + (day - 1) * 86400 + hour * 3600, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type, air_temp, dew_point, pressure, cloud_cover, wind_bearing, uv_index, visibility, ozone, summary, payload_id
+                FROM weather_v5 WHERE year IS NOT NULL AND day IS NOT NULL AND hour IS NOT NULL;
+            INSERT OR REPLACE INTO prediction (location_id, reading_timestamp, prediction_timestamp, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type, air_temp, dew_point, pressure, cloud_cover, wind_bearing, uv_index, visibility, ozone, summary)
+                SELECT location_id, reading_timestamp, prediction_timestamp, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, (prediction_timestamp - reading_timestamp) / 3600, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type, air_temp, dew_point, pressure, cloud_cover, wind_bearing, uv_index, visibility, ozone, summary
+                FROM (SELECT *, CAST(strftime('%s', printf('%04d-01-01', reading_year)) AS INTEGER) + (reading_day - 1) * 86400 + reading_hour * 3600 AS reading_timestamp,
+                        CAST(strftime('%s', printf('%04d-01-01', prediction_year)) AS INTEGER) + (prediction_day - 1) * 86400 + prediction_hour * 3600 AS prediction_timestamp
+                    FROM prediction_v5 WHERE reading_year IS NOT NULL AND reading_day IS NOT NULL AND reading_hour IS NOT NULL AND prediction_year IS NOT NULL AND prediction_day IS NOT NULL AND prediction_hour IS NOT NULL)
+                WHERE (prediction_timestamp - reading_timestamp) % 3600 = 0 AND (prediction_timestamp - reading_timestamp) / 3600 BETWEEN 1 AND 48; -- same lead times as import::lead_time, 48 is MAX_HOUR_DIFF
+            DROP TABLE weather_v5;
+            DROP TABLE prediction_v5;
+            CREATE INDEX weather_payload ON weather (payload_id);
+            CREATE INDEX prediction_predicted ON prediction (location_id, prediction_timestamp, hour_diff);
+        ",
+    },
+];
+
+const WEATHER_COLUMNS: &[&str] = &["year", "day", "hour", "icon", "precip_intensity", "precip_probability", "temp", "wind_speed", "wind_gust", "humidity", "precip_type", "air_temp", "dew_point", "pressure", "cloud_cover", "wind_bearing", "uv_index", "visibility", "ozone", "summary"];
+const PREDICTION_COLUMNS: &[&str] = &["reading_year", "reading_day", "reading_hour", "prediction_year", "prediction_day", "prediction_hour", "hour_diff", "icon", "precip_intensity", "precip_probability", "temp", "wind_speed", "wind_gust", "humidity", "precip_type", "air_temp", "dew_point", "pressure", "cloud_cover", "wind_bearing", "uv_index", "visibility", "ozone", "summary"];
+
+/// Version of the database after all migrations have been applied
+const DB_VERSION: usize = MIGRATIONS.len();
+
+/// Connection to a weather database
 pub struct DbManager {
     conn: Connection
 }
 
 impl DbManager {
-    pub fn new<T: Into<String>>(db_file: T) -> Result<DbManager, CrateError> {
+    /// Open (or create) the database at `db_file`, `init` must be called before using it
+    ///
+    /// # Errors
+    /// Unable to open file
+    ///
+    pub fn new<T: Into<String>>(db_file: T) -> Result<DbManager, Error> {
         let path = db_file.into();
         trace!("Using database at {}", path);
         Connection::open(&path)
@@ -28,95 +156,331 @@ impl DbManager {
     /// Initialize database
     /// *This must be called before using the database*
     ///
-    /// Will migrates database and update user version if needed
-    ///
-    /// # Panics
-    /// If the current database version is unexpected then will exit process (via `process::exit`, not panic)
+    /// Applies any migrations the database is missing, each in its own transaction,
+    /// and records them in the `migration` table
     ///
     /// # Errors
     /// If any read or write statements fail
+    /// If the database is newer than this program (`Error::SchemaVersion`)
     ///
-    pub fn init(&mut self) -> Result<(), CrateError> {
+    pub fn init(&mut self) -> Result<(), Error> {
         let ver = self.conn.get_user_version()?;
         trace!("Database version starting at {}", ver);
-        match ver {
-            0 => {
-                self.conn.execute(CREATE_WEATHER_TABLE, NO_PARAMS)?;
-                self.conn.execute(CREATE_WEATHER_UNIQUE_INDEX, NO_PARAMS)?;
-                self.conn.execute(CREATE_PREDICATION_TABLE, NO_PARAMS)?;
-                self.conn.execute(CREATE_PREDICATION_UNIQUE_INDEX, NO_PARAMS)?;
-                self.conn.set_user_version(DB_VERSION)?;
-                debug!("Created weather table, set db version to 1");
-            }
-            1 => {
-                trace!("Database up to date");
-            }
-            _ => {
-                error!("Unknown database version: {}", ver);
-                std::process::exit(1);
-            }
+
+        if ver > DB_VERSION {
+            return Err(Error::SchemaVersion { found: ver, supported: DB_VERSION });
+        }
+
+        self.record_earlier_migrations(ver)?;
+
+        let pending: Vec<&Migration> = MIGRATIONS.iter()
+            .filter(|migration| migration.version > ver)
+            .collect();
+
+        if pending.is_empty() {
+            trace!("Database up to date");
+        }
+
+        for migration in pending {
+            let transaction = self.conn.transaction()?;
+            transaction.execute_batch(migration.sql)?;
+            transaction.execute("INSERT INTO migration (version, description, applied_at) VALUES (?,?,?)", params![migration.version as i64, migration.description, Utc::now().timestamp()])?;
+            transaction.set_user_version(migration.version)?;
+            transaction.commit()?;
+            debug!("Applied migration {}: {}", migration.version, migration.description);
         }
 
         Ok(())
     }
 
-    pub fn get_specific_reading(&mut self, year: u16, day: u16, hour: u8) -> Result<Weather, Error> {
-        self.conn.query_row("SELECT id, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type FROM weather WHERE year = ? AND day = ? AND hour = ?", &[year, day, hour as u16], |row| Ok(DbManager::build_weather(row)))
+    /// Create the migration table if needed and add any migrations applied before it existed (with no applied time)
+    fn record_earlier_migrations(&mut self, ver: usize) -> Result<(), rusqlite::Error> {
+        let transaction = self.conn.transaction()?;
+        transaction.execute(CREATE_MIGRATION_TABLE, NO_PARAMS)?;
+        MIGRATIONS.iter()
+            .filter(|migration| migration.version <= ver)
+            .try_for_each(|migration| {
+                transaction.execute("INSERT OR IGNORE INTO migration (version, description) VALUES (?,?)", params![migration.version as i64, migration.description])
+                    .map(|_| ())
+            })?;
+
+        return transaction.commit();
     }
 
-    pub fn get_predictions_for(&mut self, year: u16, day: u16, hour: u8) -> Result<Vec<Prediction>, Error> {
-        let mut statement = self.conn.prepare(&format!("SELECT id, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type FROM prediction WHERE prediction_year = ? AND prediction_day = ? AND prediction_hour = ? ORDER BY hour_diff"))?;
-        let predictions = statement.query_map(&[year, day, hour as u16], |row| {
-            return Ok(DbManager::build_prediction(row));
-        })?
-            .map(|prediction| prediction.unwrap())
-            .collect();
+    /// Return a list of all locations (sorted by id)
+    ///
+    /// # Errors
+    /// Failed to read data
+    ///
+    pub fn get_locations(&mut self) -> Result<Vec<Location>, Error> {
+        let mut statement = self.conn.prepare("SELECT id, name, latitude, longitude, timezone FROM location ORDER BY id ASC")?;
+        let locations = statement.query_map(NO_PARAMS, DbManager::build_location)?
+            .collect::<Result<_, _>>()?;
+
+        return Ok(locations);
+    }
+
+    /// Find location at `coordinates` or create a new one
+    ///
+    /// # Errors
+    /// Failed to read or insert data
+    ///
+    /// # Returns
+    /// ID of location
+    ///
+    pub fn find_or_add_location(&mut self, coordinates: &Coordinates) -> Result<i64, Error> {
+        if let Some(id) = self.find_location(coordinates)? {
+            return Ok(id);
+        }
+        self.conn.execute("INSERT INTO location (name, latitude, longitude, timezone) VALUES (?,?,?,?)", params![coordinates.default_name(), coordinates.latitude, coordinates.longitude, coordinates.timezone])?;
+        debug!("Added location {}", coordinates.default_name());
+        return Ok(self.conn.last_insert_rowid());
+    }
+
+    /// Find location at `coordinates`
+    ///
+    /// # Errors
+    /// Failed to read data
+    ///
+    /// # Returns
+    /// ID of location, `None` if there isn't one
+    ///
+    pub fn find_location(&mut self, coordinates: &Coordinates) -> Result<Option<i64>, Error> {
+        let existing = self.conn.query_row("SELECT id FROM location WHERE latitude = ? AND longitude = ?", params![coordinates.latitude, coordinates.longitude], |row| row.get(0));
+        return match existing {
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            result => Ok(Some(result?))
+        };
+    }
+
+    /// # Errors
+    /// Failed to write data
+    ///
+    pub fn rename_location(&mut self, location_id: i64, name: &str) -> Result<(), Error> {
+        self.conn.execute("UPDATE location SET name = ? WHERE id = ?", params![name, location_id])?;
+
+        Ok(())
+    }
+
+    /// Get the reading for an hour
+    ///
+    /// # Errors
+    /// Failed to read data
+    /// No reading for hour (`Error::MissingReading`)
+    ///
+    pub fn get_specific_reading(&mut self, location_id: i64, year: u16, day: u16, hour: u8) -> Result<Weather, Error> {
+        let timestamp = SimpleDate::new(year, day, hour).timestamp();
+        return self.conn.query_row(&format!("SELECT {} FROM weather WHERE location_id = ? AND timestamp = ?", columns(WEATHER_COLUMNS, "")), params![location_id, timestamp], DbManager::build_weather)
+            .map_err(|err| match err {
+                rusqlite::Error::QueryReturnedNoRows => Error::MissingReading { year, day, hour },
+                err => err.into()
+            });
+    }
+
+    /// Get every prediction for an hour
+    ///
+    /// # Errors
+    /// Failed to read data
+    ///
+    /// # Returns
+    /// List of predictions (sorted by hour diff)
+    ///
+    pub fn get_predictions_for(&mut self, location_id: i64, year: u16, day: u16, hour: u8) -> Result<Vec<Prediction>, Error> {
+        let timestamp = SimpleDate::new(year, day, hour).timestamp();
+        let mut statement = self.conn.prepare(&format!("SELECT {} FROM prediction WHERE location_id = ? AND prediction_timestamp = ? ORDER BY hour_diff", columns(PREDICTION_COLUMNS, "")))?;
+        let predictions = statement.query_map(params![location_id, timestamp], DbManager::build_prediction)?
+            .collect::<Result<_, _>>()?;
 
         return Ok(predictions);
     }
 
-    pub fn get_readings_over_range(&mut self, start: i64, end: i64) -> Result<Vec<Weather>, Error> {
-        let mut statement = self.conn.prepare("SELECT id, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type FROM weather WHERE timestamp >= ? AND timestamp <= ? ORDER BY id ASC")?;
-        let weathers = statement.query_map(&[start, end], |row| Ok(DbManager::build_weather(row)))?
-            .map(|weather| weather.unwrap())
-            .collect();
+    /// Get every reading from `start` to `end` (unix timestamps, inclusive)
+    ///
+    /// # Errors
+    /// Failed to read data
+    ///
+    /// # Returns
+    /// List of readings (oldest to newest)
+    ///
+    pub fn get_readings_over_range(&mut self, location_id: i64, start: i64, end: i64) -> Result<Vec<Weather>, Error> {
+        let mut statement = self.conn.prepare(&format!("SELECT {} FROM weather WHERE location_id = ? AND timestamp >= ? AND timestamp <= ? ORDER BY timestamp ASC", columns(WEATHER_COLUMNS, "")))?;
+        let weathers = statement.query_map([location_id, start, end], DbManager::build_weather)?
+            .collect::<Result<_, _>>()?;
 
         return Ok(weathers);
     }
 
-    pub fn get_readings(&mut self, sort: &str, count: usize) -> Result<Vec<Weather>, Error> {
-        let mut statement = self.conn.prepare(&format!("SELECT id, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type FROM weather ORDER BY id {} LIMIT {}", sort, count))?;
-        let weathers = statement.query_map(NO_PARAMS, |row| Ok(DbManager::build_weather(row)))?
-            .map(|weather| weather.unwrap())
-            .collect();
+    /// Get every prediction for hours from `start` to `end` (inclusive, inclusive)
+    ///
+    /// `start` and `end` are unix timestamps of the predicted hour
+    ///
+    /// # Errors
+    /// Failed to read data
+    ///
+    /// # Returns
+    /// List of predictions (sorted by predicted hour then hour diff)
+    ///
+    pub fn get_predictions_over_range(&mut self, location_id: i64, start: i64, end: i64) -> Result<Vec<Prediction>, Error> {
+        let mut statement = self.conn.prepare(&format!("SELECT {} FROM prediction WHERE location_id = ? AND prediction_timestamp BETWEEN ? AND ? ORDER BY prediction_timestamp, hour_diff", columns(PREDICTION_COLUMNS, "")))?;
+        let predictions = statement.query_map([location_id, start, end], DbManager::build_prediction)?
+            .collect::<Result<_, _>>()?;
+
+        return Ok(predictions);
+    }
+
+    /// Get every prediction that has a matching reading for the hour it predicted
+    ///
+    /// `start` and `end` are unix timestamps (inclusive) of the predicted hour
+    ///
+    /// # Errors
+    /// Failed to read data
+    ///
+    /// # Returns
+    /// List of predictions and the reading for the predicted hour (sorted by predicted hour then hour diff)
+    ///
+    pub fn get_predictions_with_readings(&mut self, location_id: i64, start: i64, end: i64) -> Result<Vec<(Prediction, Weather)>, Error> {
+        let mut statement = self.conn.prepare(&format!("SELECT {}, {} FROM prediction p INNER JOIN weather w ON p.location_id = w.location_id AND p.prediction_timestamp = w.timestamp WHERE w.location_id = ? AND w.timestamp >= ? AND w.timestamp <= ? ORDER BY w.timestamp ASC, p.hour_diff ASC", columns(PREDICTION_COLUMNS, "p."), columns(WEATHER_COLUMNS, "w.")))?;
+        let pairs = statement.query_map([location_id, start, end], |row| Ok((DbManager::build_prediction(row)?, DbManager::build_weather_from(row, PREDICTION_COLUMNS.len())?)))?
+            .collect::<Result<_, _>>()?;
+
+        return Ok(pairs);
+    }
+
+    pub(crate) fn get_readings(&mut self, location_id: i64, sort: &str, count: usize) -> Result<Vec<Weather>, Error> {
+        let mut statement = self.conn.prepare(&format!("SELECT {} FROM weather WHERE location_id = ? ORDER BY timestamp {} LIMIT {}", columns(WEATHER_COLUMNS, ""), sort, count))?;
+        let weathers = statement.query_map([location_id], DbManager::build_weather)?
+            .collect::<Result<_, _>>()?;
 
         return Ok(weathers);
     }
 
     /// Insert weather reading and it's predictions into the database
     ///
+    /// Existing rows for the same hours are replaced
+    /// If `dry_run` is true the transaction is rolled back so nothing is written
+    ///
     /// # Errors
     /// Failed to start transaction
     /// Failed to insert data
     /// Failed to commit transaction
     ///
-    pub fn add_weather(&mut self, weather: Weather, predictions: Vec<Weather>) -> Result<(), CrateError> {
-        let transaction = self.conn.transaction()?;
+    /// # Returns
+    /// Number of rows inserted and replaced
+    ///
+    pub fn add_weather(&mut self, location_id: i64, weather: Weather, predictions: Vec<Weather>, dry_run: bool) -> Result<ImportCounts, Error> {
+        let file = ImportedFile { location_id, readings: vec![(weather, predictions)], payload: None };
+        return self.add_weather_batch(vec![file], dry_run)?.remove(0);
+    }
 
-        DbManager::insert_weather(&transaction, &weather)?;
+    /// Insert the readings and predictions of many files in a single transaction
+    ///
+    /// Each file is written in a savepoint so a file that fails is rolled back without losing the rest of the batch
+    /// A file's payload is stored once per location and linked to each of it's readings,
+    /// payloads no longer linked to any reading are deleted
+    /// Existing rows for the same hours are replaced
+    /// If `dry_run` is true the transaction is rolled back so nothing is written
+    ///
+    /// # Errors
+    /// Failed to start or commit transaction
+    ///
+    /// # Returns
+    /// Number of rows inserted and replaced for each file (in the same order), or why it failed
+    ///
+    pub fn add_weather_batch(&mut self, files: Vec<ImportedFile>, dry_run: bool) -> Result<Vec<Result<ImportCounts, Error>>, Error> {
+        let mut transaction = self.conn.transaction()?;
+        let mut results = Vec::with_capacity(files.len());
+        let mut replaced = false;
 
-        predictions.iter()
-            .enumerate()
-            .try_for_each(|(i, prediction)| {
-                DbManager::insert_prediction(&transaction, &weather, &prediction, i + 1) //plus hour because the first one is the next hour (so diff is 1 not 0)
-            })?;
+        for file in files {
+            let savepoint = transaction.savepoint()?;
+            let mut counts = ImportCounts::default();
+            let result = file.payload.as_ref()
+                .map(|payload| DbManager::insert_payload(&savepoint, file.location_id, payload))
+                .transpose()
+                .and_then(|payload_id| file.readings.iter()
+                    .try_for_each(|(weather, predictions)| DbManager::insert_readings(&savepoint, file.location_id, payload_id, weather, predictions, &mut counts)));
+            // Dropping the savepoint rolls it back
+            if result.is_ok() {
+                savepoint.commit()?;
+            }
+            replaced |= counts.readings_replaced > 0;
+            results.push(result.map(|_| counts));
+        }
+
+        if replaced {
+            transaction.execute("DELETE FROM payload WHERE NOT EXISTS (SELECT 1 FROM weather WHERE weather.payload_id = payload.id)", NO_PARAMS)?;
+        }
+        if dry_run {
+            transaction.rollback()?;
+        } else {
+            transaction.commit()?;
+        }
 
+        return Ok(results);
+    }
+
+    /// Return stored payloads, oldest first
+    ///
+    /// # Errors
+    /// Failed to read data
+    ///
+    /// # Returns
+    /// Up to `count` payloads with an id after `after_id`, each with it's id and location
+    /// Only payloads for `location_id`, if set
+    ///
+    pub fn get_payloads(&mut self, location_id: Option<i64>, after_id: i64, count: usize) -> Result<Vec<(i64, i64, Payload)>, Error> {
+        let mut statement = self.conn.prepare_cached("SELECT id, location_id, format, source, hash, data FROM payload WHERE id > ? AND (? IS NULL OR location_id = ?) ORDER BY id ASC LIMIT ?")?;
+        let payloads = statement.query_map(params![after_id, location_id, location_id, count as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, Payload { format: row.get(2)?, source: row.get(3)?, hash: row.get(4)?, data: row.get(5)? }))
+        })?
+            .collect::<Result<_, _>>()?;
+
+        return Ok(payloads);
+    }
+
+    /// Return every file in the ingest ledger
+    ///
+    /// # Errors
+    /// Failed to read data
+    ///
+    /// # Returns
+    /// Ledger entries by path
+    ///
+    pub fn get_ingested_files(&mut self) -> Result<HashMap<PathBuf, IngestedFile>, Error> {
+        let mut statement = self.conn.prepare("SELECT path, size, modified, hash FROM ingested_file")?;
+        let files = statement.query_map(NO_PARAMS, |row| {
+            let path: String = row.get(0)?;
+            let size: i64 = row.get(1)?;
+            Ok(IngestedFile { path: PathBuf::from(path), size: size as u64, modified: row.get(2)?, hash: row.get(3)? })
+        })?
+            .map(|file| file.map(|file| (file.path.clone(), file)))
+            .collect::<Result<_, _>>()?;
+
+        return Ok(files);
+    }
+
+    /// Add files to the ingest ledger, replacing existing entries for the same path
+    ///
+    /// # Errors
+    /// Failed to start transaction
+    /// Failed to insert data
+    /// Failed to commit transaction
+    ///
+    pub fn add_ingested_files(&mut self, files: &[IngestedFile]) -> Result<(), Error> {
+        let transaction = self.conn.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached("REPLACE INTO ingested_file (path, size, modified, hash, ingested_at) VALUES (?,?,?,?,?)")?;
+            let now = Utc::now().timestamp();
+            for file in files {
+                statement.execute(params![file.path.to_string_lossy(), file.size as i64, file.modified, file.hash, now])?;
+            }
+        }
         transaction.commit()?;
 
-        Ok(())
+        return Ok(());
     }
 
-    /// Return a list of all readings (current, not predictions)
+    /// Return a list of all readings (current, not predictions) for a location
     ///
     /// # Errors
     /// Failed to read data
@@ -124,84 +488,443 @@ impl DbManager {
     /// # Returns
     /// List of all readings (sorted by id, which should be oldest to newest)
     ///
-    pub fn get_all_readings(&mut self) -> Result<Vec<Weather>, CrateError> {
-        let mut statement = self.conn.prepare("SELECT id, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type FROM weather ORDER BY id ASC")?;
-        let weathers = statement.query_map(NO_PARAMS, |row| {
-            return Ok(DbManager::build_weather(row));
-        })?
-            .map(|weather| weather.unwrap())
-            .collect();
+    pub fn get_all_readings(&mut self, location_id: i64) -> Result<Vec<Weather>, Error> {
+        let mut statement = self.conn.prepare(&format!("SELECT {} FROM weather WHERE location_id = ? ORDER BY timestamp ASC", columns(WEATHER_COLUMNS, "")))?;
+        let weathers = statement.query_map([location_id], DbManager::build_weather)?
+            .collect::<Result<_, _>>()?;
 
         return Ok(weathers);
     }
 
-    fn build_weather(row: &Row) -> Weather {
-        let icon: String = row.get_unwrap(4);
-        return Weather::new(
-            row.get_unwrap(0),
-            row.get_unwrap(1),
-            row.get_unwrap(2),
-            row.get_unwrap(3),
+    fn build_weather(row: &Row) -> rusqlite::Result<Weather> {
+        return DbManager::build_weather_from(row, 0);
+    }
+
+    /// Build weather from the row, with the weather columns starting at `offset`
+    fn build_weather_from(row: &Row, offset: usize) -> rusqlite::Result<Weather> {
+        let icon: String = row.get(offset + 3)?;
+        return Ok(Weather::new(
+            row.get(offset)?,
+            row.get(offset + 1)?,
+            row.get(offset + 2)?,
             Icon::from_str(&icon),
-            row.get_unwrap(5),
-            row.get_unwrap(6),
-            row.get_unwrap(7),
-            row.get_unwrap(8),
-            row.get_unwrap(9),
-            row.get_unwrap(10),
-            row.get_unwrap(11)
-        );
-    }
-
-    fn build_prediction(row: &Row) -> Prediction {
-        let icon: String = row.get_unwrap(8);
-        return Prediction::new(
-            row.get_unwrap(0),
-            row.get_unwrap(1),
-            row.get_unwrap(2),
-            row.get_unwrap(3),
-            row.get_unwrap(4),
-            row.get_unwrap(5),
-            row.get_unwrap(6),
-            row.get_unwrap(7),
+            row.get(offset + 4)?,
+            row.get(offset + 5)?,
+            row.get(offset + 6)?,
+            row.get(offset + 7)?,
+            row.get(offset + 8)?,
+            row.get(offset + 9)?,
+            row.get(offset + 10)?
+        ).with_details(DbManager::build_details(row, offset + 11)?));
+    }
+
+    /// Build details from the row, with the detail columns starting at `offset`
+    fn build_details(row: &Row, offset: usize) -> rusqlite::Result<WeatherDetails> {
+        return Ok(WeatherDetails {
+            air_temp: row.get(offset)?,
+            dew_point: row.get(offset + 1)?,
+            pressure: row.get(offset + 2)?,
+            cloud_cover: row.get(offset + 3)?,
+            wind_bearing: row.get(offset + 4)?,
+            uv_index: row.get(offset + 5)?,
+            visibility: row.get(offset + 6)?,
+            ozone: row.get(offset + 7)?,
+            summary: row.get(offset + 8)?,
+        });
+    }
+
+    fn build_location(row: &Row) -> rusqlite::Result<Location> {
+        let latitude: Option<f64> = row.get(2)?;
+        let longitude: Option<f64> = row.get(3)?;
+        let coordinates = match (latitude, longitude) {
+            (Some(latitude), Some(longitude)) => Some(Coordinates { latitude, longitude, timezone: row.get(4)? }),
+            _ => None
+        };
+        return Ok(Location {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            coordinates,
+        });
+    }
+
+    fn build_prediction(row: &Row) -> rusqlite::Result<Prediction> {
+        let icon: String = row.get(7)?;
+        return Ok(Prediction::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get(6)?,
             Icon::from_str(&icon),
-            row.get_unwrap(9),
-            row.get_unwrap(10),
-            row.get_unwrap(11),
-            row.get_unwrap(12),
-            row.get_unwrap(13),
-            row.get_unwrap(14),
-            row.get_unwrap(15)
-        );
+            row.get(8)?,
+            row.get(9)?,
+            row.get(10)?,
+            row.get(11)?,
+            row.get(12)?,
+            row.get(13)?,
+            row.get(14)?
+        ).with_details(DbManager::build_details(row, 15)?));
     }
 
-    fn insert_weather(transaction: &Transaction, weather: &Weather) -> Result<(), CrateError> {
-        let params = params![weather.id, weather.timestamp, weather.year, weather.day, weather.hour, weather.icon, weather.precip_intensity, weather.precip_probability, weather.temp, weather.wind_speed, weather.wind_gust, weather.humidity, weather.precip_type];
-        transaction.execute("REPLACE INTO weather (id, timestamp, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type) VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?)", params)?;
+    /// Insert a reading and the predictions made at that time, adding to `counts`
+    ///
+    /// The lead time of each prediction is found from its timestamp, predictions not 1 to `MAX_HOUR_DIFF` hours after the reading are rejected
+    fn insert_readings(conn: &Connection, location_id: i64, payload_id: Option<i64>, weather: &Weather, predictions: &[Weather], counts: &mut ImportCounts) -> Result<(), Error> {
+        if DbManager::insert_weather(conn, location_id, payload_id, weather)? {
+            counts.readings_replaced += 1;
+        } else {
+            counts.readings_inserted += 1;
+        }
 
-        Ok(())
+        for prediction in predictions {
+            let hour_diff = match lead_time(weather, prediction) {
+                Some(hour_diff) => hour_diff,
+                None => {
+                    counts.predictions_rejected += 1;
+                    continue;
+                }
+            };
+            if DbManager::insert_prediction(conn, location_id, weather, prediction, hour_diff)? {
+                counts.predictions_replaced += 1;
+            } else {
+                counts.predictions_inserted += 1;
+            }
+        }
+
+        return Ok(());
     }
 
-    fn insert_prediction(transaction: &Transaction, origin: &Weather, target: &Weather, hour_diff: usize) -> Result<(), CrateError> {
-        let params = params![target.id, origin.year, origin.day, origin.hour, target.year, target.day, target.hour, hour_diff as u8, target.icon, target.precip_intensity, target.precip_probability, target.temp, target.wind_speed, target.wind_gust, target.humidity, target.precip_type];
-        transaction.execute("REPLACE INTO prediction (id, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type) VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)", params)?;
+    /// Insert `payload` unless the location already has one with the same hash
+    ///
+    /// # Returns
+    /// Id of the payload
+    fn insert_payload(conn: &Connection, location_id: i64, payload: &Payload) -> Result<i64, Error> {
+        conn.prepare_cached("INSERT OR IGNORE INTO payload (location_id, format, source, hash, data) VALUES (?,?,?,?,?)")?
+            .execute(params![location_id, payload.format, payload.source, payload.hash, payload.data])?;
+        let id = conn.prepare_cached("SELECT id FROM payload WHERE location_id = ? AND hash = ?")?
+            .query_row(params![location_id, payload.hash], |row| row.get(0))?;
 
-        Ok(())
+        return Ok(id);
+    }
+
+    /// # Returns
+    /// True if an existing reading was replaced
+    fn insert_weather(conn: &Connection, location_id: i64, payload_id: Option<i64>, weather: &Weather) -> Result<bool, Error> {
+        let existing: i64 = conn.prepare_cached("SELECT COUNT(*) FROM weather WHERE location_id = ? AND timestamp = ?")?
+            .query_row(params![location_id, weather.timestamp], |row| row.get(0))?;
+        let details = &weather.details;
+        let params = params![location_id, weather.timestamp, weather.year, weather.day, weather.hour, weather.icon, weather.precip_intensity, weather.precip_probability, weather.temp, weather.wind_speed, weather.wind_gust, weather.humidity, weather.precip_type,
+            details.air_temp, details.dew_point, details.pressure, details.cloud_cover, details.wind_bearing, details.uv_index, details.visibility, details.ozone, details.summary, payload_id];
+        conn.prepare_cached("REPLACE INTO weather (location_id, timestamp, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type, air_temp, dew_point, pressure, cloud_cover, wind_bearing, uv_index, visibility, ozone, summary, payload_id) VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)")?
+            .execute(params)?;
+
+        return Ok(existing > 0);
+    }
+
+    /// # Returns
+    /// True if an existing prediction was replaced
+    fn insert_prediction(conn: &Connection, location_id: i64, origin: &Weather, target: &Weather, hour_diff: u8) -> Result<bool, Error> {
+        let existing: i64 = conn.prepare_cached("SELECT COUNT(*) FROM prediction WHERE location_id = ? AND reading_timestamp = ? AND prediction_timestamp = ?")?
+            .query_row(params![location_id, origin.timestamp, target.timestamp], |row| row.get(0))?;
+        let details = &target.details;
+        let params = params![location_id, origin.timestamp, target.timestamp, origin.year, origin.day, origin.hour, target.year, target.day, target.hour, hour_diff, target.icon, target.precip_intensity, target.precip_probability, target.temp, target.wind_speed, target.wind_gust, target.humidity, target.precip_type,
+            details.air_temp, details.dew_point, details.pressure, details.cloud_cover, details.wind_bearing, details.uv_index, details.visibility, details.ozone, details.summary];
+        conn.prepare_cached("REPLACE INTO prediction (location_id, reading_timestamp, prediction_timestamp, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type, air_temp, dew_point, pressure, cloud_cover, wind_bearing, uv_index, visibility, ozone, summary) VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)")?
+            .execute(params)?;
+
+        return Ok(existing > 0);
     }
 }
 
+/// Comma separated list of `names`, each with `prefix` (e.g. `w.`)
+fn columns(names: &[&str], prefix: &str) -> String {
+    return names.iter()
+        .map(|name| format!("{}{}", prefix, name))
+        .collect::<Vec<String>>()
+        .join(", ");
+}
+
 trait UserVersion {
-    fn get_user_version(&self) -> Result<usize, Error>;
+    fn get_user_version(&self) -> Result<usize, rusqlite::Error>;
 
-    fn set_user_version(&mut self, version: usize) -> Result<(), Error>;
+    fn set_user_version(&self, version: usize) -> Result<(), rusqlite::Error>;
 }
 
 impl UserVersion for Connection {
-    fn get_user_version(&self) -> Result<usize, Error> {
+    fn get_user_version(&self) -> Result<usize, rusqlite::Error> {
         self.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get(0).map(|ver: i64| ver as usize))
     }
 
-    fn set_user_version(&mut self, version: usize) -> Result<(), Error> {
-        self.execute(&format!("PRAGMA user_version = {}", version), NO_PARAMS).and_then(|_| Ok(()))
+    fn set_user_version(&self, version: usize) -> Result<(), rusqlite::Error> {
+        self.execute(&format!("PRAGMA user_version = {}", version), NO_PARAMS).map(|_| ())
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn weather(hour: u8, temp: f64) -> Weather {
+        return Weather::new(2019, 326, hour, Icon::Clear, 0., 0., temp, 0., 0., 0., None);
+    }
+
+    fn weather_at(year: u16, day: u16, hour: u8) -> Weather {
+        return Weather::new(year, day, hour, Icon::Clear, 0., 0., 0., 0., 0., 0., None);
     }
-}
\ No newline at end of file
+
+    /// Versions of all applied migrations and if they have an applied time
+    fn applied_migrations(db_manager: &DbManager) -> Vec<(usize, bool)> {
+        let mut statement = db_manager.conn.prepare("SELECT version, applied_at FROM migration ORDER BY version ASC").unwrap();
+        return statement.query_map(NO_PARAMS, |row| Ok((row.get_unwrap::<_, i64>(0) as usize, row.get_unwrap::<_, Option<i64>>(1).is_some())))
+            .unwrap()
+            .map(|migration| migration.unwrap())
+            .collect();
+    }
+
+    #[test]
+    fn test_new_database() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+
+        assert_eq!(db_manager.conn.get_user_version().unwrap(), DB_VERSION);
+        let applied = applied_migrations(&db_manager);
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert!(applied.iter().all(|(_, has_time)| *has_time));
+
+        db_manager.init().unwrap();
+        assert_eq!(applied_migrations(&db_manager), applied, "running again changes nothing");
+        assert!(db_manager.get_locations().unwrap().is_empty(), "no placeholder location without data");
+    }
+
+    #[test]
+    fn test_migrate_v1_fixture() {
+        let dir = TempDir::new("migrate_v1");
+        let path = dir.copy_fixture("weather_v1.db");
+        let mut db_manager = DbManager::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(db_manager.conn.get_user_version().unwrap(), 1);
+
+        db_manager.init().unwrap();
+
+        assert_eq!(db_manager.conn.get_user_version().unwrap(), DB_VERSION);
+        assert_eq!(applied_migrations(&db_manager)[..2], [(1, false), (2, true)], "migrations from before they were recorded have no time");
+
+        let locations = db_manager.get_locations().unwrap();
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].name, "Unknown");
+        assert_eq!(locations[0].coordinates, None);
+
+        let readings = db_manager.get_all_readings(locations[0].id).unwrap();
+        assert_eq!(readings.len(), 1);
+        let reading = &readings[0];
+        assert_eq!((reading.year, reading.day, reading.hour), (2019, 326, 4));
+        assert_eq!(reading.timestamp, 1574395200);
+        assert_eq!(reading.temp, 3.69);
+
+        let predictions = db_manager.get_predictions_for(locations[0].id, 2019, 327, 4).unwrap();
+        assert_eq!(predictions.len(), 1);
+        assert_eq!((predictions[0].reading_timestamp, predictions[0].prediction_timestamp), (1574395200, 1574481600));
+        assert_eq!(i64::from(predictions[0].hour_diff), (predictions[0].prediction_timestamp - predictions[0].reading_timestamp) / 3600, "hour diff is recomputed from the timestamps");
+    }
+
+    #[test]
+    fn test_migrate_text_ids_to_timestamps() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        for migration in &MIGRATIONS[..5] {
+            db_manager.conn.execute_batch(migration.sql).unwrap();
+        }
+        db_manager.conn.set_user_version(5).unwrap();
+        db_manager.conn.execute_batch("
+            INSERT INTO location (id, name) VALUES (1, 'Unknown');
+            INSERT INTO weather (id, location_id, timestamp, year, day, hour, icon, temp, precip_intensity, precip_probability, wind_speed, wind_gust, humidity) VALUES
+                ('2020-10-9', 1, 0, 2020, 10, 9, 'clear', 4., 0, 0, 0, 0, 0),
+                ('2020-009-10', 1, 0, 2020, 9, 10, 'clear', 3., 0, 0, 0, 0, 0),
+                ('2020-366-23', 1, 0, 2020, 366, 23, 'clear', 6., 0, 0, 0, 0, 0),
+                ('2019-365-23', 1, 0, 2019, 365, 23, 'clear', 1., 0, 0, 0, 0, 0),
+                ('2020-1-0', 1, 0, 2020, 1, 0, 'clear', 2., 0, 0, 0, 0, 0),
+                ('2020-100-0', 1, 0, 2020, 100, 0, 'clear', 5., 0, 0, 0, 0, 0);
+            INSERT INTO prediction (id, location_id, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, temp, precip_intensity, precip_probability, wind_speed, wind_gust, humidity) VALUES
+                ('2020-1-0-2019-365-23', 1, 2019, 365, 23, 2020, 1, 0, 9, 'clear', 2., 0, 0, 0, 0, 0),
+                ('2020-10-9-2020-1-0', 1, 2020, 1, 0, 2020, 10, 9, 9, 'clear', 4., 0, 0, 0, 0, 0),
+                ('2019-365-23-2020-1-0', 1, 2020, 1, 0, 2019, 365, 23, 1, 'clear', 1., 0, 0, 0, 0, 0);
+        ").unwrap();
+
+        db_manager.init().unwrap();
+
+        let readings = db_manager.get_all_readings(1).unwrap();
+        let temps: Vec<f64> = readings.iter().map(|weather| weather.temp).collect();
+        assert_eq!(temps, vec![1., 2., 3., 4., 5., 6.], "ordered by time, not by text id");
+        assert!(readings.iter().all(|weather| weather.timestamp == weather.simple_date().timestamp()), "timestamps match year, day and hour");
+        assert_eq!(readings[1].timestamp - readings[0].timestamp, 3600, "new year is the next hour");
+        assert_eq!(readings[5].timestamp, 1609455600, "leap day 366 is 2020-12-31");
+
+        let predictions = db_manager.get_predictions_for(1, 2020, 1, 0).unwrap();
+        assert_eq!(predictions.len(), 1);
+        assert_eq!(predictions[0].prediction_timestamp - predictions[0].reading_timestamp, 3600);
+        assert_eq!(predictions[0].hour_diff, 1, "stale hour diff is replaced");
+        assert!(db_manager.get_predictions_for(1, 2020, 10, 9).unwrap().is_empty(), "lead time over the maximum is dropped");
+        assert!(db_manager.get_predictions_for(1, 2019, 365, 23).unwrap().is_empty(), "prediction before its reading is dropped");
+    }
+
+    #[test]
+    fn test_readings_are_ordered_by_time() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let location_id = db_manager.find_or_add_location(&Coordinates { latitude: 51.4, longitude: -2.6, timezone: None }).unwrap();
+        let hours = [(2020, 100, 0), (2020, 9, 10), (2019, 365, 23), (2020, 366, 23), (2020, 10, 9), (2020, 1, 0), (2020, 99, 23)];
+        for &(year, day, hour) in &hours {
+            db_manager.add_weather(location_id, weather_at(year, day, hour), vec![], false).unwrap();
+        }
+        db_manager.add_weather(location_id, weather_at(2019, 365, 22), vec![weather_at(2019, 365, 23), weather_at(2020, 1, 0), weather_at(2020, 1, 1)], false).unwrap();
+
+        let ordered: Vec<(u16, u16, u8)> = db_manager.get_all_readings(location_id).unwrap().iter().map(|weather| weather.simple_date().into()).collect();
+        assert_eq!(ordered, vec![(2019, 365, 22), (2019, 365, 23), (2020, 1, 0), (2020, 9, 10), (2020, 10, 9), (2020, 99, 23), (2020, 100, 0), (2020, 366, 23)]);
+        assert_eq!(db_manager.get_readings(location_id, "DESC", 1).unwrap()[0].day, 366);
+        assert_eq!(db_manager.get_readings(location_id, "ASC", 1).unwrap()[0].year, 2019);
+
+        let new_year = db_manager.get_readings_over_range(location_id, SimpleDate::new(2019, 365, 23).timestamp(), SimpleDate::new(2020, 1, 0).timestamp()).unwrap();
+        assert_eq!(new_year.len(), 2, "range crosses the new year");
+
+        let predictions = db_manager.get_predictions_over_range(location_id, SimpleDate::new(2020, 1, 0).timestamp(), SimpleDate::new(2020, 1, 1).timestamp()).unwrap();
+        let predicted: Vec<(u16, u16, u8)> = predictions.iter().map(|prediction| (prediction.prediction_year, prediction.prediction_day, prediction.prediction_hour)).collect();
+        assert_eq!(predicted, vec![(2020, 1, 0), (2020, 1, 1)]);
+        assert_eq!(db_manager.get_predictions_with_readings(location_id, SimpleDate::new(2019, 365, 23).timestamp(), SimpleDate::new(2020, 1, 1).timestamp()).unwrap().len(), 2, "predictions join readings across the new year");
+    }
+
+    #[test]
+    fn test_newer_database_is_rejected() {
+        let dir = TempDir::new("newer");
+        let path = dir.copy_fixture("weather_v1.db");
+        let mut db_manager = DbManager::new(path.to_str().unwrap()).unwrap();
+        db_manager.conn.set_user_version(DB_VERSION + 1).unwrap();
+
+        match db_manager.init() {
+            Err(Error::SchemaVersion { found, supported }) => assert_eq!((found, supported), (DB_VERSION + 1, DB_VERSION)),
+            result => panic!("Expected schema version error, got {:?}", result)
+        }
+        assert_eq!(db_manager.conn.get_user_version().unwrap(), DB_VERSION + 1, "database is left untouched");
+    }
+
+    #[test]
+    fn test_add_weather_counts() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let location_id = db_manager.find_or_add_location(&Coordinates { latitude: 51.4, longitude: -2.6, timezone: None }).unwrap();
+
+        let dry_run = db_manager.add_weather(location_id, weather(10, 5.), vec![weather(11, 6.), weather(12, 7.)], true).unwrap();
+        assert_eq!(dry_run, ImportCounts { readings_inserted: 1, readings_replaced: 0, predictions_inserted: 2, predictions_replaced: 0, predictions_rejected: 0 });
+        assert!(db_manager.get_all_readings(location_id).unwrap().is_empty(), "dry run writes nothing");
+
+        db_manager.add_weather(location_id, weather(10, 5.), vec![weather(11, 6.)], false).unwrap();
+        let counts = db_manager.add_weather(location_id, weather(10, 5.5), vec![weather(11, 6.), weather(12, 7.)], false).unwrap();
+        assert_eq!(counts, ImportCounts { readings_inserted: 0, readings_replaced: 1, predictions_inserted: 1, predictions_replaced: 1, predictions_rejected: 0 });
+        assert_eq!(db_manager.get_all_readings(location_id).unwrap()[0].temp, 5.5);
+    }
+
+    #[test]
+    fn test_hour_diff_from_timestamps() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let location_id = db_manager.find_or_add_location(&Coordinates { latitude: 51.4, longitude: -2.6, timezone: None }).unwrap();
+
+        // Hour 12 is missing and hours 10 and 9 aren't after the reading
+        let counts = db_manager.add_weather(location_id, weather(10, 5.), vec![weather(11, 6.), weather(13, 7.), weather(10, 5.), weather(9, 4.)], false).unwrap();
+        assert_eq!(counts, ImportCounts { readings_inserted: 1, readings_replaced: 0, predictions_inserted: 2, predictions_replaced: 0, predictions_rejected: 2 });
+
+        let hour_diffs: Vec<u8> = db_manager.get_predictions_over_range(location_id, SimpleDate::new(2019, 326, 0).timestamp(), SimpleDate::new(2019, 326, 23).timestamp()).unwrap()
+            .iter()
+            .map(|prediction| prediction.hour_diff)
+            .collect();
+        assert_eq!(hour_diffs, vec![1, 3], "lead time isn't shifted by the gap");
+    }
+
+    #[test]
+    fn test_payloads() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let location_id = db_manager.find_or_add_location(&Coordinates { latitude: 51.4, longitude: -2.6, timezone: None }).unwrap();
+        let file = |hours: &[u8], text: &str| ImportedFile {
+            location_id,
+            readings: hours.iter().map(|hour| (weather(*hour, 5.), vec![])).collect(),
+            payload: Some(Payload::new("dark-sky", std::path::Path::new("a.json"), text.to_owned(), text).unwrap()),
+        };
+
+        // The same document is only stored once
+        db_manager.add_weather_batch(vec![file(&[10, 11], "first"), file(&[10, 11], "first")], false).unwrap();
+        let payloads = db_manager.get_payloads(None, 0, 10).unwrap();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].2.text().unwrap(), "first");
+
+        // Still linked to hour 11
+        db_manager.add_weather_batch(vec![file(&[10], "second")], false).unwrap();
+        assert_eq!(db_manager.get_payloads(None, 0, 10).unwrap().len(), 2);
+
+        // No longer linked to any reading
+        db_manager.add_weather_batch(vec![file(&[11], "third")], false).unwrap();
+        let sources: Vec<String> = db_manager.get_payloads(Some(location_id), 0, 10).unwrap().into_iter().map(|(_, _, payload)| payload.hash).collect();
+        assert_eq!(sources, vec!["second", "third"]);
+        assert!(db_manager.get_payloads(Some(location_id + 1), 0, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_missing_reading() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let location_id = db_manager.find_or_add_location(&Coordinates { latitude: 51.4, longitude: -2.6, timezone: None }).unwrap();
+        db_manager.add_weather(location_id, weather(10, 5.), vec![], false).unwrap();
+
+        assert_eq!(db_manager.get_specific_reading(location_id, 2019, 326, 10).unwrap().temp, 5.);
+        assert!(matches!(db_manager.get_specific_reading(location_id, 2019, 326, 11), Err(Error::MissingReading { year: 2019, day: 326, hour: 11 })));
+        assert!(db_manager.get_readings(location_id + 1, "ASC", 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_details_are_stored() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let location_id = db_manager.find_or_add_location(&Coordinates { latitude: 51.4, longitude: -2.6, timezone: None }).unwrap();
+
+        let details = WeatherDetails {
+            air_temp: Some(6.5),
+            pressure: Some(993.7),
+            cloud_cover: Some(0.66),
+            summary: Some(String::from("Mostly Cloudy")),
+            ..WeatherDetails::default()
+        };
+        db_manager.add_weather(location_id, weather(10, 5.).with_details(details.clone()), vec![weather(11, 6.).with_details(details.clone())], false).unwrap();
+
+        assert_eq!(db_manager.get_specific_reading(location_id, 2019, 326, 10).unwrap().details, details);
+        assert_eq!(db_manager.get_predictions_for(location_id, 2019, 326, 11).unwrap()[0].details, details);
+        assert_eq!(db_manager.get_readings(location_id, "ASC", 1).unwrap()[0].details.dew_point, None);
+    }
+
+    #[test]
+    fn test_locations_are_separate() {
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+
+        let bristol = Coordinates { latitude: 51.4, longitude: -2.6, timezone: Some(String::from("Europe/London")) };
+        let london = Coordinates { latitude: 51.5, longitude: -0.12, timezone: None };
+        let bristol_id = db_manager.find_or_add_location(&bristol).unwrap();
+        let london_id = db_manager.find_or_add_location(&london).unwrap();
+
+        assert_ne!(bristol_id, london_id);
+        assert_eq!(db_manager.find_or_add_location(&bristol).unwrap(), bristol_id, "existing location is reused");
+
+        db_manager.add_weather(bristol_id, weather(10, 5.), vec![weather(11, 6.)], false).unwrap();
+        db_manager.add_weather(london_id, weather(10, 8.), vec![], false).unwrap();
+
+        assert_eq!(db_manager.get_specific_reading(bristol_id, 2019, 326, 10).unwrap().temp, 5.);
+        assert_eq!(db_manager.get_specific_reading(london_id, 2019, 326, 10).unwrap().temp, 8.);
+        assert_eq!(db_manager.get_predictions_for(bristol_id, 2019, 326, 11).unwrap().len(), 1);
+        assert_eq!(db_manager.get_predictions_over_range(bristol_id, SimpleDate::new(2019, 326, 0).timestamp(), SimpleDate::new(2019, 326, 11).timestamp()).unwrap().len(), 1);
+        assert!(db_manager.get_predictions_over_range(bristol_id, SimpleDate::new(2019, 326, 12).timestamp(), SimpleDate::new(2019, 327, 0).timestamp()).unwrap().is_empty());
+        assert!(db_manager.get_predictions_for(london_id, 2019, 326, 11).unwrap().is_empty());
+
+        let locations = db_manager.get_locations().unwrap();
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[0].name, "Europe/London (51.40, -2.60)");
+        assert_eq!(locations[1].coordinates, Some(london));
+    }
+}
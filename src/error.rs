@@ -0,0 +1,210 @@
+//! [`Error`] returned by every fallible function in this library
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// Everything that can go wrong reading, importing, analysing or exporting weather
+#[derive(Debug)]
+pub enum Error {
+    /// No readings stored for the location
+    EmptyDatabase,
+    /// No reading stored for the hour
+    MissingReading {
+        /// UTC year
+        year: u16,
+        /// UTC day of year
+        day: u16,
+        /// UTC hour
+        hour: u8,
+    },
+    /// Input could not be parsed
+    Parse {
+        /// File being parsed, if known
+        file: Option<PathBuf>,
+        /// Line (starting at 1) the error was found on, if known
+        line: Option<usize>,
+        /// What was wrong with the input
+        message: String,
+    },
+    /// Database was written by a newer version of this program
+    SchemaVersion {
+        /// Version of the database
+        found: usize,
+        /// Latest version this program can open
+        supported: usize,
+    },
+    /// Failed to read or write a file
+    Io(std::io::Error),
+    /// SQLite error
+    Database(rusqlite::Error),
+    /// Failed to convert or write exported rows
+    Export(String),
+    /// Invalid argument, such as an unknown format or location
+    Invalid(String),
+    /// HTTP request failed or returned an error status
+    Http(String),
+}
+
+impl Error {
+    /// Parse error with no file or line
+    pub fn parse<S: Into<String>>(message: S) -> Error {
+        return Error::Parse { file: None, line: None, message: message.into() };
+    }
+
+    /// Set the file of a parse error, other errors are returned unchanged
+    pub fn in_file(self, path: &Path) -> Error {
+        return match self {
+            Error::Parse { line, message, .. } => Error::Parse { file: Some(path.to_path_buf()), line, message },
+            err => err
+        };
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            Error::EmptyDatabase => write!(f, "No readings in database"),
+            Error::MissingReading { year, day, hour } => write!(f, "No reading for {} day {} hour {}", year, day, hour),
+            Error::Parse { file, line, message } => {
+                write!(f, "Unable to parse")?;
+                if let Some(file) = file {
+                    write!(f, " {}", file.to_string_lossy())?;
+                }
+                if let Some(line) = line {
+                    write!(f, " (line {})", line)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::SchemaVersion { found, supported } => write!(f, "Database is version {} but this program only supports up to version {}, please update", found, supported),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Database(err) => write!(f, "Database error: {}", err),
+            Error::Export(message) => write!(f, "Unable to export: {}", message),
+            Error::Invalid(message) => write!(f, "{}", message),
+            Error::Http(message) => write!(f, "Unable to fetch: {}", message),
+        };
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            Error::Io(err) => Some(err),
+            Error::Database(err) => Some(err),
+            _ => None
+        };
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        return Error::Io(err);
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        return Error::Database(err);
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            return Error::Io(err.into());
+        }
+        // serde_json appends the position to the message, it's stored separately here
+        let message = err.to_string();
+        let position = format!(" at line {} column {}", err.line(), err.column());
+        return Error::Parse {
+            file: None,
+            line: if err.line() > 0 { Some(err.line()) } else { None },
+            message: message.strip_suffix(&position).unwrap_or(&message).to_owned(),
+        };
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        return Error::Export(err.to_string());
+    }
+}
+
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        return Error::Export(err.to_string());
+    }
+}
+
+impl From<arrow_schema::ArrowError> for Error {
+    fn from(err: arrow_schema::ArrowError) -> Self {
+        return Error::Export(err.to_string());
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(err: zip::result::ZipError) -> Self {
+        return match err {
+            zip::result::ZipError::Io(err) => Error::Io(err),
+            err => Error::parse(err.to_string())
+        };
+    }
+}
+
+impl From<globset::Error> for Error {
+    fn from(err: globset::Error) -> Self {
+        return Error::Invalid(err.to_string());
+    }
+}
+
+impl From<ureq::Error> for Error {
+    fn from(err: ureq::Error) -> Self {
+        // The URL isn't included as it may contain an API key
+        return match err {
+            ureq::Error::Status(code, response) => Error::Http(format!("{} {}", code, response.status_text())),
+            ureq::Error::Transport(transport) => Error::Http(match transport.message() {
+                Some(message) => format!("{}: {}", transport.kind(), message),
+                None => transport.kind().to_string()
+            })
+        };
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        return Error::Invalid(message);
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        return Error::Invalid(message.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_error_has_line() {
+        let err: Error = serde_json::from_str::<serde_json::Value>("{\n\"a\": 1,\n\"b\": }").unwrap_err().into();
+        let err = err.in_file(Path::new("data/file.json"));
+
+        match &err {
+            Error::Parse { file, line, message } => {
+                assert_eq!(file.as_deref(), Some(Path::new("data/file.json")));
+                assert_eq!(*line, Some(3));
+                assert_eq!(message, "expected value");
+            }
+            err => panic!("Expected parse error, got {:?}", err)
+        }
+        assert_eq!(err.to_string(), "Unable to parse data/file.json (line 3): expected value");
+    }
+
+    #[test]
+    fn test_in_file_only_changes_parse_errors() {
+        assert!(matches!(Error::EmptyDatabase.in_file(Path::new("a.json")), Error::EmptyDatabase));
+        assert!(matches!(Error::parse("bad").in_file(Path::new("a.json")), Error::Parse { file: Some(_), line: None, .. }));
+    }
+}
@@ -0,0 +1,494 @@
+//! Writing readings and predictions as CSV, JSON Lines or Parquet
+
+use crate::models::{Weather, Prediction};
+use crate::Error;
+use serde::Serialize;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+use arrow_schema::{Schema, Field, DataType};
+use parquet::arrow::ArrowWriter;
+
+/// Rows are converted to parquet in batches of this size
+const PARQUET_BATCH_SIZE: usize = 8192;
+
+/// What to export
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExportKind {
+    /// One row per reading
+    Readings,
+    /// One row per prediction
+    Predictions,
+    /// One row per prediction that has a reading for the hour it predicted, with both values
+    Joined,
+}
+
+impl ExportKind {
+    /// Name of each kind, as accepted by `from_str`
+    pub const NAMES: [&'static str; 3] = ["readings", "predictions", "joined"];
+}
+
+impl FromStr for ExportKind {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return match value {
+            "readings" => Ok(ExportKind::Readings),
+            "predictions" => Ok(ExportKind::Predictions),
+            "joined" => Ok(ExportKind::Joined),
+            _ => Err(Error::from(format!("Unknown export type '{}', expected one of: {}", value, ExportKind::NAMES.join(", "))))
+        };
+    }
+}
+
+/// File format to export as
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExportFormat {
+    /// Comma separated values with a header row, missing values are empty
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+    /// Apache Parquet, a column based file
+    Parquet,
+}
+
+impl ExportFormat {
+    /// Name of each format, as accepted by `from_str` (also the file extension)
+    pub const NAMES: [&'static str; 3] = ["csv", "jsonl", "parquet"];
+
+    /// Format matching the extension of `path`, if any
+    pub fn for_path(path: &str) -> Option<ExportFormat> {
+        let extension = path.rsplit('.').next()?;
+        return ExportFormat::from_str(&extension.to_lowercase()).ok();
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        return match value {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" => Ok(ExportFormat::JsonLines),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(Error::from(format!("Unknown export format '{}', expected one of: {}", value, ExportFormat::NAMES.join(", "))))
+        };
+    }
+}
+
+/// Row that can be exported in any `ExportFormat`
+pub trait ExportRow: Serialize {
+    /// Arrow schema matching the serialized row, used for parquet
+    fn schema() -> Schema;
+}
+
+/// Build schema from `(name, type, nullable)`
+fn schema(fields: &[(&str, DataType, bool)]) -> Schema {
+    return Schema::new(fields.iter()
+        .map(|(name, data_type, nullable)| Field::new(*name, data_type.clone(), *nullable))
+        .collect::<Vec<Field>>());
+}
+
+/// Reading and its details, the fields match [`Weather`]
+#[derive(Debug, Serialize)]
+pub struct ReadingRow {
+    location_id: i64,
+    /// Unix timestamp of the hour
+    timestamp: i64,
+    year: u16,
+    day: u16,
+    hour: u8,
+    icon: &'static str,
+    precip_intensity: f64,
+    precip_probability: f64,
+    precip_type: Option<String>,
+    temp: f64,
+    wind_speed: f64,
+    wind_gust: f64,
+    humidity: f64,
+    air_temp: Option<f64>,
+    dew_point: Option<f64>,
+    pressure: Option<f64>,
+    cloud_cover: Option<f64>,
+    wind_bearing: Option<f64>,
+    uv_index: Option<f64>,
+    visibility: Option<f64>,
+    ozone: Option<f64>,
+    summary: Option<String>,
+}
+
+impl ReadingRow {
+    /// Row for `weather` at `location_id`
+    pub fn new(location_id: i64, weather: &Weather) -> ReadingRow {
+        let details = &weather.details;
+        return ReadingRow {
+            location_id,
+            timestamp: weather.timestamp,
+            year: weather.year,
+            day: weather.day,
+            hour: weather.hour,
+            icon: weather.icon.to_str(),
+            precip_intensity: weather.precip_intensity,
+            precip_probability: weather.precip_probability,
+            precip_type: weather.precip_type.clone(),
+            temp: weather.temp,
+            wind_speed: weather.wind_speed,
+            wind_gust: weather.wind_gust,
+            humidity: weather.humidity,
+            air_temp: details.air_temp,
+            dew_point: details.dew_point,
+            pressure: details.pressure,
+            cloud_cover: details.cloud_cover,
+            wind_bearing: details.wind_bearing,
+            uv_index: details.uv_index,
+            visibility: details.visibility,
+            ozone: details.ozone,
+            summary: details.summary.clone(),
+        };
+    }
+}
+
+impl ExportRow for ReadingRow {
+    fn schema() -> Schema {
+        return schema(&[
+            ("location_id", DataType::Int64, false),
+            ("timestamp", DataType::Int64, false),
+            ("year", DataType::UInt16, false),
+            ("day", DataType::UInt16, false),
+            ("hour", DataType::UInt8, false),
+            ("icon", DataType::Utf8, false),
+            ("precip_intensity", DataType::Float64, false),
+            ("precip_probability", DataType::Float64, false),
+            ("precip_type", DataType::Utf8, true),
+            ("temp", DataType::Float64, false),
+            ("wind_speed", DataType::Float64, false),
+            ("wind_gust", DataType::Float64, false),
+            ("humidity", DataType::Float64, false),
+            ("air_temp", DataType::Float64, true),
+            ("dew_point", DataType::Float64, true),
+            ("pressure", DataType::Float64, true),
+            ("cloud_cover", DataType::Float64, true),
+            ("wind_bearing", DataType::Float64, true),
+            ("uv_index", DataType::Float64, true),
+            ("visibility", DataType::Float64, true),
+            ("ozone", DataType::Float64, true),
+            ("summary", DataType::Utf8, true),
+        ]);
+    }
+}
+
+/// Prediction and its details, the fields match [`Prediction`]
+#[derive(Debug, Serialize)]
+pub struct PredictionRow {
+    location_id: i64,
+    /// Unix timestamp of the hour the prediction was made
+    reading_timestamp: i64,
+    /// Unix timestamp of the hour being predicted
+    prediction_timestamp: i64,
+    hour_diff: u8,
+    icon: &'static str,
+    precip_intensity: f64,
+    precip_probability: f64,
+    precip_type: Option<String>,
+    temp: f64,
+    wind_speed: f64,
+    wind_gust: f64,
+    humidity: f64,
+    air_temp: Option<f64>,
+    dew_point: Option<f64>,
+    pressure: Option<f64>,
+    cloud_cover: Option<f64>,
+    wind_bearing: Option<f64>,
+    uv_index: Option<f64>,
+    visibility: Option<f64>,
+    ozone: Option<f64>,
+    summary: Option<String>,
+}
+
+impl PredictionRow {
+    /// Row for `prediction` at `location_id`
+    pub fn new(location_id: i64, prediction: &Prediction) -> PredictionRow {
+        let details = &prediction.details;
+        return PredictionRow {
+            location_id,
+            reading_timestamp: prediction.reading_timestamp,
+            prediction_timestamp: prediction.prediction_timestamp,
+            hour_diff: prediction.hour_diff,
+            icon: prediction.icon.to_str(),
+            precip_intensity: prediction.precip_intensity,
+            precip_probability: prediction.precip_probability,
+            precip_type: prediction.precip_type.clone(),
+            temp: prediction.temp,
+            wind_speed: prediction.wind_speed,
+            wind_gust: prediction.wind_gust,
+            humidity: prediction.humidity,
+            air_temp: details.air_temp,
+            dew_point: details.dew_point,
+            pressure: details.pressure,
+            cloud_cover: details.cloud_cover,
+            wind_bearing: details.wind_bearing,
+            uv_index: details.uv_index,
+            visibility: details.visibility,
+            ozone: details.ozone,
+            summary: details.summary.clone(),
+        };
+    }
+}
+
+impl ExportRow for PredictionRow {
+    fn schema() -> Schema {
+        return schema(&[
+            ("location_id", DataType::Int64, false),
+            ("reading_timestamp", DataType::Int64, false),
+            ("prediction_timestamp", DataType::Int64, false),
+            ("hour_diff", DataType::UInt8, false),
+            ("icon", DataType::Utf8, false),
+            ("precip_intensity", DataType::Float64, false),
+            ("precip_probability", DataType::Float64, false),
+            ("precip_type", DataType::Utf8, true),
+            ("temp", DataType::Float64, false),
+            ("wind_speed", DataType::Float64, false),
+            ("wind_gust", DataType::Float64, false),
+            ("humidity", DataType::Float64, false),
+            ("air_temp", DataType::Float64, true),
+            ("dew_point", DataType::Float64, true),
+            ("pressure", DataType::Float64, true),
+            ("cloud_cover", DataType::Float64, true),
+            ("wind_bearing", DataType::Float64, true),
+            ("uv_index", DataType::Float64, true),
+            ("visibility", DataType::Float64, true),
+            ("ozone", DataType::Float64, true),
+            ("summary", DataType::Utf8, true),
+        ]);
+    }
+}
+
+/// Prediction and the observed weather for the hour it predicted, values are prefixed with `predicted_` and `observed_`
+#[derive(Debug, Serialize)]
+pub struct JoinedRow {
+    location_id: i64,
+    /// Unix timestamp of the hour the prediction was made
+    reading_timestamp: i64,
+    /// Unix timestamp of the hour being predicted
+    timestamp: i64,
+    hour_diff: u8,
+    predicted_icon: &'static str,
+    observed_icon: &'static str,
+    predicted_precip_intensity: f64,
+    observed_precip_intensity: f64,
+    predicted_precip_probability: f64,
+    observed_precip_type: Option<String>,
+    predicted_temp: f64,
+    observed_temp: f64,
+    predicted_wind_speed: f64,
+    observed_wind_speed: f64,
+    predicted_wind_gust: f64,
+    observed_wind_gust: f64,
+    predicted_humidity: f64,
+    observed_humidity: f64,
+    predicted_air_temp: Option<f64>,
+    observed_air_temp: Option<f64>,
+    predicted_pressure: Option<f64>,
+    observed_pressure: Option<f64>,
+    predicted_cloud_cover: Option<f64>,
+    observed_cloud_cover: Option<f64>,
+}
+
+impl JoinedRow {
+    /// Row for `prediction` and the reading `observed` for the hour it predicted
+    pub fn new(location_id: i64, prediction: &Prediction, observed: &Weather) -> JoinedRow {
+        return JoinedRow {
+            location_id,
+            reading_timestamp: prediction.reading_timestamp,
+            timestamp: observed.timestamp,
+            hour_diff: prediction.hour_diff,
+            predicted_icon: prediction.icon.to_str(),
+            observed_icon: observed.icon.to_str(),
+            predicted_precip_intensity: prediction.precip_intensity,
+            observed_precip_intensity: observed.precip_intensity,
+            predicted_precip_probability: prediction.precip_probability,
+            observed_precip_type: observed.precip_type.clone(),
+            predicted_temp: prediction.temp,
+            observed_temp: observed.temp,
+            predicted_wind_speed: prediction.wind_speed,
+            observed_wind_speed: observed.wind_speed,
+            predicted_wind_gust: prediction.wind_gust,
+            observed_wind_gust: observed.wind_gust,
+            predicted_humidity: prediction.humidity,
+            observed_humidity: observed.humidity,
+            predicted_air_temp: prediction.details.air_temp,
+            observed_air_temp: observed.details.air_temp,
+            predicted_pressure: prediction.details.pressure,
+            observed_pressure: observed.details.pressure,
+            predicted_cloud_cover: prediction.details.cloud_cover,
+            observed_cloud_cover: observed.details.cloud_cover,
+        };
+    }
+}
+
+impl ExportRow for JoinedRow {
+    fn schema() -> Schema {
+        return schema(&[
+            ("location_id", DataType::Int64, false),
+            ("reading_timestamp", DataType::Int64, false),
+            ("timestamp", DataType::Int64, false),
+            ("hour_diff", DataType::UInt8, false),
+            ("predicted_icon", DataType::Utf8, false),
+            ("observed_icon", DataType::Utf8, false),
+            ("predicted_precip_intensity", DataType::Float64, false),
+            ("observed_precip_intensity", DataType::Float64, false),
+            ("predicted_precip_probability", DataType::Float64, false),
+            ("observed_precip_type", DataType::Utf8, true),
+            ("predicted_temp", DataType::Float64, false),
+            ("observed_temp", DataType::Float64, false),
+            ("predicted_wind_speed", DataType::Float64, false),
+            ("observed_wind_speed", DataType::Float64, false),
+            ("predicted_wind_gust", DataType::Float64, false),
+            ("observed_wind_gust", DataType::Float64, false),
+            ("predicted_humidity", DataType::Float64, false),
+            ("observed_humidity", DataType::Float64, false),
+            ("predicted_air_temp", DataType::Float64, true),
+            ("observed_air_temp", DataType::Float64, true),
+            ("predicted_pressure", DataType::Float64, true),
+            ("observed_pressure", DataType::Float64, true),
+            ("predicted_cloud_cover", DataType::Float64, true),
+            ("observed_cloud_cover", DataType::Float64, true),
+        ]);
+    }
+}
+
+/// Write rows to `out` in `format`
+///
+/// # Errors
+/// Failed to write
+/// Failed to convert rows for parquet
+///
+pub fn write_rows<R: ExportRow, W: Write + Send>(rows: &[R], format: ExportFormat, mut out: W) -> Result<(), Error> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::JsonLines => {
+            for row in rows {
+                serde_json::to_writer(&mut out, row)?;
+                out.write_all(b"\n")?;
+            }
+            out.flush()?;
+        }
+        ExportFormat::Parquet => {
+            let schema = Arc::new(R::schema());
+            let mut decoder = arrow_json::ReaderBuilder::new(schema.clone()).build_decoder()?;
+            let mut writer = ArrowWriter::try_new(out, schema, None)?;
+            for chunk in rows.chunks(PARQUET_BATCH_SIZE) {
+                decoder.serialize(chunk)?;
+                if let Some(batch) = decoder.flush()? {
+                    writer.write(&batch)?;
+                }
+            }
+            writer.close()?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::{Icon, WeatherDetails};
+    use crate::test_support::TempDir;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn readings() -> Vec<ReadingRow> {
+        let details = WeatherDetails { pressure: Some(1012.5), ..WeatherDetails::default() };
+        return vec![
+            ReadingRow::new(1, &Weather::new(2019, 326, 4, Icon::Rain, 0.5, 0.8, 3.5, 4., 8., 0.9, Some(String::from("rain"))).with_details(details)),
+            ReadingRow::new(1, &Weather::new(2019, 326, 5, Icon::Cloudy, 0., 0.1, 4., 3., 6., 0.85, None)),
+        ];
+    }
+
+    #[test]
+    fn test_csv() {
+        let mut out = vec![];
+        write_rows(&readings(), ExportFormat::Csv, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("location_id,timestamp,year,day,hour,icon,precip_intensity"));
+        assert!(lines[1].starts_with("1,1574395200,2019,326,4,rain,0.5,0.8,rain,3.5"));
+        assert!(lines[1].contains(",1012.5,"));
+        assert!(lines[2].contains(",cloudy,0.0,0.1,,4.0,"), "missing values are empty");
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let mut out = vec![];
+        write_rows(&readings(), ExportFormat::JsonLines, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let rows: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["icon"], "rain");
+        assert_eq!(rows[0]["pressure"], 1012.5);
+        assert!(rows[1]["pressure"].is_null());
+    }
+
+    #[test]
+    fn test_parquet() {
+        let dir = TempDir::new("export_parquet");
+        let path = dir.path().join("readings.parquet");
+        write_rows(&readings(), ExportFormat::Parquet, std::fs::File::create(&path).unwrap()).unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata();
+
+        assert_eq!(metadata.file_metadata().num_rows(), 2);
+        assert_eq!(metadata.file_metadata().schema_descr().num_columns(), ReadingRow::schema().fields().len());
+    }
+
+    #[test]
+    fn test_schemas_match_rows() {
+        let prediction = Prediction::new(2019, 326, 4, 2019, 326, 5, 1, Icon::Rain, 0., 0., 0., 0., 0., 0., None);
+        let observed = Weather::new(2019, 326, 5, Icon::Rain, 0., 0., 0., 0., 0., 0., None);
+
+        fn keys<R: Serialize>(row: &R) -> Vec<String> {
+            return serde_json::to_value(row).unwrap().as_object().unwrap().keys().cloned().collect();
+        }
+        fn names(schema: Schema) -> Vec<String> {
+            let mut names: Vec<String> = schema.fields().iter().map(|field| field.name().clone()).collect();
+            names.sort();
+            return names;
+        }
+
+        assert_eq!(keys(&readings()[0]), names(ReadingRow::schema()));
+        assert_eq!(keys(&PredictionRow::new(1, &prediction)), names(PredictionRow::schema()));
+        assert_eq!(keys(&JoinedRow::new(1, &prediction, &observed)), names(JoinedRow::schema()));
+    }
+
+    #[test]
+    fn test_prediction_timestamps() {
+        let mut prediction = Prediction::new(2019, 326, 4, 2019, 326, 5, 1, Icon::Rain, 0., 0., 0., 0., 0., 0., None);
+        assert_eq!((prediction.reading_timestamp, prediction.prediction_timestamp), (1574395200, 1574398800));
+
+        // Stored timestamps are exported as they are, not rebuilt from the day and hour
+        prediction.reading_timestamp += 1800;
+        prediction.prediction_timestamp += 1800;
+        let observed = Weather::new(2019, 326, 5, Icon::Rain, 0., 0., 0., 0., 0., 0., None);
+
+        let row = PredictionRow::new(1, &prediction);
+        assert_eq!((row.reading_timestamp, row.prediction_timestamp), (1574397000, 1574400600));
+        assert_eq!(JoinedRow::new(1, &prediction, &observed).reading_timestamp, 1574397000);
+    }
+
+    #[test]
+    fn test_format_for_path() {
+        assert_eq!(ExportFormat::for_path("out/data.PARQUET"), Some(ExportFormat::Parquet));
+        assert_eq!(ExportFormat::for_path("data.jsonl"), Some(ExportFormat::JsonLines));
+        assert_eq!(ExportFormat::for_path("data"), None);
+    }
+}
@@ -1,13 +1,22 @@
-use chrono::{NaiveDateTime, Duration, Timelike, Datelike};
+//! Date helpers
+
+use chrono::{DateTime, NaiveDateTime, Duration, Timelike, Datelike};
 use std::ops::{Add, Sub};
 use crate::models::SimpleDate;
 
+/// Step a date by an hour, day or month
 pub trait Utils {
+    /// Same time an hour earlier
     fn minus_one_hour(&self) -> Self;
+    /// Same time an hour later
     fn plus_one_hour(&self) -> Self;
+    /// Same time a day earlier
     fn minus_one_day(&self) -> Self;
+    /// Same time a day later
     fn plus_one_day(&self) -> Self;
+    /// First day of the next month, at the same time
     fn plus_one_month(&self) -> Self;
+    /// First day of the previous month, at the same time
     fn minus_one_month(&self) -> Self;
 }
 
@@ -43,8 +52,9 @@ impl Utils for NaiveDateTime {
     }
 }
 
+/// True if February has 29 days in `year`
 pub fn is_leap_year(year: u32) -> bool {
-    return (year % 4 == 0) && (year % 100 != 0 || year % 400 == 0);
+    return year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
 }
 
 /// Returns days in a month (accounting for leap years)
@@ -66,7 +76,7 @@ pub fn days_in_month(month: u8, year: u32) -> u8 {
 
 impl From<SimpleDate> for NaiveDateTime {
     fn from(value: SimpleDate) -> Self {
-        NaiveDateTime::from_timestamp(0, 0)
+        DateTime::UNIX_EPOCH.naive_utc()
             .with_year(value.year as i32).expect("Bad year")
             .with_ordinal(value.day as u32).expect("Bad day")
             .with_hour(value.hour as u32).expect("Bad hour")
@@ -79,20 +89,6 @@ impl From<NaiveDateTime> for SimpleDate {
     }
 }
 
-fn unit() {
-    return ();
-}
-
-pub trait MapToUnit<E> {
-    fn map_to_unit(self) -> Result<(), E>;
-}
-
-impl<U, E> MapToUnit<E> for Result<U, E> {
-    fn map_to_unit(self) -> Result<(), E> {
-        return self.map(|_| unit());
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
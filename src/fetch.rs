@@ -0,0 +1,407 @@
+//! Downloading forecasts from an HTTP API for each location, archiving and importing them
+
+use crate::app::WeatherApp;
+use crate::import::{ImportOptions, ImportCounts, FileOutcome};
+use crate::models::{Location, Coordinates};
+use crate::Error;
+use chrono::{NaiveDateTime, Duration as ChronoDuration, Timelike};
+use log::{debug, info, warn};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How forecasts are requested and stored by `Fetcher`
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// Forecast endpoint, `{latitude}` and `{longitude}` are replaced with the location's coordinates
+    /// `{time}` (unix timestamp) and `{hour}` (`YYYY-MM-DDTHH:00`) are replaced with the hour being fetched in UTC
+    pub url: String,
+    /// Directory responses are saved in before they're imported
+    pub archive_dir: PathBuf,
+    /// Name of importer to use for responses, if `None` the format is detected
+    pub format: Option<String>,
+    /// Number of times a request is retried after a network error, `429` or `5xx` response
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each retry after
+    pub backoff: Duration,
+    /// Timeout of each request
+    pub timeout: Duration,
+    /// Number of hours to look back for hours with no reading, these are fetched before the current hour
+    /// Only used if `url` has `{time}` or `{hour}`
+    pub fill_missed: u32,
+}
+
+impl FetchOptions {
+    /// Options with 3 retries 5 seconds apart, a 30 second timeout and no missed hours filled
+    pub fn new<S: Into<String>, P: Into<PathBuf>>(url: S, archive_dir: P) -> FetchOptions {
+        return FetchOptions {
+            url: url.into(),
+            archive_dir: archive_dir.into(),
+            format: None,
+            retries: 3,
+            backoff: Duration::from_secs(5),
+            timeout: Duration::from_secs(30),
+            fill_missed: 0,
+        };
+    }
+
+    /// True if `url` requests a specific hour, so missed hours can be fetched
+    pub fn has_hour(&self) -> bool {
+        return self.url.contains("{time}") || self.url.contains("{hour}");
+    }
+
+    /// `url` with placeholders replaced
+    pub fn url_for(&self, coordinates: &Coordinates, hour: NaiveDateTime) -> String {
+        return self.url
+            .replace("{latitude}", &coordinates.latitude.to_string())
+            .replace("{longitude}", &coordinates.longitude.to_string())
+            .replace("{time}", &hour.and_utc().timestamp().to_string())
+            .replace("{hour}", &hour.format("%Y-%m-%dT%H:00").to_string());
+    }
+}
+
+/// Result of fetching an hour for a location
+#[derive(Debug)]
+pub struct FetchOutcome {
+    /// Name of the location
+    pub location: String,
+    /// Hour requested (UTC)
+    pub hour: NaiveDateTime,
+    /// Rows written, or why the request or import failed
+    pub result: Result<ImportCounts, Error>,
+}
+
+/// Outcome of every request in a run
+#[derive(Debug, Default)]
+pub struct FetchReport {
+    /// Each request in the order they were made
+    pub outcomes: Vec<FetchOutcome>,
+}
+
+impl FetchReport {
+    /// Number of responses fetched and imported
+    pub fn fetched(&self) -> usize {
+        return self.outcomes.iter()
+            .filter(|outcome| outcome.result.is_ok())
+            .count();
+    }
+
+    /// Requests that failed and the reason why
+    pub fn failed(&self) -> Vec<&FetchOutcome> {
+        return self.outcomes.iter()
+            .filter(|outcome| outcome.result.is_err())
+            .collect();
+    }
+
+    /// Sum of rows written for all responses
+    pub fn totals(&self) -> ImportCounts {
+        let mut totals = ImportCounts::default();
+        for outcome in &self.outcomes {
+            if let Ok(counts) = &outcome.result {
+                totals += *counts;
+            }
+        }
+        return totals;
+    }
+}
+
+impl Display for FetchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let totals = self.totals();
+        let failed = self.failed();
+
+        writeln!(f, "Fetched:               {}", self.fetched())?;
+        writeln!(f, "Failed:                {}", failed.len())?;
+        writeln!(f, "Readings inserted:     {}", totals.readings_inserted)?;
+        writeln!(f, "Predictions inserted:  {}", totals.predictions_inserted)?;
+        writeln!(f, "Predictions rejected:  {}", totals.predictions_rejected)?;
+
+        for outcome in failed {
+            if let Err(err) = &outcome.result {
+                writeln!(f, "{} {}: {}", outcome.location, outcome.hour.format("%Y-%m-%d %H:00"), err)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Requests forecasts for locations, saves them in the archive directory then imports them
+pub struct Fetcher {
+    options: FetchOptions,
+    agent: ureq::Agent,
+}
+
+impl Fetcher {
+    /// Fetcher with an HTTP client using `options.timeout`
+    pub fn new(options: FetchOptions) -> Fetcher {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(options.timeout)
+            .build();
+        return Fetcher { options, agent };
+    }
+
+    /// Fetch the hour of `now` for each location, and any missed hours before it
+    ///
+    /// Locations without coordinates are skipped
+    /// A request that fails (after retrying) is recorded in the report and the rest are still fetched
+    ///
+    /// # Errors
+    /// Database errors while finding missed hours
+    ///
+    /// # Returns
+    /// Outcome of each request
+    ///
+    pub fn run_once(&self, app: &mut WeatherApp, locations: &[Location], now: NaiveDateTime) -> Result<FetchReport, Error> {
+        let current = now.with_minute(0).and_then(|time| time.with_second(0)).and_then(|time| time.with_nanosecond(0)).unwrap_or(now);
+        if self.options.fill_missed > 0 && !self.options.has_hour() {
+            warn!("Missed hours can't be fetched as the url has no {{time}} or {{hour}}");
+        }
+
+        let mut report = FetchReport::default();
+        for location in locations {
+            let coordinates = match &location.coordinates {
+                Some(coordinates) => coordinates,
+                None => {
+                    debug!("Skipping {} as it has no coordinates", location.name);
+                    continue;
+                }
+            };
+
+            let mut hours = if self.options.fill_missed > 0 && self.options.has_hour() {
+                let start = current - ChronoDuration::hours(self.options.fill_missed as i64);
+                app.get_missing_hours(location.id, start, current)?
+            } else {
+                vec![]
+            };
+            hours.push(current);
+
+            for hour in hours {
+                let result = self.fetch_hour(app, location, coordinates, hour);
+                match &result {
+                    Ok(_) => info!("Fetched {} {}", location.name, hour),
+                    Err(err) => warn!("Failed to fetch {} {}: {}", location.name, hour, err)
+                }
+                report.outcomes.push(FetchOutcome { location: location.name.clone(), hour, result });
+            }
+        }
+
+        return Ok(report);
+    }
+
+    /// Request, save and import one hour for a location
+    ///
+    /// The response is saved as `<archive dir>/<location id>/<YYYY>/<MM>/<DD>/<HH>.json`
+    ///
+    /// # Errors
+    /// Request failed
+    /// Unable to save response
+    /// Response could not be imported
+    ///
+    fn fetch_hour(&self, app: &mut WeatherApp, location: &Location, coordinates: &Coordinates, hour: NaiveDateTime) -> Result<ImportCounts, Error> {
+        let body = self.get(&self.options.url_for(coordinates, hour))?;
+
+        let dir = self.options.archive_dir.join(location.id.to_string()).join(hour.format("%Y/%m/%d").to_string());
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", hour.format("%H")));
+        std::fs::write(&path, body)?;
+
+        let options = ImportOptions {
+            format: self.options.format.clone(),
+            location_id: Some(location.id),
+            ..ImportOptions::default()
+        };
+        let report = app.import_data_from_file(&path, &options)?;
+        return match report.files.into_iter().next() {
+            Some((_, FileOutcome::Imported(counts))) => Ok(counts),
+            Some((_, FileOutcome::Rejected(err))) => Err(err),
+            Some((_, FileOutcome::Skipped)) | None => Ok(ImportCounts::default())
+        };
+    }
+
+    /// GET `url`, retrying with exponential backoff
+    ///
+    /// # Errors
+    /// Request failed after all retries, or failed with a status that isn't worth retrying (e.g. `404`)
+    ///
+    /// # Returns
+    /// Body of the response
+    ///
+    fn get(&self, url: &str) -> Result<String, Error> {
+        let mut delay = self.options.backoff;
+        let mut attempt = 0;
+        loop {
+            match self.agent.get(url).call() {
+                Ok(response) => return Ok(response.into_string()?),
+                Err(err) if attempt < self.options.retries && is_retryable(&err) => {
+                    warn!("{}, retrying in {:?}", Error::from(err), delay);
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err.into())
+            }
+        }
+    }
+}
+
+/// True if the request may succeed if it's sent again
+fn is_retryable(err: &ureq::Error) -> bool {
+    return match err {
+        ureq::Error::Status(code, _) => *code == 429 || *code >= 500,
+        ureq::Error::Transport(_) => true
+    };
+}
+
+/// Time until the next multiple of `interval` after `now` (e.g. the next hour for an hour interval)
+pub fn until_next(now: NaiveDateTime, interval: Duration) -> Duration {
+    let interval = interval.as_secs().max(1);
+    let elapsed = now.and_utc().timestamp().rem_euclid(interval as i64) as u64;
+    return Duration::from_secs(interval - elapsed);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db_manager::DbManager;
+    use chrono::NaiveDate;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::test_support::{TempDir, dark_sky_at, dark_sky_time_machine};
+
+    /// HTTP server on a random port, answering each request with `respond(request number, url)`
+    struct StubServer {
+        server: Arc<tiny_http::Server>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    impl StubServer {
+        fn new<F: Fn(usize, &str) -> (u16, String) + Send + 'static>(respond: F) -> StubServer {
+            let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+            let requests = Arc::new(AtomicUsize::new(0));
+            let (thread_server, thread_requests) = (server.clone(), requests.clone());
+            std::thread::spawn(move || {
+                for request in thread_server.incoming_requests() {
+                    let (status, body) = respond(thread_requests.fetch_add(1, Ordering::SeqCst), request.url());
+                    let _ = request.respond(tiny_http::Response::from_string(body).with_status_code(status));
+                }
+            });
+            return StubServer { server, requests };
+        }
+
+        fn url(&self) -> String {
+            return format!("http://{}/forecast/{{latitude}},{{longitude}},{{time}}", self.server.server_addr().to_ip().unwrap());
+        }
+
+        fn requests(&self) -> usize {
+            return self.requests.load(Ordering::SeqCst);
+        }
+    }
+
+    impl Drop for StubServer {
+        fn drop(&mut self) {
+            self.server.unblock();
+        }
+    }
+
+    fn url_time(url: &str) -> i64 {
+        return url.rsplit(',').next().unwrap().parse().unwrap();
+    }
+
+    /// DarkSky test fixture moved to the time at the end of `url`
+    fn dark_sky_response(url: &str) -> String {
+        return dark_sky_at(url_time(url)).to_string();
+    }
+
+    /// DarkSky time machine response for the time at the end of `url`
+    fn time_machine_response(url: &str) -> String {
+        return dark_sky_time_machine(url_time(url)).to_string();
+    }
+
+    fn hour(hour: u32, minute: u32) -> NaiveDateTime {
+        return NaiveDate::from_ymd_opt(2019, 11, 22).unwrap().and_hms_opt(hour, minute, 0).unwrap();
+    }
+
+    fn setup(name: &str, server: &StubServer) -> (TempDir, WeatherApp, Vec<Location>, FetchOptions) {
+        let dir = TempDir::new(name);
+        let mut db_manager = DbManager::new(":memory:").unwrap();
+        db_manager.init().unwrap();
+        let mut app = WeatherApp::new(db_manager);
+        app.add_location(&Coordinates { latitude: 51.4, longitude: -2.6, timezone: None }).unwrap();
+        let locations = app.get_locations().unwrap();
+
+        let mut options = FetchOptions::new(server.url(), dir.path().join("archive"));
+        options.retries = 2;
+        options.backoff = Duration::from_millis(1);
+        return (dir, app, locations, options);
+    }
+
+    #[test]
+    fn test_retries_then_imports() {
+        let server = StubServer::new(|i, url| if i == 0 { (503, String::new()) } else { (200, dark_sky_response(url)) });
+        let (dir, mut app, locations, options) = setup("fetch_retries", &server);
+
+        let report = Fetcher::new(options).run_once(&mut app, &locations, hour(4, 30)).unwrap();
+
+        assert_eq!(server.requests(), 2);
+        assert_eq!(report.fetched(), 1);
+        assert_eq!(report.totals().readings_inserted, 1);
+        assert_eq!(report.outcomes[0].hour, hour(4, 0));
+        assert!(dir.path().join(format!("archive/{}/2019/11/22/04.json", locations[0].id)).is_file());
+        assert_eq!(app.get_last_reading().unwrap().hour, 4);
+    }
+
+    #[test]
+    fn test_gives_up_after_retries() {
+        let server = StubServer::new(|_, _| (500, String::new()));
+        let (_dir, mut app, locations, options) = setup("fetch_gives_up", &server);
+
+        let report = Fetcher::new(options).run_once(&mut app, &locations, hour(4, 30)).unwrap();
+
+        assert_eq!(server.requests(), 3);
+        assert!(matches!(&report.failed()[0].result, Err(Error::Http(message)) if message.starts_with("500")));
+    }
+
+    #[test]
+    fn test_client_errors_are_not_retried() {
+        let server = StubServer::new(|_, _| (404, String::new()));
+        let (_dir, mut app, locations, options) = setup("fetch_not_found", &server);
+
+        let report = Fetcher::new(options).run_once(&mut app, &locations, hour(4, 30)).unwrap();
+
+        assert_eq!(server.requests(), 1);
+        assert_eq!(report.failed().len(), 1);
+    }
+
+    #[test]
+    fn test_fills_missed_hours() {
+        let server = StubServer::new(|_, url| (200, time_machine_response(url)));
+        let (_dir, mut app, locations, mut options) = setup("fetch_fills_missed", &server);
+        options.fill_missed = 24;
+        let fetcher = Fetcher::new(options);
+
+        // Nothing is missed before the first reading
+        let report = fetcher.run_once(&mut app, &locations, hour(4, 30)).unwrap();
+        assert_eq!(report.outcomes.len(), 1);
+
+        let report = fetcher.run_once(&mut app, &locations, hour(8, 10)).unwrap();
+        let hours: Vec<NaiveDateTime> = report.outcomes.iter().map(|outcome| outcome.hour).collect();
+        assert_eq!(hours, vec![hour(5, 0), hour(6, 0), hour(7, 0), hour(8, 0)]);
+        assert_eq!(report.fetched(), 4);
+        assert!(app.get_missing_hours(locations[0].id, hour(0, 0), hour(9, 0)).unwrap().is_empty());
+
+        // Hourly data starts at midnight, so predictions are matched to the reading by time
+        let (reading, predictions) = app.get_reading_with_predictions(2019, 326, 6).unwrap();
+        assert_eq!(reading.hour, 6);
+        let made: Vec<(u8, u8)> = predictions.iter().map(|prediction| (prediction.reading_hour, prediction.hour_diff)).collect();
+        assert_eq!(made, vec![(5, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn test_until_next() {
+        assert_eq!(until_next(hour(4, 30), Duration::from_secs(3600)), Duration::from_secs(1800));
+        assert_eq!(until_next(hour(4, 0), Duration::from_secs(3600)), Duration::from_secs(3600));
+        assert_eq!(until_next(hour(4, 10), Duration::from_secs(900)), Duration::from_secs(300));
+    }
+}
@@ -0,0 +1,283 @@
+//! Options for importing files, finding them and the report of what was imported
+
+use crate::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::AddAssign;
+use crate::models::{ReadingWithPredictions, Weather};
+use crate::analysis::MAX_HOUR_DIFF;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+pub mod source;
+
+/// How files are imported by `WeatherApp::import_data`
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// Name of importer to use for every file, if `None` the format of each file is detected
+    pub format: Option<String>,
+    /// Location to add all data to, if `None` the location is found (or created) from the coordinates in each file
+    pub location_id: Option<i64>,
+    /// Parse files and count what would change without writing anything
+    pub dry_run: bool,
+    /// Import from subdirectories as well
+    pub recursive: bool,
+    /// Only import files (and archive entries) matching any of these globs, if empty all files are imported
+    pub include: Vec<String>,
+    /// Skip files, archives and archive entries matching any of these globs
+    pub exclude: Vec<String>,
+    /// Import every file, even if it's unchanged since it was last imported
+    pub force: bool,
+}
+
+/// Number of rows written (or that would be written in a dry run)
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ImportCounts {
+    /// Readings for hours that had no reading
+    pub readings_inserted: usize,
+    /// Readings that replaced an existing reading for the same location and hour
+    pub readings_replaced: usize,
+    /// Predictions that weren't already stored
+    pub predictions_inserted: usize,
+    /// Predictions that replaced an existing prediction for the same location, reading hour and predicted hour
+    pub predictions_replaced: usize,
+    /// Predictions not written as they weren't 1 to `MAX_HOUR_DIFF` hours after their reading
+    pub predictions_rejected: usize,
+}
+
+impl AddAssign for ImportCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.readings_inserted += rhs.readings_inserted;
+        self.readings_replaced += rhs.readings_replaced;
+        self.predictions_inserted += rhs.predictions_inserted;
+        self.predictions_replaced += rhs.predictions_replaced;
+        self.predictions_rejected += rhs.predictions_rejected;
+    }
+}
+
+/// Hours between a reading and a prediction made with it, found from their timestamps
+///
+/// # Returns
+/// `None` if the prediction isn't 1 to `MAX_HOUR_DIFF` whole hours after the reading
+///
+pub fn lead_time(reading: &Weather, prediction: &Weather) -> Option<u8> {
+    let seconds = prediction.timestamp - reading.timestamp;
+    if seconds % 3600 != 0 {
+        return None;
+    }
+    return Some(seconds / 3600)
+        .filter(|hours| *hours >= 1 && *hours <= MAX_HOUR_DIFF as i64)
+        .map(|hours| hours as u8);
+}
+
+/// What happened to a single file in an import
+#[derive(Debug)]
+pub enum FileOutcome {
+    /// File was parsed and its readings written (or counted in a dry run)
+    Imported(ImportCounts),
+    /// File is unchanged since it was last imported
+    Skipped,
+    /// File could not be read, parsed or saved, nothing from it was written
+    Rejected(Error),
+}
+
+/// Entry in the ingest ledger, a file (or archive entry) that has been imported
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngestedFile {
+    /// Path of the file, or archive path followed by the entry name
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+    /// Last modified time as a unix timestamp, if known
+    pub modified: Option<i64>,
+    /// SHA-256 of the contents, as hex
+    pub hash: String,
+}
+
+impl IngestedFile {
+    /// True if `size` and `modified` are the same as when this was imported
+    pub fn matches(&self, size: u64, modified: Option<i64>) -> bool {
+        return self.size == size && modified.is_some() && self.modified == modified;
+    }
+}
+
+/// Original document readings were imported from, kept so they can be parsed again (see `WeatherApp::reparse`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payload {
+    /// Name of the importer that parsed it
+    pub format: String,
+    /// Path of the file (or archive entry) it was imported from
+    pub source: String,
+    /// SHA-256 of the document, as hex
+    pub hash: String,
+    /// Gzip compressed document
+    pub data: Vec<u8>,
+}
+
+impl Payload {
+    /// Compress `text` into a payload
+    ///
+    /// # Errors
+    /// Failed to compress
+    ///
+    pub fn new(format: &str, source: &Path, hash: String, text: &str) -> Result<Payload, Error> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(text.as_bytes())?;
+        return Ok(Payload {
+            format: format.to_owned(),
+            source: source.to_string_lossy().into_owned(),
+            hash,
+            data: encoder.finish()?,
+        });
+    }
+
+    /// Decompressed document
+    ///
+    /// # Errors
+    /// Data is not valid gzip or UTF-8
+    ///
+    pub fn text(&self) -> Result<String, Error> {
+        let mut text = String::new();
+        GzDecoder::new(self.data.as_slice()).read_to_string(&mut text)?;
+        return Ok(text);
+    }
+}
+
+/// Readings from one file, written with `DbManager::add_weather_batch`
+#[derive(Debug)]
+pub struct ImportedFile {
+    /// Location the readings are for
+    pub location_id: i64,
+    /// Each reading in the file and the predictions made with it
+    pub readings: Vec<ReadingWithPredictions>,
+    /// Document the readings came from, stored alongside them if set
+    pub payload: Option<Payload>,
+}
+
+/// Outcome of every file in an import
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// True if nothing was written
+    pub dry_run: bool,
+    /// Each file in the order they were imported
+    pub files: Vec<(PathBuf, FileOutcome)>,
+}
+
+impl ImportReport {
+    /// Report with no files
+    pub fn new(dry_run: bool) -> ImportReport {
+        return ImportReport {
+            dry_run,
+            files: vec![],
+        };
+    }
+
+    /// Number of files that were parsed and imported
+    pub fn parsed(&self) -> usize {
+        return self.files.iter()
+            .filter(|(_, outcome)| matches!(outcome, FileOutcome::Imported(_)))
+            .count();
+    }
+
+    /// Number of files skipped because they're unchanged
+    pub fn skipped(&self) -> usize {
+        return self.files.iter()
+            .filter(|(_, outcome)| matches!(outcome, FileOutcome::Skipped))
+            .count();
+    }
+
+    /// Files that could not be imported and the reason why
+    pub fn rejected(&self) -> Vec<(&PathBuf, &Error)> {
+        return self.files.iter()
+            .filter_map(|(path, outcome)| match outcome {
+                FileOutcome::Rejected(err) => Some((path, err)),
+                FileOutcome::Imported(_) | FileOutcome::Skipped => None
+            })
+            .collect();
+    }
+
+    /// Sum of rows written for all files
+    pub fn totals(&self) -> ImportCounts {
+        let mut totals = ImportCounts::default();
+        for (_, outcome) in &self.files {
+            if let FileOutcome::Imported(counts) = outcome {
+                totals += *counts;
+            }
+        }
+        return totals;
+    }
+}
+
+impl Display for ImportReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let totals = self.totals();
+        let rejected = self.rejected();
+
+        if self.dry_run {
+            writeln!(f, "Dry run, nothing was written")?;
+        }
+        writeln!(f, "Files parsed:          {}", self.parsed())?;
+        writeln!(f, "Files skipped:         {}", self.skipped())?;
+        writeln!(f, "Files rejected:        {}", rejected.len())?;
+        writeln!(f, "Readings inserted:     {}", totals.readings_inserted)?;
+        writeln!(f, "Readings replaced:     {}", totals.readings_replaced)?;
+        writeln!(f, "Predictions inserted:  {}", totals.predictions_inserted)?;
+        writeln!(f, "Predictions replaced:  {}", totals.predictions_replaced)?;
+        writeln!(f, "Predictions rejected:  {}", totals.predictions_rejected)?;
+
+        if !rejected.is_empty() {
+            writeln!(f, "\nRejected")?;
+            for (path, err) in rejected {
+                writeln!(f, "{}: {}", path.to_string_lossy(), err)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let counts = ImportCounts { readings_inserted: 1, readings_replaced: 0, predictions_inserted: 48, predictions_replaced: 2, predictions_rejected: 1 };
+        let mut report = ImportReport::new(true);
+        report.files.push((PathBuf::from("a.json"), FileOutcome::Imported(counts)));
+        report.files.push((PathBuf::from("b.json"), FileOutcome::Rejected(Error::parse("expected value"))));
+        report.files.push((PathBuf::from("c.json"), FileOutcome::Imported(counts)));
+        report.files.push((PathBuf::from("d.json"), FileOutcome::Skipped));
+
+        assert_eq!(report.parsed(), 2);
+        assert_eq!(report.skipped(), 1);
+        assert_eq!(report.rejected().len(), 1);
+        assert_eq!(report.totals(), ImportCounts { readings_inserted: 2, readings_replaced: 0, predictions_inserted: 96, predictions_replaced: 4, predictions_rejected: 2 });
+        assert_eq!(report.to_string(), "Dry run, nothing was written
+Files parsed:          2
+Files skipped:         1
+Files rejected:        1
+Readings inserted:     2
+Readings replaced:     0
+Predictions inserted:  96
+Predictions replaced:  4
+Predictions rejected:  2
+
+Rejected
+b.json: Unable to parse: expected value
+");
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let text = "{\"currently\": {}}";
+        let payload = Payload::new("dark-sky", Path::new("data/a.json"), String::from("abc"), text).unwrap();
+
+        assert_eq!(payload.source, "data/a.json");
+        assert_ne!(payload.data, text.as_bytes());
+        assert_eq!(payload.text().unwrap(), text);
+    }
+}
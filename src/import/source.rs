@@ -0,0 +1,388 @@
+//! Finding json documents in a directory, including inside `.json.gz`, `.zip`, `.tar` and `.tar.gz` files
+
+use crate::Error;
+use flate2::read::GzDecoder;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use chrono::NaiveDate;
+use walkdir::WalkDir;
+
+/// Json document found while walking the import directory
+#[derive(Debug)]
+pub struct Source {
+    /// Path of the file, for archive entries this is the archive path joined with the entry path
+    pub path: PathBuf,
+    /// Size in bytes (before decompressing a `.json.gz`)
+    pub size: u64,
+    /// Last modified time as a unix timestamp, if known
+    pub modified: Option<i64>,
+    /// Contents of the document, or why it couldn't be read
+    pub text: Result<String, Error>,
+}
+
+impl Source {
+    fn error(path: PathBuf, err: Error) -> Source {
+        return Source { path, size: 0, modified: None, text: Err(err) };
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum FileKind {
+    Json,
+    JsonGz,
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl FileKind {
+    /// Kind of file from its name, `None` if it's not a supported file
+    fn of(path: &Path) -> Option<FileKind> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        return if name.ends_with(".json") {
+            Some(FileKind::Json)
+        } else if name.ends_with(".json.gz") {
+            Some(FileKind::JsonGz)
+        } else if name.ends_with(".zip") {
+            Some(FileKind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(FileKind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(FileKind::TarGz)
+        } else {
+            None
+        };
+    }
+}
+
+/// Include and exclude globs for paths relative to the import directory
+///
+/// `*` matches any characters including `/`, so `*.json.gz` matches at any depth
+/// Include globs are matched against json files and entries inside archives,
+/// exclude globs are also matched against the archives themselves
+#[derive(Debug)]
+pub struct PathFilter {
+    /// If `None` everything is included
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    /// # Errors
+    /// Invalid glob
+    ///
+    pub fn new(include: &[String], exclude: &[String]) -> Result<PathFilter, Error> {
+        return Ok(PathFilter {
+            include: if include.is_empty() { None } else { Some(glob_set(include)?) },
+            exclude: glob_set(exclude)?,
+        });
+    }
+
+    fn is_included(&self, path: &Path) -> bool {
+        return self.include.as_ref().map(|include| include.is_match(path)).unwrap_or(true) && !self.is_excluded(path);
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        return self.exclude.is_match(path);
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    return Ok(builder.build()?);
+}
+
+/// Call `f` with every json document in `dir`, in file name order
+///
+/// Archives are streamed, each entry is passed to `f` as it's read
+/// `should_read` is called with the path, size and modified time of each document before it's read,
+/// if it returns false the document is skipped without reading it
+/// Files and entries that can't be read are still passed to `f` with the error
+/// Symlinked directories are not followed
+///
+/// # Errors
+/// `dir` is inaccessible
+/// Any error returned by `f` (no more sources are read)
+///
+pub fn for_each_source<R, F>(dir: &Path, recursive: bool, filter: &PathFilter, mut should_read: R, mut f: F) -> Result<(), Error> where
+    R: FnMut(&Path, u64, Option<i64>) -> bool,
+    F: FnMut(Source) -> Result<(), Error>
+{
+    std::fs::read_dir(dir)?;
+
+    let walker = WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(if recursive { usize::MAX } else { 1 })
+        .sort_by_file_name();
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                let path = err.path().unwrap_or(dir).to_path_buf();
+                f(Source::error(path, Error::Io(err.into())))?;
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        match FileKind::of(path) {
+            Some(FileKind::Json) | Some(FileKind::JsonGz) if filter.is_included(relative) => {
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(err) => {
+                        f(Source::error(path.to_path_buf(), Error::Io(err.into())))?;
+                        continue;
+                    }
+                };
+                let size = metadata.len();
+                let modified = metadata.modified().ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs() as i64);
+                if should_read(path, size, modified) {
+                    f(Source { path: path.to_path_buf(), size, modified, text: read_json_file(path) })?;
+                }
+            }
+            Some(kind @ FileKind::Zip) | Some(kind @ FileKind::Tar) | Some(kind @ FileKind::TarGz) if !filter.is_excluded(relative) => {
+                read_archive(path, kind, filter, &mut should_read, &mut f)?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a `.json` or `.json.gz` file
+///
+/// # Errors
+/// Unable to read file
+/// File isn't `.json` or `.json.gz`
+///
+pub fn read_json_file(path: &Path) -> Result<String, Error> {
+    return match FileKind::of(path) {
+        Some(kind @ FileKind::Json) | Some(kind @ FileKind::JsonGz) => read_text(BufReader::new(File::open(path)?), kind),
+        _ => Err(Error::from(format!("{} is not a .json or .json.gz file", path.to_string_lossy())))
+    };
+}
+
+/// Read the json document from `reader`, decompressing it if `kind` is `JsonGz`
+fn read_text<R: Read>(mut reader: R, kind: FileKind) -> Result<String, Error> {
+    let mut text = String::new();
+    if kind == FileKind::JsonGz {
+        GzDecoder::new(reader).read_to_string(&mut text)?;
+    } else {
+        reader.read_to_string(&mut text)?;
+    }
+    return Ok(text);
+}
+
+/// Call `f` with each json document in the archive at `path`
+///
+/// If the archive can't be opened or read `f` is called with the error for the archive itself
+/// (entries already read will have been passed to `f`)
+///
+/// # Errors
+/// Any error returned by `f`
+///
+fn read_archive<R, F>(path: &Path, kind: FileKind, filter: &PathFilter, should_read: &mut R, f: &mut F) -> Result<(), Error> where
+    R: FnMut(&Path, u64, Option<i64>) -> bool,
+    F: FnMut(Source) -> Result<(), Error>
+{
+    // Errors from `f` are kept separately so they aren't mistaken for errors reading the archive
+    let mut callback_result = Ok(());
+    let mut forward = |source: Source| {
+        callback_result = f(source);
+        return callback_result.as_ref().map(|_| ()).map_err(|_| Error::from("Stopped"));
+    };
+    let read_result = File::open(path)
+        .map_err(Error::from)
+        .and_then(|file| {
+            let file = BufReader::new(file);
+            return match kind {
+                FileKind::Zip => read_zip(path, file, filter, should_read, &mut forward),
+                FileKind::Tar => read_tar(path, file, filter, should_read, &mut forward),
+                FileKind::TarGz => read_tar(path, GzDecoder::new(file), filter, should_read, &mut forward),
+                FileKind::Json | FileKind::JsonGz => unreachable!("Not an archive")
+            };
+        });
+    callback_result?;
+    if let Err(err) = read_result {
+        f(Source::error(path.to_path_buf(), err))?;
+    }
+    Ok(())
+}
+
+fn read_zip<R, F>(path: &Path, file: BufReader<File>, filter: &PathFilter, should_read: &mut R, f: &mut F) -> Result<(), Error> where
+    R: FnMut(&Path, u64, Option<i64>) -> bool,
+    F: FnMut(Source) -> Result<(), Error>
+{
+    let mut archive = zip::ZipArchive::new(file)?;
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        let entry_path = match entry.enclosed_name() {
+            Some(entry_path) => entry_path.to_path_buf(),
+            None => continue
+        };
+        if let Some(kind @ FileKind::Json) | Some(kind @ FileKind::JsonGz) = FileKind::of(&entry_path) {
+            if entry.is_file() && filter.is_included(&entry_path) {
+                let path = path.join(entry_path);
+                let size = entry.size();
+                let modified = zip_timestamp(entry.last_modified());
+                if should_read(&path, size, modified) {
+                    f(Source { path, size, modified, text: read_text(entry, kind) })?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Unix timestamp of a zip entry's modified time (zip doesn't store a timezone so it's treated as UTC)
+fn zip_timestamp(time: zip::DateTime) -> Option<i64> {
+    return NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)?
+        .and_hms_opt(time.hour() as u32, time.minute() as u32, time.second() as u32)
+        .map(|time| time.and_utc().timestamp());
+}
+
+fn read_tar<T, R, F>(path: &Path, reader: T, filter: &PathFilter, should_read: &mut R, f: &mut F) -> Result<(), Error> where
+    T: Read,
+    R: FnMut(&Path, u64, Option<i64>) -> bool,
+    F: FnMut(Source) -> Result<(), Error>
+{
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let entry_path = entry.path()?.to_path_buf();
+        if let Some(kind @ FileKind::Json) | Some(kind @ FileKind::JsonGz) = FileKind::of(&entry_path) {
+            if entry.header().entry_type().is_file() && filter.is_included(&entry_path) {
+                let path = path.join(entry_path);
+                let size = entry.header().size()?;
+                let modified = entry.header().mtime().ok().map(|mtime| mtime as i64);
+                if should_read(&path, size, modified) {
+                    f(Source { path, size, modified, text: read_text(entry, kind) })?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use crate::test_support::TempDir;
+
+    fn gzip(contents: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(contents).unwrap();
+        return encoder.finish().unwrap();
+    }
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        for (name, contents) in entries {
+            writer.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        return writer.finish().unwrap().into_inner();
+    }
+
+    fn tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (name, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *contents).unwrap();
+        }
+        return builder.into_inner().unwrap();
+    }
+
+    fn sources(dir: &TempDir, recursive: bool, include: &[&str], exclude: &[&str]) -> Vec<(String, String)> {
+        let include: Vec<String> = include.iter().map(|glob| glob.to_string()).collect();
+        let exclude: Vec<String> = exclude.iter().map(|glob| glob.to_string()).collect();
+        let filter = PathFilter::new(&include, &exclude).unwrap();
+        let mut sources = vec![];
+        for_each_source(dir.path(), recursive, &filter, |_, _, _| true, |source| {
+            let path = source.path.strip_prefix(dir.path()).unwrap().to_string_lossy().into_owned();
+            sources.push((path, source.text.unwrap()));
+            Ok(())
+        }).unwrap();
+        return sources;
+    }
+
+    fn example_dir(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        dir.write("top.json", b"top");
+        dir.write("notes.txt", b"ignored");
+        dir.write("2020/01/01/00.json.gz", &gzip(b"gz"));
+        dir.write("2020/01.zip", &zip(&[("01/00.json", b"zip"), ("readme.md", b"ignored")]));
+        dir.write("2020/02.tar.gz", &gzip(&tar(&[("02/01/00.json", b"targz"), ("02/01/01.json.gz", &gzip(b"tar entry gz"))])));
+        dir.write("2020/03.tar", &tar(&[("03/01/00.json", b"tar")]));
+        return dir;
+    }
+
+    #[test]
+    fn test_top_level_only() {
+        let dir = example_dir("source_top_level");
+
+        assert_eq!(sources(&dir, false, &[], &[]), vec![(String::from("top.json"), String::from("top"))]);
+    }
+
+    #[test]
+    fn test_recursive_with_archives() {
+        let dir = example_dir("source_recursive");
+
+        let found = sources(&dir, true, &[], &[]);
+        let expected = vec![
+            ("2020/01/01/00.json.gz", "gz"),
+            ("2020/01.zip/01/00.json", "zip"),
+            ("2020/02.tar.gz/02/01/00.json", "targz"),
+            ("2020/02.tar.gz/02/01/01.json.gz", "tar entry gz"),
+            ("2020/03.tar/03/01/00.json", "tar"),
+            ("top.json", "top"),
+        ];
+        assert_eq!(found, expected.into_iter().map(|(path, text)| (path.to_string(), text.to_string())).collect::<Vec<(String, String)>>());
+    }
+
+    #[test]
+    fn test_globs() {
+        let dir = example_dir("source_globs");
+
+        let found: Vec<String> = sources(&dir, true, &["*.json"], &["*.tar.gz", "top*"]).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(found, vec!["2020/01.zip/01/00.json", "2020/03.tar/03/01/00.json"], "include matches archive entries, exclude skips whole archives");
+
+        let found: Vec<String> = sources(&dir, true, &["02/**"], &[]).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(found, vec!["2020/02.tar.gz/02/01/00.json", "2020/02.tar.gz/02/01/01.json.gz"]);
+    }
+
+    #[test]
+    fn test_unreadable_archive_is_reported() {
+        let dir = TempDir::new("source_bad_archive");
+        dir.write("broken.zip", b"not a zip");
+        let filter = PathFilter::new(&[], &[]).unwrap();
+        let mut sources = vec![];
+        for_each_source(dir.path(), false, &filter, |_, _, _| true, |source| {
+            sources.push(source);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(sources.len(), 1);
+        assert!(sources[0].path.ends_with("broken.zip"));
+        assert!(sources[0].text.is_err());
+    }
+}
@@ -0,0 +1,47 @@
+#![allow(clippy::needless_return, clippy::too_many_arguments)]
+#![warn(missing_docs)]
+
+//! Storage, import and analysis of hourly weather readings and the predictions made alongside them
+//!
+//! The `weather` binary is a CLI/TUI on top of this library, other programs can use it to read the same database
+//!
+//! - [`db_manager`] opens and migrates the SQLite database and runs queries for a location
+//! - [`app`] wraps [`DbManager`](db_manager::DbManager) with a selected location and date based queries
+//! - [`import`] options and report for importing files
+//! - [`templates`] converts files from weather providers (DarkSky, Open-Meteo) into [`models`]
+//! - [`analysis`] calculates error metrics, calibration, confusion matrices, skill scores, rain detection scores and forecast convergence of predictions
+//! - [`export`] writes readings and predictions as CSV, JSON Lines or Parquet
+//! - [`fetch`] requests forecasts from an HTTP API for each location and imports them
+//! - [`calendar`] splits UTC hours into days in UTC or a location's local time
+//!
+//! ```no_run
+//! use weather::app::WeatherApp;
+//! use weather::db_manager::DbManager;
+//!
+//! # fn main() -> Result<(), weather::Error> {
+//! let mut db_manager = DbManager::new("weather.db")?;
+//! db_manager.init()?;
+//!
+//! let mut app = WeatherApp::new(db_manager);
+//! let reading = app.get_last_reading()?;
+//! println!("{}", reading);
+//! # Ok(())
+//! # }
+//! ```
+
+pub use error::Error;
+
+pub mod error;
+pub mod app;
+pub mod db_manager;
+pub mod models;
+pub mod templates;
+pub mod extensions;
+pub mod analysis;
+pub mod export;
+pub mod import;
+pub mod fetch;
+pub mod calendar;
+
+#[cfg(test)]
+mod test_support;
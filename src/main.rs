@@ -1,28 +1,49 @@
+#![allow(clippy::needless_return, clippy::too_many_arguments)]
+
 use app_dirs2::{AppInfo, app_root, AppDataType};
-use clap::{App, Arg, crate_description, crate_authors, crate_name, crate_version};
+use clap::{App, Arg, SubCommand, ArgMatches, crate_description, crate_authors, crate_name, crate_version};
 use simplelog::{SimpleLogger, ConfigBuilder};
 use log::{LevelFilter, error, trace, info};
-use crate::app::WeatherApp;
-use crate::db_manager::DbManager;
+use weather::app::WeatherApp;
+use weather::db_manager::DbManager;
 use crate::ui::Ui;
-use std::path::Path;
+use weather::models::SimpleDate;
+use weather::analysis::error_metrics::ErrorMetricsTable;
+use weather::analysis::calibration::CalibrationCsv;
+use weather::analysis::rain_events::RainEventsCsv;
+use weather::analysis::skill::SkillTable;
+use weather::analysis::convergence::{ConvergenceCsv, ConvergenceTable};
+use weather::analysis::{RainEvent, DEFAULT_RAIN_THRESHOLD};
+use weather::templates::importer_names;
+use weather::export::{ExportKind, ExportFormat};
+use weather::import::ImportOptions;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use weather::fetch::{FetchOptions, Fetcher, until_next};
+use weather::models::{Location, Coordinates};
+use chrono::Utc;
+use std::fs::File;
+use std::io::{BufWriter, Write, stdout};
+use chrono::{NaiveDate, DateTime, Timelike};
 
+/// Errors from the library (`weather::Error`) and the terminal
 pub type Error = Box<dyn std::error::Error>;
 
-mod app;
-mod db_manager;
-mod models;
-mod templates;
 mod ui;
-mod extensions;
-mod min_max_avg;
 
 const APP_INFO: AppInfo = AppInfo {
     name: "Weather",
     author: "Ray Britton",
 };
 
-fn main() -> Result<(), Error> {
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
     let matches = App::new(crate_name!())
         .author(crate_authors!())
         .version(crate_version!())
@@ -35,6 +56,19 @@ fn main() -> Result<(), Error> {
             .multiple(false)
             .number_of_values(1)
             .help("Weather database file to use\nFile will be created if it doesn't exist\nIf not set this program will automatically generate one in the users data directory"))
+        .arg(Arg::with_name("location")
+            .long("location")
+            .short("l")
+            .takes_value(true)
+            .value_name("ID|NAME")
+            .multiple(false)
+            .number_of_values(1)
+            .help("Location to use (see the locations command)\nIf not set the first location is used, or when importing the location is found from the coordinates in each file"))
+        .arg(Arg::with_name("local_time")
+            .takes_value(false)
+            .long("local-time")
+            .multiple(false)
+            .help("Use the location's time zone for days (--from, --to and the day and month views) instead of UTC\nDays are 23 or 25 hours when the clocks change\nCan also be switched from the main menu"))
         .arg(Arg::with_name("verbose")
             .takes_value(false)
             .short("v")
@@ -47,9 +81,53 @@ fn main() -> Result<(), Error> {
             .long("update")
             .value_name("PATH")
             .conflicts_with_all(&["path", "clear"])
-            .help("Update DB with all json files at path and exit")
+            .help("Update DB with all json files at path and exit\n.json.gz, .zip, .tar and .tar.gz files are also read")
             .multiple(false)
             .number_of_values(1))
+        .arg(Arg::with_name("format")
+            .takes_value(true)
+            .long("format")
+            .value_name("FORMAT")
+            .requires("update")
+            .possible_values(&importer_names())
+            .help("Format of files being imported\nIf not set the format of each file is detected")
+            .multiple(false)
+            .number_of_values(1))
+        .arg(Arg::with_name("dry_run")
+            .takes_value(false)
+            .long("dry-run")
+            .requires("update")
+            .help("Parse files and report what would be imported without writing anything")
+            .multiple(false))
+        .arg(Arg::with_name("force")
+            .takes_value(false)
+            .long("force")
+            .requires("update")
+            .help("Import every file, including those unchanged since they were last imported")
+            .multiple(false))
+        .arg(Arg::with_name("recursive")
+            .takes_value(false)
+            .long("recursive")
+            .short("r")
+            .requires("update")
+            .help("Import from subdirectories of --update as well")
+            .multiple(false))
+        .arg(Arg::with_name("include")
+            .takes_value(true)
+            .long("include")
+            .value_name("GLOB")
+            .requires("update")
+            .help("Only import files and archive entries matching glob (relative to --update, * matches across directories)\nCan be used multiple times")
+            .multiple(true)
+            .number_of_values(1))
+        .arg(Arg::with_name("exclude")
+            .takes_value(true)
+            .long("exclude")
+            .value_name("GLOB")
+            .requires("update")
+            .help("Skip files, archives and archive entries matching glob\nCan be used multiple times")
+            .multiple(true)
+            .number_of_values(1))
         .arg(Arg::with_name("path")
             .takes_value(false)
             .long("path")
@@ -62,6 +140,122 @@ fn main() -> Result<(), Error> {
             .help("Delete database file")
             .conflicts_with_all(&["update", "path"])
             .multiple(false))
+        .subcommand(SubCommand::with_name("locations")
+            .about("Print all locations and exit")
+            .arg(Arg::with_name("rename")
+                .long("rename")
+                .takes_value(true)
+                .value_name("NAME")
+                .multiple(false)
+                .number_of_values(1)
+                .help("Rename the location set with --location")))
+        .subcommand(SubCommand::with_name("errors")
+            .about("Print MAE, RMSE and bias of predictions for each lead time and exit")
+            .arg(date_arg("from", "First day to include (YYYY-MM-DD)\nDefaults to the first reading"))
+            .arg(date_arg("to", "Last day to include (YYYY-MM-DD)\nDefaults to the last reading")))
+        .subcommand(SubCommand::with_name("skill")
+            .about("Print skill scores (1 - MSE / baseline MSE) against persistence and climatology for each lead time and exit")
+            .arg(date_arg("from", "First day to include (YYYY-MM-DD)\nDefaults to the first reading"))
+            .arg(date_arg("to", "Last day to include (YYYY-MM-DD)\nDefaults to the last reading")))
+        .subcommand(SubCommand::with_name("calibration")
+            .about("Print Brier score and reliability table of precipitation probability for each lead time as CSV and exit")
+            .arg(date_arg("from", "First day to include (YYYY-MM-DD)\nDefaults to the first reading"))
+            .arg(date_arg("to", "Last day to include (YYYY-MM-DD)\nDefaults to the last reading"))
+            .args(&rain_event_args())
+            .arg(output_arg("File to write CSV to\nIf not set CSV is printed")))
+        .subcommand(SubCommand::with_name("rain")
+            .about("Print POD, FAR, CSI, ETS and ROC area of rain forecasts for each lead time and probability threshold as CSV and exit")
+            .arg(date_arg("from", "First day to include (YYYY-MM-DD)\nDefaults to the first reading"))
+            .arg(date_arg("to", "Last day to include (YYYY-MM-DD)\nDefaults to the last reading"))
+            .args(&rain_event_args())
+            .arg(output_arg("File to write CSV to\nIf not set CSV is printed")))
+        .subcommand(SubCommand::with_name("convergence")
+            .about("Print every forecast of each variable for one hour, ending at the reading, as CSV and exit")
+            .arg(date_arg("date", "Day of the hour (YYYY-MM-DD)")
+                .required(true))
+            .arg(Arg::with_name("hour")
+                .long("hour")
+                .takes_value(true)
+                .value_name("HOUR")
+                .multiple(false)
+                .number_of_values(1)
+                .required(true)
+                .help("Hour of the day (0 - 23)"))
+            .arg(Arg::with_name("summary")
+                .long("summary")
+                .short("s")
+                .takes_value(false)
+                .multiple(false)
+                .help("Print flip-flops, total variation and the lead time the forecast came within tolerance for each variable instead"))
+            .arg(output_arg("File to write to\nIf not set output is printed")))
+        .subcommand(SubCommand::with_name("export")
+            .about("Export readings, predictions or predictions joined with readings and exit")
+            .arg(Arg::with_name("type")
+                .long("type")
+                .short("t")
+                .takes_value(true)
+                .value_name("TYPE")
+                .multiple(false)
+                .number_of_values(1)
+                .possible_values(&ExportKind::NAMES)
+                .default_value("readings")
+                .help("What to export\njoined is one row per prediction with the reading for the hour it predicted"))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .short("f")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .multiple(false)
+                .number_of_values(1)
+                .possible_values(&ExportFormat::NAMES)
+                .help("File format\nDefaults to the extension of --output, or csv"))
+            .arg(date_arg("from", "First day to include (YYYY-MM-DD)\nDefaults to the first reading"))
+            .arg(date_arg("to", "Last day to include (YYYY-MM-DD)\nDefaults to the last reading"))
+            .arg(output_arg("File to write to\nIf not set data is printed")))
+        .subcommand(SubCommand::with_name("reparse")
+            .about("Rebuild readings and predictions from the documents they were imported from and exit\nOnly for --location if set, otherwise every location"))
+        .subcommand(SubCommand::with_name("fetch")
+            .about("Fetch forecasts for each location from an HTTP API, save them in an archive directory and import them\nRuns every --interval minutes until stopped unless --once is set")
+            .arg(Arg::with_name("url")
+                .long("url")
+                .takes_value(true)
+                .value_name("URL")
+                .required(true)
+                .multiple(false)
+                .number_of_values(1)
+                .help("Forecast endpoint, {latitude} and {longitude} are replaced with each location's coordinates\n{time} (unix timestamp) and {hour} (YYYY-MM-DDTHH:00) are replaced with the hour being fetched in UTC"))
+            .arg(Arg::with_name("archive")
+                .long("archive")
+                .takes_value(true)
+                .value_name("DIR")
+                .multiple(false)
+                .number_of_values(1)
+                .help("Directory to save responses in\nDefaults to 'archive' next to the database"))
+            .arg(Arg::with_name("coordinates")
+                .long("coordinates")
+                .takes_value(true)
+                .value_name("LAT,LON")
+                .allow_hyphen_values(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Location to fetch, added if it doesn't exist\nIf neither this or --location is set every location with coordinates is fetched"))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .possible_values(&importer_names())
+                .multiple(false)
+                .number_of_values(1)
+                .help("Format of responses\nIf not set the format is detected"))
+            .arg(number_arg("interval", "MINUTES", "60", "Minutes between fetches"))
+            .arg(number_arg("retries", "COUNT", "3", "Times to retry a request after a network error, 429 or 5xx response"))
+            .arg(number_arg("backoff", "SECONDS", "5", "Wait before the first retry, doubled for each retry after"))
+            .arg(number_arg("fill-missed", "HOURS", "0", "Hours to look back for hours with no reading, these are fetched as well\nRequires {time} or {hour} in --url"))
+            .arg(Arg::with_name("once")
+                .long("once")
+                .takes_value(false)
+                .multiple(false)
+                .help("Fetch once and exit")))
         .get_matches();
 
     let verbosity = matches.occurrences_of("verbose");
@@ -105,10 +299,110 @@ fn main() -> Result<(), Error> {
 
     let mut app = WeatherApp::new(db_manager);
 
+    let location_id = match matches.value_of("location") {
+        Some(location) => Some(app.find_location(location)?.id),
+        None => None
+    };
+    if let Some(location_id) = location_id {
+        app.set_location(location_id);
+    }
+    app.set_local_time(matches.is_present("local_time"));
+
     if let Some(update_dir) = matches.value_of("update") {
         trace!("Importing...");
-        app.import_data(update_dir.to_string())?;
-        info!("Done");
+        let options = ImportOptions {
+            format: matches.value_of("format").map(String::from),
+            location_id,
+            dry_run: matches.is_present("dry_run"),
+            recursive: matches.is_present("recursive"),
+            include: matches.values_of("include").map(|globs| globs.map(String::from).collect()).unwrap_or_default(),
+            exclude: matches.values_of("exclude").map(|globs| globs.map(String::from).collect()).unwrap_or_default(),
+            force: matches.is_present("force"),
+        };
+        let report = app.import_data_with_progress(update_dir.to_string(), &options, |report| {
+            eprint!("\rRead {} files ({} skipped, {} rejected)", report.files.len(), report.skipped(), report.rejected().len());
+        })?;
+        eprintln!();
+        print!("{}", report);
+    } else if let Some(locations_matches) = matches.subcommand_matches("locations") {
+        if let Some(name) = locations_matches.value_of("rename") {
+            let location_id = location_id.ok_or("--location must be set to rename")?;
+            app.rename_location(location_id, name)?;
+        }
+        for location in app.get_locations()? {
+            match &location.coordinates {
+                Some(coordinates) => println!("{}  ({}, {}) {}", location, coordinates.latitude, coordinates.longitude, coordinates.timezone.as_deref().unwrap_or("")),
+                None => println!("{}", location)
+            }
+        }
+    } else if let Some(errors_matches) = matches.subcommand_matches("errors") {
+        let (start, end) = date_range(&mut app, errors_matches)?;
+        let results = app.get_error_metrics(start, end)?;
+        print!("{}", ErrorMetricsTable(&results));
+    } else if let Some(skill_matches) = matches.subcommand_matches("skill") {
+        let (start, end) = date_range(&mut app, skill_matches)?;
+        let results = app.get_skill_scores(start, end)?;
+        print!("{}", SkillTable(&results));
+    } else if let Some(calibration_matches) = matches.subcommand_matches("calibration") {
+        let (start, end) = date_range(&mut app, calibration_matches)?;
+        let event = rain_event(calibration_matches)?;
+        CalibrationCsv(&app.get_calibration(start, end, event)?).write(output(calibration_matches)?)?;
+    } else if let Some(rain_matches) = matches.subcommand_matches("rain") {
+        let (start, end) = date_range(&mut app, rain_matches)?;
+        let event = rain_event(rain_matches)?;
+        RainEventsCsv(&app.get_rain_events(start, end, event)?).write(output(rain_matches)?)?;
+    } else if let Some(convergence_matches) = matches.subcommand_matches("convergence") {
+        let target = target_hour(&mut app, convergence_matches)?;
+        let results = app.get_convergence(target.year, target.day, target.hour)?;
+        let mut out = output(convergence_matches)?;
+        if convergence_matches.is_present("summary") {
+            write!(out, "{}", ConvergenceTable(&results))?;
+            out.flush()?;
+        } else {
+            ConvergenceCsv(&results).write(out)?;
+        }
+    } else if let Some(export_matches) = matches.subcommand_matches("export") {
+        let (start, end) = date_range(&mut app, export_matches)?;
+        let kind: ExportKind = export_matches.value_of("type").unwrap().parse()?;
+        let format = match export_matches.value_of("format") {
+            Some(format) => format.parse()?,
+            None => export_matches.value_of("output").and_then(ExportFormat::for_path).unwrap_or(ExportFormat::Csv)
+        };
+        let count = app.export(kind, format, start, end, output(export_matches)?)?;
+        info!("Exported {} rows", count);
+    } else if matches.subcommand_matches("reparse").is_some() {
+        let report = app.reparse(|report| {
+            eprint!("\rReparsed {} files ({} rejected)", report.files.len(), report.rejected().len());
+        })?;
+        eprintln!();
+        print!("{}", report);
+    } else if let Some(fetch_matches) = matches.subcommand_matches("fetch") {
+        let archive_dir = match fetch_matches.value_of("archive") {
+            Some(dir) => PathBuf::from(dir),
+            None => Path::new(&db_file).parent().unwrap_or_else(|| Path::new(".")).join("archive")
+        };
+        let mut options = FetchOptions::new(fetch_matches.value_of("url").unwrap(), archive_dir);
+        options.format = fetch_matches.value_of("format").map(String::from);
+        options.retries = fetch_matches.value_of("retries").unwrap().parse()?;
+        options.backoff = Duration::from_secs(fetch_matches.value_of("backoff").unwrap().parse()?);
+        options.fill_missed = fetch_matches.value_of("fill-missed").unwrap().parse()?;
+        let interval = Duration::from_secs(fetch_matches.value_of("interval").unwrap().parse::<u64>()? * 60);
+        let locations = fetch_locations(&mut app, location_id, fetch_matches)?;
+        if locations.is_empty() {
+            return Err(Error::from("No locations with coordinates to fetch, add one with --coordinates"));
+        }
+
+        let fetcher = Fetcher::new(options);
+        loop {
+            let report = fetcher.run_once(&mut app, &locations, Utc::now().naive_utc())?;
+            print!("{}", report);
+            if fetch_matches.is_present("once") {
+                break;
+            }
+            let wait = until_next(Utc::now().naive_utc(), interval);
+            info!("Next fetch in {} seconds", wait.as_secs());
+            std::thread::sleep(wait);
+        }
     } else if matches.is_present("path") {
         println!("{}", db_file);
     } else if matches.is_present("clear") {
@@ -131,3 +425,149 @@ fn int_to_log_level(count: u64) -> log::LevelFilter {
     };
 }
 
+fn number_arg<'a>(name: &'a str, value_name: &'a str, default: &'a str, help: &'a str) -> Arg<'a, 'a> {
+    return Arg::with_name(name)
+        .long(name)
+        .takes_value(true)
+        .value_name(value_name)
+        .multiple(false)
+        .number_of_values(1)
+        .default_value(default)
+        .help(help);
+}
+
+/// Locations to fetch, those given with `--coordinates` (added if needed) and `--location`,
+/// or every location with coordinates if neither is set
+fn fetch_locations(app: &mut WeatherApp, location_id: Option<i64>, matches: &ArgMatches) -> Result<Vec<Location>, Error> {
+    let mut ids = vec![];
+    if let Some(values) = matches.values_of("coordinates") {
+        for value in values {
+            let (latitude, longitude) = value.split_once(',').ok_or("Coordinates must be LAT,LON")?;
+            let coordinates = Coordinates { latitude: latitude.trim().parse()?, longitude: longitude.trim().parse()?, timezone: None };
+            ids.push(app.add_location(&coordinates)?);
+        }
+    }
+    ids.extend(location_id);
+
+    return Ok(app.get_locations()?
+        .into_iter()
+        .filter(|location| location.coordinates.is_some() && (ids.is_empty() || ids.contains(&location.id)))
+        .collect());
+}
+
+fn date_arg<'a>(name: &'a str, help: &'a str) -> Arg<'a, 'a> {
+    return Arg::with_name(name)
+        .long(name)
+        .takes_value(true)
+        .value_name("DATE")
+        .multiple(false)
+        .number_of_values(1)
+        .help(help);
+}
+
+fn output_arg<'a>(help: &'a str) -> Arg<'a, 'a> {
+    return Arg::with_name("output")
+        .long("output")
+        .short("o")
+        .takes_value(true)
+        .value_name("PATH")
+        .multiple(false)
+        .number_of_values(1)
+        .help(help);
+}
+
+/// File set by `--output`, or stdout if not set
+///
+/// # Errors
+/// Unable to create the file
+///
+fn output(matches: &ArgMatches) -> Result<Box<dyn Write + Send>, Error> {
+    return Ok(match matches.value_of("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(stdout())
+    });
+}
+
+fn rain_event_args<'a>() -> Vec<Arg<'a, 'a>> {
+    return vec![
+        Arg::with_name("threshold")
+            .long("threshold")
+            .takes_value(true)
+            .value_name("MM/H")
+            .multiple(false)
+            .number_of_values(1)
+            .conflicts_with("precip_type")
+            .help("Observed precipitation intensity above which an hour counts as rainy (defaults to 0.1)"),
+        Arg::with_name("precip_type")
+            .long("precip-type")
+            .takes_value(false)
+            .multiple(false)
+            .help("Count an hour as rainy if any precipitation type was observed, instead of using a threshold"),
+    ];
+}
+
+/// Read the `threshold` and `precip_type` args of a subcommand
+///
+/// # Errors
+/// Threshold is not a number
+///
+fn rain_event(matches: &ArgMatches) -> Result<RainEvent, Error> {
+    if matches.is_present("precip_type") {
+        return Ok(RainEvent::PrecipType);
+    }
+    let threshold = match matches.value_of("threshold") {
+        Some(value) => value.parse().map_err(|err| Error::from(format!("Invalid threshold '{}': {}", value, err)))?,
+        None => DEFAULT_RAIN_THRESHOLD
+    };
+    return Ok(RainEvent::Intensity(threshold));
+}
+
+/// Read the `from` and `to` args of a subcommand
+///
+/// # Errors
+/// Date is not in YYYY-MM-DD format
+/// No readings in database (if either arg is missing)
+///
+/// # Returns
+/// Start (first hour of from) and end (last hour of to), days are in local time if `--local-time` is set
+///
+fn date_range(app: &mut WeatherApp, matches: &ArgMatches) -> Result<(SimpleDate, SimpleDate), Error> {
+    let calendar = app.calendar()?;
+    let start = match matches.value_of("from") {
+        Some(date) => parse_date(date)?,
+        None => calendar.date_of(app.get_first_reading()?.timestamp)
+    };
+    let end = match matches.value_of("to") {
+        Some(date) => parse_date(date)?,
+        None => calendar.date_of(app.get_last_reading()?.timestamp)
+    };
+
+    return Ok(calendar.day_range(start, end));
+}
+
+/// Read the `date` and `hour` args of a subcommand
+///
+/// # Errors
+/// Date is not in YYYY-MM-DD format
+/// Hour is not 0 - 23, or doesn't exist on that day (skipped by daylight saving)
+///
+/// # Returns
+/// The UTC hour, the date and hour are in local time if `--local-time` is set
+///
+fn target_hour(app: &mut WeatherApp, matches: &ArgMatches) -> Result<SimpleDate, Error> {
+    let date = parse_date(matches.value_of("date").expect("date is required"))?;
+    let value = matches.value_of("hour").expect("hour is required");
+    let hour: u32 = value.parse().map_err(|err| Error::from(format!("Invalid hour '{}': {}", value, err)))?;
+    let calendar = app.calendar()?;
+
+    let timestamp = calendar.hours_of_day(date).into_iter()
+        .find(|timestamp| calendar.local_time(*timestamp).hour() == hour)
+        .ok_or_else(|| format!("Hour {} is not on {} in {}", hour, date, calendar.name()))?;
+
+    return Ok(DateTime::from_timestamp(timestamp, 0).map(|time| time.naive_utc()).ok_or("Invalid timestamp")?.into());
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, Error> {
+    return NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|err| Error::from(format!("Invalid date '{}': {}", value, err)));
+}
@@ -1,18 +1,22 @@
+//! Readings, predictions and locations as stored in the database
+
 use serde::{Serialize, Deserialize};
 use log::error;
 use rusqlite::types::{ToSql, FromSql, FromSqlResult, ValueRef};
 use rusqlite::Error;
 use rusqlite::types::ToSqlOutput;
 use std::fmt::Display;
-use serde::export::Formatter;
+use std::fmt::Formatter;
 use std::fmt;
 use chrono::NaiveDateTime;
 
+/// Reading and the predictions made at that time (the first is for the next hour)
+pub type ReadingWithPredictions = (Weather, Vec<Weather>);
+
+/// Observed weather for one hour at a location
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Weather {
-    /// ID of weather reading, should be '{year}-{day}-{hour}'
-    pub id: String,
-    /// Unix timestamp of reading
+    /// Unix timestamp of the start of the UTC hour of reading, unique for each location
     pub timestamp: i64,
     /// UTC year of reading
     pub year: u16,
@@ -34,14 +38,19 @@ pub struct Weather {
     pub wind_gust: f64,
     /// Relative humidity percentage
     pub humidity: f64,
-    // Optional type of precipitation (only `rain`, `snow`, `sleet` and `None` are supported)
+    /// Optional type of precipitation (only `rain`, `snow`, `sleet` and `None` are supported)
     pub precip_type: Option<String>,
+    /// Values not every provider (or older database rows) has
+    pub details: WeatherDetails,
 }
 
+/// Weather forecast for an hour, made at the time of a reading
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Prediction {
-    /// ID of weather reading, should be '{prediction_year}-{prediction_day}-{prediction_hour}-{reading_year}-{reading_day}-{reading_hour}'
-    pub id: String,
+    /// Unix timestamp of the start of the UTC hour of reading
+    pub reading_timestamp: i64,
+    /// Unix timestamp of the start of the UTC hour being predicted
+    pub prediction_timestamp: i64,
     /// UTC year of reading
     pub reading_year: u16,
     /// UTC day of year of reading
@@ -70,55 +79,124 @@ pub struct Prediction {
     pub wind_gust: f64,
     /// Relative humidity percentage
     pub humidity: f64,
-    // Optional type of precipitation (only `rain`, `snow`, `sleet` and `None` are supported)
+    /// Optional type of precipitation (only `rain`, `snow`, `sleet` and `None` are supported)
     pub precip_type: Option<String>,
+    /// Values not every provider (or older database rows) has
+    pub details: WeatherDetails,
 }
 
-impl Weather {
-    pub fn new(id: String, year: u16, day: u16, hour: u8, icon: Icon, precip_intensity: f64, precip_probability: f64, temp: f64, wind_speed: f64, wind_gust: f64, humidity: f64, precip_type: Option<String>) -> Weather {
-        let timestamp = Into::<NaiveDateTime>::into(SimpleDate::new(year, day, hour)).timestamp();
-        return Weather { id, timestamp, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type };
-    }
+/// Extra measurements, each is `None` if the provider doesn't supply it
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct WeatherDetails {
+    /// Air temperature in celsius
+    pub air_temp: Option<f64>,
+    /// Dew point in celsius
+    pub dew_point: Option<f64>,
+    /// Sea level air pressure in hectopascals
+    pub pressure: Option<f64>,
+    /// Between 0 and 1, percentage of sky occluded by clouds
+    pub cloud_cover: Option<f64>,
+    /// Direction the wind is coming from in degrees, with 0 being north
+    pub wind_bearing: Option<f64>,
+    /// UV index
+    pub uv_index: Option<f64>,
+    /// Average visibility in kilometers (capped at 16)
+    pub visibility: Option<f64>,
+    /// Columnar density of ozone in Dobson units
+    pub ozone: Option<f64>,
+    /// Human readable summary (e.g. 'Mostly Cloudy')
+    pub summary: Option<String>,
 }
 
-impl Prediction {
-    pub fn new(id: String, reading_year: u16, reading_day: u16, reading_hour: u8, prediction_year: u16, prediction_day: u16, prediction_hour: u8, hour_diff: u8, icon: Icon, precip_intensity: f64, precip_probability: f64, temp: f64, wind_speed: f64, wind_gust: f64, humidity: f64, precip_type: Option<String>) -> Prediction {
-        return Prediction { id, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type };
+impl WeatherDetails {
+    /// Compass point (e.g. `NE`) for the wind bearing
+    pub fn wind_direction(&self) -> Option<&'static str> {
+        const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+        return self.wind_bearing.map(|bearing| POINTS[((bearing.rem_euclid(360.) + 22.5) / 45.) as usize % 8]);
     }
 }
 
 impl Weather {
-    pub fn simple_date(&self) -> SimpleDate {
-        SimpleDate::new(self.year, self.day, self.hour)
+    /// Reading without details, `timestamp` is calculated from the date
+    pub fn new(year: u16, day: u16, hour: u8, icon: Icon, precip_intensity: f64, precip_probability: f64, temp: f64, wind_speed: f64, wind_gust: f64, humidity: f64, precip_type: Option<String>) -> Weather {
+        let timestamp = SimpleDate::new(year, day, hour).timestamp();
+        return Weather { timestamp, year, day, hour, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type, details: WeatherDetails::default() };
     }
 
-    pub fn date(&self) -> NaiveDateTime {
-        self.simple_date().into()
+    /// Replace the details
+    pub fn with_details(mut self, details: WeatherDetails) -> Weather {
+        self.details = details;
+        return self;
     }
 }
 
 impl Prediction {
-    pub fn simple_reading_date(&self) -> SimpleDate {
-        SimpleDate::new(self.reading_year, self.reading_day, self.reading_hour)
+    /// Prediction without details, both timestamps are calculated from the dates
+    pub fn new(reading_year: u16, reading_day: u16, reading_hour: u8, prediction_year: u16, prediction_day: u16, prediction_hour: u8, hour_diff: u8, icon: Icon, precip_intensity: f64, precip_probability: f64, temp: f64, wind_speed: f64, wind_gust: f64, humidity: f64, precip_type: Option<String>) -> Prediction {
+        let reading_timestamp = SimpleDate::new(reading_year, reading_day, reading_hour).timestamp();
+        let prediction_timestamp = SimpleDate::new(prediction_year, prediction_day, prediction_hour).timestamp();
+        return Prediction { reading_timestamp, prediction_timestamp, reading_year, reading_day, reading_hour, prediction_year, prediction_day, prediction_hour, hour_diff, icon, precip_intensity, precip_probability, temp, wind_speed, wind_gust, humidity, precip_type, details: WeatherDetails::default() };
     }
 
-    pub fn simple_prediction_date(&self) -> SimpleDate {
-        SimpleDate::new(self.prediction_year, self.prediction_day, self.prediction_hour)
+    /// Replace the details
+    pub fn with_details(mut self, details: WeatherDetails) -> Prediction {
+        self.details = details;
+        return self;
     }
 
-    pub fn reading_date(&self) -> NaiveDateTime {
-        self.simple_reading_date().into()
+    /// Compare this prediction against what was actually observed
+    ///
+    /// All differences are signed as `predicted - observed`, so positive values mean the prediction was too high
+    pub fn diff(&self, observed: &Weather) -> PredictionDiff {
+        return PredictionDiff {
+            hour_diff: self.hour_diff,
+            icon_matched: self.icon == observed.icon,
+            precip_intensity: self.precip_intensity - observed.precip_intensity,
+            precip_probability: self.precip_probability - observed.precip_probability,
+            temp: self.temp - observed.temp,
+            wind_speed: self.wind_speed - observed.wind_speed,
+            wind_gust: self.wind_gust - observed.wind_gust,
+            humidity: self.humidity - observed.humidity,
+        };
+    }
+}
+
+impl Weather {
+    /// UTC hour of reading
+    pub fn simple_date(&self) -> SimpleDate {
+        SimpleDate::new(self.year, self.day, self.hour)
     }
 
-    pub fn prediction_date(&self) -> NaiveDateTime {
-        self.simple_prediction_date().into()
+    /// UTC hour of reading
+    pub fn date(&self) -> NaiveDateTime {
+        self.simple_date().into()
     }
 }
 
+/// Signed error of a single prediction compared to the observed weather (`predicted - observed`)
+#[derive(Debug, Serialize, Deserialize, Copy, Clone)]
+pub struct PredictionDiff {
+    /// How many hours forward the prediction was made
+    pub hour_diff: u8,
+    /// True if the predicted icon was the same as the observed icon
+    pub icon_matched: bool,
+    /// Precipitation error in millimeters per hour
+    pub precip_intensity: f64,
+    /// Precipitation probability error (between -1 and 1)
+    pub precip_probability: f64,
+    /// 'Feels like' temperature error in celsius
+    pub temp: f64,
+    /// Average wind speed error in meters per hour
+    pub wind_speed: f64,
+    /// Wind gust speed error in meters per hour
+    pub wind_gust: f64,
+    /// Relative humidity error (between -1 and 1)
+    pub humidity: f64,
+}
+
 impl Display for Weather {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, r#"
-ID        {}
 Year      {}
 Day       {}
 Hour      {}
@@ -130,8 +208,8 @@ Temp      {}°C
 W. Speed  {} m/s
 W. Gust   {} m/s
 Humidity  {}%
+{}
         "#,
-               self.id,
                self.year,
                self.day,
                self.hour,
@@ -142,14 +220,14 @@ Humidity  {}%
                self.temp,
                self.wind_speed,
                self.wind_gust,
-               self.humidity * 100.)
+               self.humidity * 100.,
+               DetailsDisplay(&self.details, ""))
     }
 }
 
 impl Display for Prediction {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, r#"
- ID        {}
  R Year    {}
  R Day     {}
  R Hour    {}
@@ -165,8 +243,8 @@ impl Display for Prediction {
  W. Speed  {} m/s
  W. Gust   {} m/s
  Humidity  {}%
+{}
         "#,
-               self.id,
                self.reading_year,
                self.reading_day,
                self.reading_hour,
@@ -181,30 +259,84 @@ impl Display for Prediction {
                self.temp,
                self.wind_speed,
                self.wind_gust,
-               self.humidity * 100.)
+               self.humidity * 100.,
+               DetailsDisplay(&self.details, " "))
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Lines for each detail, with `prefix` before each line
+struct DetailsDisplay<'a>(&'a WeatherDetails, &'a str);
+
+impl Display for DetailsDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let details = self.0;
+        let value = |value: Option<f64>, multiplier: f64, unit: &str| match value {
+            Some(value) => format!("{}{}", value * multiplier, unit),
+            None => String::from("-")
+        };
+        let lines = [
+            ("Air Temp", value(details.air_temp, 1., "°C")),
+            ("Dew Point", value(details.dew_point, 1., "°C")),
+            ("Pressure", value(details.pressure, 1., " hPa")),
+            ("Cloud", value(details.cloud_cover, 100., "%")),
+            ("W. Dir", match (details.wind_bearing, details.wind_direction()) {
+                (Some(bearing), Some(direction)) => format!("{}° {}", bearing, direction),
+                _ => String::from("-")
+            }),
+            ("UV Index", value(details.uv_index, 1., "")),
+            ("Vis. Dist", value(details.visibility, 1., " km")),
+            ("Ozone", value(details.ozone, 1., " DU")),
+            ("Summary", details.summary.clone().unwrap_or_else(|| String::from("-"))),
+        ];
+        let text = lines.iter()
+            .map(|(title, value)| format!("{}{: <10}{}", self.1, title, value))
+            .collect::<Vec<String>>()
+            .join("\n");
+        write!(f, "{}", text)
+    }
+}
+
+/// Summary of the weather, as named by DarkSky
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Icon {
+    /// `rain`
     Rain,
+    /// `thunderstorm`
     Thunderstorm,
+    /// `fog`
     Fog,
+    /// `snow`
     Snow,
+    /// `partly-cloudy-day` or `partly-cloudy-night`
     PartlyCloudy,
+    /// `cloudy`
     Cloudy,
+    /// `hail`
     Hail,
+    /// `sleet`
     Sleet,
+    /// `clear-day` or `clear-night`
     Clear,
+    /// `wind`
     Wind,
 
+    /// Unrecognised name in an imported file
     Unknown,
 }
 
 impl Icon {
+    /// Every icon, in declaration order
+    pub const ALL: [Icon; 11] = [Icon::Rain, Icon::Thunderstorm, Icon::Fog, Icon::Snow, Icon::PartlyCloudy, Icon::Cloudy, Icon::Hail, Icon::Sleet, Icon::Clear, Icon::Wind, Icon::Unknown];
+
+    /// Position of icon in `Icon::ALL`
+    pub fn index(self) -> usize {
+        return Icon::ALL.iter().position(|icon| icon == &self).expect("Icon missing from ALL");
+    }
+
     /// Attempt to convert a string into an icon
     ///
     /// Will return `Icon::Unknown` and log error for any unrecognised input
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(name: &str) -> Icon {
         return match name {
             "rain" => Icon::Rain,
@@ -224,7 +356,8 @@ impl Icon {
         };
     }
 
-    pub fn to_str(&self) -> &'static str {
+    /// DarkSky name for the icon, as stored in the database (day variants are used for clear and partly cloudy)
+    pub fn to_str(self) -> &'static str {
         return match self {
             Icon::Rain => "rain",
             Icon::Thunderstorm => "thunderstorm",
@@ -249,16 +382,49 @@ impl ToSql for Icon {
 
 impl FromSql for Icon {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-        FromSqlResult::from(value.as_str().map(|str| Icon::from_str(str)))
+        FromSqlResult::from(value.as_str().map(Icon::from_str))
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct WeatherReading {
-    current: Weather,
-    prediction: Vec<Weather>,
+/// Place that readings and predictions were recorded for
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Location {
+    /// Database id
+    pub id: i64,
+    /// User chosen name, defaults to the timezone and coordinates
+    pub name: String,
+    /// Position the data was requested for (missing for data imported before locations were recorded)
+    pub coordinates: Option<Coordinates>,
+}
+
+/// Position and timezone from an imported file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Coordinates {
+    /// Degrees north
+    pub latitude: f64,
+    /// Degrees east
+    pub longitude: f64,
+    /// IANA timezone name (e.g. Europe/London)
+    pub timezone: Option<String>,
 }
 
+impl Coordinates {
+    /// Name for a new location at these coordinates
+    pub fn default_name(&self) -> String {
+        return match &self.timezone {
+            Some(timezone) => format!("{} ({:.2}, {:.2})", timezone, self.latitude, self.longitude),
+            None => format!("{:.2}, {:.2}", self.latitude, self.longitude)
+        };
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{: >3}  {}", self.id, self.name)
+    }
+}
+
+/// UTC hour as year, day of year and hour, as stored in the database
 #[derive(Debug, Serialize, Deserialize, Copy, Clone)]
 pub struct SimpleDate {
     /// Four digit year (e.g. 2020)
@@ -281,6 +447,11 @@ impl SimpleDate {
             hour
         };
     }
+
+    /// Unix timestamp of the start of the hour
+    pub fn timestamp(&self) -> i64 {
+        return Into::<NaiveDateTime>::into(*self).and_utc().timestamp();
+    }
 }
 
 impl From<SimpleDate> for (u16, u16, u8) {
@@ -288,3 +459,26 @@ impl From<SimpleDate> for (u16, u16, u8) {
         return (value.year, value.day, value.hour);
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prediction_diff() {
+        let observed = Weather::new(2019, 326, 4, Icon::Rain, 0.5, 0.6, 4.0, 3.0, 8.0, 0.9, Some(String::from("rain")));
+        let prediction = Prediction::new(2019, 325, 4, 2019, 326, 4, 24, Icon::Cloudy, 0.25, 0.4, 5.5, 2.0, 10.0, 0.8, None);
+
+        let diff = prediction.diff(&observed);
+
+        assert_eq!(diff.hour_diff, 24);
+        assert!(!diff.icon_matched, "cloudy is not rain");
+        assert_eq!(diff.precip_intensity, -0.25);
+        assert!((diff.precip_probability - -0.2).abs() < 1e-9, "prob");
+        assert_eq!(diff.temp, 1.5);
+        assert_eq!(diff.wind_speed, -1.0);
+        assert_eq!(diff.wind_gust, 2.0);
+        assert!((diff.humidity - -0.1).abs() < 1e-9, "humidity");
+    }
+}
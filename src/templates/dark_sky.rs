@@ -0,0 +1,266 @@
+//! DarkSky forecast files
+
+use serde::{Serialize, Deserialize};
+use crate::models::{Weather, Icon, Coordinates, WeatherDetails};
+use crate::templates::{Importer, ImportedWeather};
+use crate::Error;
+use chrono::{DateTime, Datelike, Timelike};
+use std::convert::TryFrom;
+use serde_json::Value;
+
+/// Imports DarkSky forecast responses (`currently` and `hourly` blocks, SI units)
+pub struct DarkSkyImporter;
+
+impl Importer for DarkSkyImporter {
+    fn name(&self) -> &'static str {
+        return "dark-sky";
+    }
+
+    fn detect(&self, json: &Value) -> bool {
+        return json.get("currently").is_some() && json["hourly"].get("data").is_some();
+    }
+
+    fn import(&self, json: &str) -> Result<ImportedWeather, Error> {
+        let reading = serde_json::from_str::<DarkSkyReading>(json)?;
+        let coordinates = reading.coordinates();
+        let (current, future) = reading.get_weather()?;
+
+        return Ok(ImportedWeather {
+            coordinates,
+            readings: vec![(current, future)],
+        });
+    }
+}
+
+/// DarkSky forecast response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DarkSkyReading {
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    /// IANA timezone name
+    timezone: Option<String>,
+    currently: DarkSkyWeather,
+    hourly: DarkSkyPrediction,
+}
+
+impl DarkSkyReading {
+    /// `currently` as the reading, with every hour in `hourly` after it as predictions
+    ///
+    /// Hours are matched by time as `hourly` doesn't always start at the current hour
+    ///
+    /// # Errors
+    /// Any time can't be converted to a date
+    ///
+    pub fn get_weather(self) -> Result<(Weather, Vec<Weather>), Error> {
+        let current_hour = self.currently.time - self.currently.time % 3600;
+        let future = self.hourly.data.iter()
+            .filter(|weather| weather.time > current_hour)
+            .map(Weather::try_from)
+            .collect::<Result<_, _>>()?;
+        return Ok((Weather::try_from(self.currently)?, future));
+    }
+
+    /// Position of the forecast, `None` if the file doesn't have it
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        return match (self.latitude, self.longitude) {
+            (Some(latitude), Some(longitude)) => Some(Coordinates { latitude, longitude, timezone: self.timezone.clone() }),
+            _ => None
+        };
+    }
+}
+
+/// Data will generally contain 49 entries starting at the current hour, which should be the same as `currently`
+/// Time machine requests (for a past time) instead have the 24 hours of that day, starting at local midnight
+#[derive(Debug, Serialize, Deserialize)]
+struct DarkSkyPrediction {
+    data: Vec<DarkSkyWeather>
+}
+
+/// Dark Sky Weather Reading (from `currently` or `hourly`)
+/// Units are expected to be SI
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DarkSkyWeather {
+    /// Unix timestamp
+    time: u64,
+    /// One of
+    /// clear-day, clear-night, sleet, hail, cloudy, partly-cloudy-day, partly-cloudy-night, snow, fog, thunderstorm, rain
+    /// Possibly others too
+    icon: String,
+    /// 0+
+    precip_intensity: f64,
+    /// Between 0 and 1, percentage probability of precipitation occurring
+    precip_probability: f64,
+    /// Air temperature
+    temperature: f64,
+    /// 'Feels like' temperature
+    apparent_temperature: f64,
+    /// Between 0 and 1, percentage of sky occluded by clouds
+    cloud_cover: f64,
+    /// 0+, average wind speed
+    wind_speed: f64,
+    /// 0+, wind gust speed
+    wind_gust: f64,
+    /// Between 0 and 1, relative humidity percentage
+    humidity: f64,
+    /// Optional, one of
+    /// `rain`, `snow`, `sleet`
+    precip_type: Option<String>,
+    /// Human readable summary (e.g. 'Mostly Cloudy')
+    summary: Option<String>,
+    /// Dew point in celsius
+    dew_point: Option<f64>,
+    /// Sea level air pressure in hectopascals
+    pressure: Option<f64>,
+    /// Direction the wind is coming from in degrees, with 0 being north
+    /// Not present if there is no wind
+    wind_bearing: Option<f64>,
+    /// UV index
+    uv_index: Option<f64>,
+    /// Average visibility in kilometers (capped at 16)
+    visibility: Option<f64>,
+    /// Columnar density of ozone in Dobson units
+    ozone: Option<f64>,
+}
+
+impl DarkSkyWeather {
+    fn details(&self) -> WeatherDetails {
+        return WeatherDetails {
+            air_temp: Some(self.temperature),
+            dew_point: self.dew_point,
+            pressure: self.pressure,
+            cloud_cover: Some(self.cloud_cover),
+            wind_bearing: self.wind_bearing,
+            uv_index: self.uv_index,
+            visibility: self.visibility,
+            ozone: self.ozone,
+            summary: self.summary.clone(),
+        };
+    }
+}
+
+impl TryFrom<DarkSkyWeather> for Weather {
+    type Error = Error;
+
+    fn try_from(weather: DarkSkyWeather) -> Result<Self, Self::Error> {
+        return Weather::try_from(&weather);
+    }
+}
+
+impl TryFrom<&DarkSkyWeather> for Weather {
+    type Error = Error;
+
+    fn try_from(weather: &DarkSkyWeather) -> Result<Self, Self::Error> {
+        let datetime = i64::try_from(weather.time).ok()
+            .and_then(|time| DateTime::from_timestamp(time, 0))
+            .ok_or_else(|| Error::parse(format!("Invalid time {}", weather.time)))?
+            .naive_utc();
+        return Ok(Weather::new(
+            datetime.year() as u16,
+            datetime.ordinal() as u16,
+            datetime.hour() as u8,
+            Icon::from_str(&weather.icon),
+            weather.precip_intensity,
+            weather.precip_probability,
+            weather.apparent_temperature,
+            weather.wind_speed,
+            weather.wind_gust,
+            weather.humidity,
+            weather.precip_type.clone(),
+        ).with_details(weather.details()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{fixture_json, dark_sky_time_machine};
+
+    /// Test that the DarkSky* models field names and types match the json
+    #[test]
+    fn test_dark_sky_parsing() {
+        let weather: DarkSkyReading = serde_json::from_value(fixture_json("dark_sky_weather.json")).unwrap();
+
+        assert_eq!(weather.currently.time, 1574395263);
+        assert_eq!(weather.currently.icon, "partly-cloudy-night");
+        assert_eq!(weather.currently.precip_intensity, 0.);
+        assert_eq!(weather.currently.precip_probability, 0.);
+        assert_eq!(weather.currently.temperature, 6.56);
+        assert_eq!(weather.currently.apparent_temperature, 3.69);
+        assert_eq!(weather.currently.cloud_cover, 0.66);
+        assert_eq!(weather.currently.wind_speed, 4.18);
+        assert_eq!(weather.currently.wind_gust, 8.6);
+        assert_eq!(weather.currently.humidity, 0.89);
+        assert!(weather.currently.precip_type.is_none());
+        assert_eq!(weather.currently.summary.as_deref(), Some("Mostly Cloudy"));
+
+        assert_eq!(weather.hourly.data.len(), 49);
+
+
+        assert_eq!(weather.hourly.data[25].time, 1574485200);
+        assert_eq!(weather.hourly.data[25].icon, "rain");
+        assert_eq!(weather.hourly.data[25].precip_intensity, 0.7018);
+        assert_eq!(weather.hourly.data[25].precip_probability, 0.58);
+        assert_eq!(weather.hourly.data[25].temperature, 8.44);
+        assert_eq!(weather.hourly.data[25].apparent_temperature, 4.91);
+        assert_eq!(weather.hourly.data[25].cloud_cover, 1.);
+        assert_eq!(weather.hourly.data[25].wind_speed, 7.05);
+        assert_eq!(weather.hourly.data[25].wind_gust, 13.01);
+        assert_eq!(weather.hourly.data[25].humidity, 0.85);
+        assert_eq!(weather.hourly.data[25].precip_type.as_ref().unwrap(), "rain");
+    }
+
+    #[test]
+    fn test_get_weather() {
+        let (current, future) = serde_json::from_value::<DarkSkyReading>(fixture_json("dark_sky_weather.json")).unwrap().get_weather().unwrap();
+
+        assert_eq!(current.timestamp, 1574395200);
+        assert_eq!(future.len(), 48);
+        assert_eq!(future[0].timestamp, 1574395200 + 3600, "predictions start the hour after the reading");
+    }
+
+    /// Time machine responses start at midnight rather than the requested hour
+    #[test]
+    fn test_get_weather_from_midnight() {
+        // Current hour is 04:00
+        let json = dark_sky_time_machine(1574395263);
+
+        let (current, future) = serde_json::from_value::<DarkSkyReading>(json).unwrap().get_weather().unwrap();
+
+        assert_eq!(current.timestamp, 1574395200);
+        assert_eq!(future.len(), 19, "hours up to and including the current hour are not predictions");
+        assert_eq!(future[0].timestamp, 1574395200 + 3600);
+        assert_eq!(future[18].timestamp, 1574395200 + 19 * 3600);
+    }
+
+    #[test]
+    fn test_invalid_time() {
+        let mut json = fixture_json("dark_sky_weather.json");
+        json["hourly"]["data"][25]["time"] = u64::MAX.into();
+
+        match serde_json::from_value::<DarkSkyReading>(json).unwrap().get_weather() {
+            Err(Error::Parse { message, .. }) => assert_eq!(message, format!("Invalid time {}", u64::MAX)),
+            result => panic!("Expected parse error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn test_into_weather() {
+        let weather_reading: DarkSkyReading = serde_json::from_value(fixture_json("dark_sky_weather.json")).unwrap();
+
+        let borrowed_weather = Weather::try_from(&weather_reading.currently).unwrap();
+        let current_weather = Weather::try_from(weather_reading.currently).unwrap();
+        let rainy_weather = Weather::try_from(&weather_reading.hourly.data[25]).unwrap();
+
+        assert_eq!((current_weather.year, current_weather.day, current_weather.hour), (2019, 326, 4));
+        assert_eq!(current_weather.timestamp, 1574395200, "time is truncated to the hour");
+        assert_eq!(borrowed_weather.timestamp, current_weather.timestamp, "owned and borrowed conversions match");
+        assert_eq!(rainy_weather.timestamp, 1574485200);
+
+        assert_eq!(rainy_weather.details.air_temp, Some(8.44));
+        assert_eq!(rainy_weather.details.cloud_cover, Some(1.));
+        assert_eq!(rainy_weather.details.pressure, Some(990.3));
+        assert_eq!(rainy_weather.details.wind_bearing, Some(84.));
+        assert_eq!(rainy_weather.details.summary.as_deref(), Some("Possible Light Rain"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Importers that convert files from weather providers into readings and predictions
+
+use crate::models::{ReadingWithPredictions, Coordinates};
+use crate::Error;
+use serde_json::Value;
+
+pub mod dark_sky;
+pub mod open_meteo;
+
+pub use dark_sky::DarkSkyImporter;
+pub use open_meteo::OpenMeteoImporter;
+
+/// Contents of a single file
+#[derive(Debug)]
+pub struct ImportedWeather {
+    /// Where the data is for, if the file has it
+    pub coordinates: Option<Coordinates>,
+    /// Readings, each with the predictions made at that time
+    pub readings: Vec<ReadingWithPredictions>,
+}
+
+/// Converts json from a weather provider into readings and predictions
+///
+/// To add a provider implement this and add it to `importers()`
+pub trait Importer: Send + Sync {
+    /// Used to select this importer with `--format`
+    fn name(&self) -> &'static str;
+
+    /// True if `json` looks like it came from this provider
+    fn detect(&self, json: &Value) -> bool;
+
+    /// Convert `json` (the contents of a file) into readings and their predictions
+    ///
+    /// Json is parsed from text rather than `Value` so errors include the line
+    ///
+    /// # Errors
+    /// Json does not match the providers format (`Error::Parse`)
+    ///
+    fn import(&self, json: &str) -> Result<ImportedWeather, Error>;
+}
+
+/// All registered importers, in the order they are tried when detecting the format
+pub fn importers() -> Vec<Box<dyn Importer>> {
+    return vec![
+        Box::new(DarkSkyImporter),
+        Box::new(OpenMeteoImporter),
+    ];
+}
+
+/// Names of all registered importers
+pub fn importer_names() -> Vec<&'static str> {
+    return importers().iter().map(|importer| importer.name()).collect();
+}
+
+/// Find importer by name
+///
+/// # Errors
+/// No importer with that name
+///
+pub fn importer_named(name: &str) -> Result<Box<dyn Importer>, Error> {
+    return importers().into_iter()
+        .find(|importer| importer.name() == name)
+        .ok_or_else(|| Error::from(format!("Unknown format '{}', expected one of: {}", name, importer_names().join(", "))));
+}
+
+/// Find the first importer that recognises `json`
+///
+/// # Errors
+/// No importer recognises the json
+///
+pub fn detect_importer(json: &Value) -> Result<Box<dyn Importer>, Error> {
+    return importers().into_iter()
+        .find(|importer| importer.detect(json))
+        .ok_or_else(|| Error::parse(format!("Unrecognised format, expected one of: {}", importer_names().join(", "))));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{fixture_json, fixture_path};
+
+    fn read_fixture_text(name: &str) -> String {
+        return std::fs::read_to_string(fixture_path(name)).unwrap();
+    }
+
+    #[test]
+    fn test_detect_importer() {
+        assert_eq!(detect_importer(&fixture_json("dark_sky_weather.json")).unwrap().name(), "dark-sky");
+        assert_eq!(detect_importer(&fixture_json("open_meteo_forecast.json")).unwrap().name(), "open-meteo");
+        assert_eq!(detect_importer(&fixture_json("open_meteo_archive.json")).unwrap().name(), "open-meteo");
+        assert!(detect_importer(&serde_json::json!({"hourly": []})).is_err());
+    }
+
+    #[test]
+    fn test_importer_named() {
+        assert_eq!(importer_named("open-meteo").unwrap().name(), "open-meteo");
+        assert!(importer_named("dark_sky").is_err());
+    }
+
+    #[test]
+    fn test_dark_sky_import() {
+        let importer = importer_named("dark-sky").unwrap();
+        let imported = importer.import(&read_fixture_text("dark_sky_weather.json")).unwrap();
+
+        let coordinates = imported.coordinates.unwrap();
+        assert_eq!((coordinates.latitude, coordinates.longitude), (51.4, -2.6));
+        assert_eq!(coordinates.timezone.as_deref(), Some("Europe/London"));
+        assert_eq!(imported.readings.len(), 1);
+        let (current, future) = &imported.readings[0];
+        assert_eq!(future.len(), 48);
+        assert_eq!(current.timestamp, 1574395200, "reading is for the start of the hour");
+        assert!(future.iter().enumerate().all(|(i, weather)| weather.timestamp == current.timestamp + (i as i64 + 1) * 3600), "predictions are for each following hour");
+    }
+
+    #[test]
+    fn test_import_error_has_line() {
+        let json = read_fixture_text("dark_sky_weather.json").replacen("\"temperature\"", "\"temperature\": \"warm\", \"unused\"", 1);
+        let line = json.lines().position(|line| line.contains("warm")).unwrap() + 1;
+
+        match importer_named("dark-sky").unwrap().import(&json) {
+            Err(Error::Parse { line: Some(err_line), .. }) => assert_eq!(err_line, line),
+            result => panic!("Expected parse error with line, got {:?}", result.map(|imported| imported.readings.len()))
+        }
+    }
+}
@@ -0,0 +1,442 @@
+//! Open-Meteo forecast and archive files
+
+use serde::{Serialize, Deserialize};
+use crate::models::{Weather, Icon, Coordinates, WeatherDetails};
+use crate::Error;
+use crate::templates::{Importer, ImportedWeather};
+use serde_json::Value;
+use chrono::{NaiveDateTime, Datelike, Timelike, Duration, TimeZone, LocalResult};
+use chrono_tz::Tz;
+use std::ops::Sub;
+use log::debug;
+use crate::analysis::MAX_HOUR_DIFF;
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// Imports Open-Meteo forecast and archive responses, see `OpenMeteoReading`
+pub struct OpenMeteoImporter;
+
+impl Importer for OpenMeteoImporter {
+    fn name(&self) -> &'static str {
+        return "open-meteo";
+    }
+
+    fn detect(&self, json: &Value) -> bool {
+        return json.get("hourly_units").is_some() && json["hourly"].get("time").is_some();
+    }
+
+    fn import(&self, json: &str) -> Result<ImportedWeather, Error> {
+        let reading = serde_json::from_str::<OpenMeteoReading>(json)?;
+        return Ok(ImportedWeather {
+            coordinates: reading.coordinates(),
+            readings: reading.get_weather()?,
+        });
+    }
+}
+
+/// Open-Meteo forecast or archive (historical) response
+///
+/// Requires `hourly=temperature_2m,apparent_temperature,precipitation,wind_speed_10m,wind_gusts_10m,relative_humidity_2m,weather_code`
+/// Forecasts should also request `precipitation_probability` and `current=temperature_2m`
+/// `dew_point_2m`, `pressure_msl`, `cloud_cover`, `wind_direction_10m`, `uv_index` and `visibility` are optional
+/// Time must be `iso8601` (the default), wind speed can be `kmh` (the default) or `ms`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenMeteoReading {
+    latitude: f64,
+    longitude: f64,
+    /// IANA timezone name, `GMT` unless `timezone` was requested
+    timezone: Option<String>,
+    /// Offset of times from UTC at the time of the request, only used if `timezone` is missing
+    #[serde(default)]
+    utc_offset_seconds: i64,
+    /// Only present in forecasts if requested, used as the time the forecast was made
+    current: Option<OpenMeteoCurrent>,
+    hourly_units: OpenMeteoUnits,
+    hourly: OpenMeteoHourly,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenMeteoCurrent {
+    /// Local time in `TIME_FORMAT`
+    time: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenMeteoUnits {
+    /// `km/h`, `m/s`, `mp/h` or `kn`
+    wind_speed_10m: String,
+}
+
+/// Each field is a list with one entry per hour in `time`
+/// Open-Meteo uses `null` for values it doesn't have
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenMeteoHourly {
+    /// Local time in `TIME_FORMAT`
+    time: Vec<String>,
+    /// Air temperature in celsius
+    temperature_2m: Vec<Option<f64>>,
+    /// 'Feels like' temperature in celsius
+    apparent_temperature: Vec<Option<f64>>,
+    /// Between 0 and 100, only available in forecasts
+    precipitation_probability: Option<Vec<Option<f64>>>,
+    /// Total precipitation in millimeters for the preceding hour
+    precipitation: Vec<Option<f64>>,
+    /// Average wind speed at 10m (in `hourly_units.wind_speed_10m`)
+    wind_speed_10m: Vec<Option<f64>>,
+    /// Wind gust speed at 10m (in `hourly_units.wind_speed_10m`)
+    wind_gusts_10m: Vec<Option<f64>>,
+    /// Between 0 and 100
+    relative_humidity_2m: Vec<Option<f64>>,
+    /// WMO weather interpretation code
+    weather_code: Vec<Option<u8>>,
+    /// Dew point in celsius
+    dew_point_2m: Option<Vec<Option<f64>>>,
+    /// Sea level air pressure in hectopascals
+    pressure_msl: Option<Vec<Option<f64>>>,
+    /// Between 0 and 100
+    cloud_cover: Option<Vec<Option<f64>>>,
+    /// Direction the wind is coming from in degrees
+    wind_direction_10m: Option<Vec<Option<f64>>>,
+    /// UV index, only available in forecasts
+    uv_index: Option<Vec<Option<f64>>>,
+    /// Visibility in meters, only available in forecasts
+    visibility: Option<Vec<Option<f64>>>,
+}
+
+impl OpenMeteoReading {
+    /// True if this is a forecast (rather than an archive of observations)
+    pub fn is_forecast(&self) -> bool {
+        return self.current.is_some() || self.hourly.precipitation_probability.is_some();
+    }
+
+    /// Position and timezone of the forecast
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        return Some(Coordinates {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            timezone: self.timezone.clone(),
+        });
+    }
+
+    /// Convert into readings and their predictions
+    ///
+    /// For forecasts this will be a single reading for the `current` hour (or the first hour if `current` is missing)
+    /// with the following `MAX_HOUR_DIFF` hours as predictions
+    /// For archives every hour is a reading without predictions
+    /// Hours missing any required value are left out (see `weather_at`)
+    ///
+    /// # Errors
+    /// Invalid time
+    /// Unknown wind speed unit
+    /// Forecast has no data for the current hour, or it's missing a required value
+    ///
+    pub fn get_weather(self) -> Result<Vec<(Weather, Vec<Weather>)>, Error> {
+        let is_forecast = self.is_forecast();
+        let current = match &self.current {
+            Some(current) => Some(self.to_utc(&current.time, None)?.with_minute(0).expect("Invalid minute")),
+            None => None
+        };
+        let mut times: Vec<NaiveDateTime> = Vec::with_capacity(self.hourly.time.len());
+        for time in &self.hourly.time {
+            times.push(self.to_utc(time, times.last().cloned())?);
+        }
+        let weathers = self.weathers(&times)?;
+
+        if !is_forecast {
+            return Ok(weathers.into_iter().flatten().map(|weather| (weather, vec![])).collect());
+        }
+
+        let current_index = match current {
+            Some(current) => times.iter().position(|time| *time == current)
+                .ok_or_else(|| Error::parse(format!("No hourly data for current time {}", current)))?,
+            None => 0
+        };
+
+        let mut future = weathers.into_iter().skip(current_index);
+        let current_weather = future.next().flatten()
+            .ok_or_else(|| Error::parse("Missing values for the current hour"))?;
+        // Lead time is found from the timestamps when saved, so hours with missing values can be left out without shifting the rest
+        let last_timestamp = current_weather.timestamp + MAX_HOUR_DIFF as i64 * 3600;
+        let future_weathers = future
+            .flatten()
+            .take_while(|weather| weather.timestamp <= last_timestamp)
+            .collect();
+
+        return Ok(vec![(current_weather, future_weathers)]);
+    }
+
+    /// Convert a local `time` to UTC using `timezone`, so each time gets the offset in effect at that time
+    ///
+    /// When clocks go back an hour happens twice, the second is used if the first is not after `previous`
+    ///
+    /// # Errors
+    /// Invalid time
+    /// Unknown timezone
+    /// Time doesn't exist in the timezone (skipped when clocks go forward)
+    ///
+    fn to_utc(&self, time: &str, previous: Option<NaiveDateTime>) -> Result<NaiveDateTime, Error> {
+        let local = NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+            .map_err(|err| Error::parse(format!("Invalid time '{}': {}", time, err)))?;
+        let timezone = match &self.timezone {
+            Some(name) => name.parse::<Tz>().map_err(|_| Error::parse(format!("Unknown timezone '{}'", name)))?,
+            None => return Ok(local.sub(Duration::seconds(self.utc_offset_seconds)))
+        };
+        return match timezone.from_local_datetime(&local) {
+            LocalResult::Single(time) => Ok(time.naive_utc()),
+            LocalResult::Ambiguous(first, second) => {
+                let repeated = previous.is_some_and(|previous| first.naive_utc() <= previous);
+                Ok(if repeated { second } else { first }.naive_utc())
+            }
+            LocalResult::None => Err(Error::parse(format!("Time '{}' doesn't exist in {}", time, timezone)))
+        };
+    }
+
+    /// Multiplier to convert wind speed to meters per second
+    fn wind_speed_multiplier(&self) -> Result<f64, Error> {
+        return match self.hourly_units.wind_speed_10m.as_str() {
+            "m/s" => Ok(1.),
+            "km/h" => Ok(1. / 3.6),
+            "mp/h" => Ok(0.44704),
+            "kn" => Ok(0.514444),
+            unit => Err(Error::parse(format!("Unknown wind speed unit: {}", unit)))
+        };
+    }
+
+    /// Every hour in `times` as weather, in the order they appear in the file
+    ///
+    /// Hours missing a required value are `None` so the rest keep their position
+    fn weathers(&self, times: &[NaiveDateTime]) -> Result<Vec<Option<Weather>>, Error> {
+        let wind_multiplier = self.wind_speed_multiplier()?;
+        let weathers = times.iter()
+            .enumerate()
+            .map(|(i, datetime)| {
+                let weather = self.weather_at(i, *datetime, wind_multiplier);
+                if weather.is_none() {
+                    debug!("Skipping {}, missing required values", datetime);
+                }
+                weather
+            })
+            .collect();
+
+        return Ok(weathers);
+    }
+
+    /// Weather for hour `i` of `hourly`
+    ///
+    /// # Returns
+    /// `None` if the 'feels like' temperature, precipitation, wind speed, wind gust or humidity is missing,
+    /// or if this is a forecast and the precipitation probability is missing
+    ///
+    fn weather_at(&self, i: usize, datetime: NaiveDateTime, wind_multiplier: f64) -> Option<Weather> {
+        let hourly = &self.hourly;
+        let value = |list: &Vec<Option<f64>>| list.get(i).cloned().flatten();
+        let optional = |list: &Option<Vec<Option<f64>>>| list.as_ref().and_then(&value);
+
+        let temp = value(&hourly.apparent_temperature)?;
+        let precip_intensity = value(&hourly.precipitation)?;
+        let wind_speed = value(&hourly.wind_speed_10m)?;
+        let wind_gust = value(&hourly.wind_gusts_10m)?;
+        let humidity = value(&hourly.relative_humidity_2m)?;
+        // Archives don't have a probability, they're observations
+        let probability = match &hourly.precipitation_probability {
+            Some(list) => value(list)?,
+            None => 0.
+        };
+        let code = hourly.weather_code.get(i).cloned().flatten();
+        let details = WeatherDetails {
+            air_temp: value(&hourly.temperature_2m),
+            dew_point: optional(&hourly.dew_point_2m),
+            pressure: optional(&hourly.pressure_msl),
+            cloud_cover: optional(&hourly.cloud_cover).map(|cover| cover / 100.),
+            wind_bearing: optional(&hourly.wind_direction_10m),
+            uv_index: optional(&hourly.uv_index),
+            visibility: optional(&hourly.visibility).map(|meters| meters / 1000.),
+            ozone: None,
+            summary: None,
+        };
+
+        return Some(Weather::new(
+            datetime.year() as u16,
+            datetime.ordinal() as u16,
+            datetime.hour() as u8,
+            code.map(icon_for_code).unwrap_or(Icon::Unknown),
+            precip_intensity,
+            probability / 100.,
+            temp,
+            wind_speed * wind_multiplier,
+            wind_gust * wind_multiplier,
+            humidity / 100.,
+            code.and_then(precip_type_for_code).map(String::from),
+        ).with_details(details));
+    }
+}
+
+/// Convert WMO weather interpretation code to icon
+///
+/// See https://open-meteo.com/en/docs (WMO Weather interpretation codes)
+fn icon_for_code(code: u8) -> Icon {
+    return match code {
+        0 | 1 => Icon::Clear,
+        2 => Icon::PartlyCloudy,
+        3 => Icon::Cloudy,
+        45 | 48 => Icon::Fog,
+        56 | 57 | 66 | 67 => Icon::Sleet,
+        51..=55 | 61..=65 | 80..=82 => Icon::Rain,
+        71..=77 | 85 | 86 => Icon::Snow,
+        95 => Icon::Thunderstorm,
+        96 | 99 => Icon::Hail,
+        _ => Icon::Unknown
+    };
+}
+
+/// Convert WMO weather interpretation code to precipitation type (`rain`, `snow` or `sleet`)
+fn precip_type_for_code(code: u8) -> Option<&'static str> {
+    return match code {
+        56 | 57 | 66 | 67 => Some("sleet"),
+        51..=55 | 61..=65 | 80..=82 | 95..=99 => Some("rain"),
+        71..=77 | 85 | 86 => Some("snow"),
+        _ => None
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::fixture_json;
+
+    fn read_fixture(name: &str) -> OpenMeteoReading {
+        return serde_json::from_value(fixture_json(name)).unwrap();
+    }
+
+    /// Test that the OpenMeteo* models field names and types match the json
+    #[test]
+    fn test_open_meteo_parsing() {
+        let reading = read_fixture("open_meteo_forecast.json");
+
+        assert!(reading.is_forecast());
+        assert_eq!(reading.utc_offset_seconds, 0);
+        assert_eq!((reading.latitude, reading.longitude), (51.5, -0.12));
+        assert_eq!(reading.timezone.as_deref(), Some("GMT"));
+        assert_eq!(reading.current.as_ref().unwrap().time, "2024-03-10T14:15");
+        assert_eq!(reading.hourly_units.wind_speed_10m, "km/h");
+        assert_eq!(reading.hourly.time.len(), 72);
+        assert_eq!(reading.hourly.time[4], "2024-03-10T04:00");
+        assert_eq!(reading.hourly.weather_code[4], Some(61));
+        assert_eq!(reading.hourly.precipitation_probability.as_ref().unwrap()[4], Some(60.));
+        assert_eq!(reading.hourly.precipitation[5], Some(0.4));
+        assert_eq!(reading.hourly.temperature_2m.len(), 72);
+    }
+
+    #[test]
+    fn test_forecast_into_weather() {
+        let readings = read_fixture("open_meteo_forecast.json").get_weather().unwrap();
+
+        assert_eq!(readings.len(), 1, "forecast is a single reading");
+        let (current, future) = &readings[0];
+
+        assert_eq!(current.timestamp, 1710079200);
+        assert_eq!((current.year, current.day, current.hour), (2024, 70, 14));
+        assert_eq!(future.len(), 48);
+        assert_eq!((future[0].year, future[0].day, future[0].hour), (2024, 70, 15));
+        assert_eq!(future[47].timestamp, current.timestamp + 48 * 3600);
+
+        let rainy = &future[1];
+        assert_eq!(rainy.hour, 16);
+        assert_eq!(rainy.icon, Icon::Rain);
+        assert_eq!(rainy.precip_intensity, 0.4);
+        assert_eq!(rainy.precip_probability, 0.6);
+        assert_eq!(rainy.precip_type.as_deref(), Some("rain"));
+        assert!((rainy.wind_speed - 13.6 / 3.6).abs() < 1e-9, "km/h converted to m/s");
+        assert!((rainy.humidity - 0.86).abs() < 1e-9, "humidity is a ratio");
+        assert_eq!(rainy.details.air_temp, Some(2.5));
+        assert_eq!(rainy.details.pressure, Some(1008.));
+        assert_eq!(rainy.details.cloud_cover, Some(1.), "cloud cover is a ratio");
+        assert_eq!(rainy.details.wind_direction(), Some("W"));
+        assert_eq!(rainy.details.uv_index, None, "missing fields are none");
+    }
+
+    #[test]
+    fn test_missing_values_are_skipped() {
+        let mut json = fixture_json("open_meteo_forecast.json");
+        // Current hour is index 14, so these are 2 and 6 hours ahead
+        json["hourly"]["apparent_temperature"][16] = Value::Null;
+        json["hourly"]["wind_speed_10m"][20] = Value::Null;
+        let reading: OpenMeteoReading = serde_json::from_value(json).unwrap();
+
+        let readings = reading.get_weather().unwrap();
+        let (current, future) = &readings[0];
+
+        assert_eq!(future.len(), 46, "hours with missing values are left out, not defaulted");
+        assert_eq!(future[1].timestamp, current.timestamp + 3 * 3600, "later hours keep their time");
+        assert_eq!(future[4].timestamp, current.timestamp + 7 * 3600);
+        assert_eq!(future[45].timestamp, current.timestamp + 48 * 3600);
+    }
+
+    #[test]
+    fn test_missing_current_values() {
+        let mut json = fixture_json("open_meteo_forecast.json");
+        json["hourly"]["relative_humidity_2m"][14] = Value::Null;
+        let reading: OpenMeteoReading = serde_json::from_value(json).unwrap();
+
+        assert!(reading.get_weather().is_err());
+    }
+
+    #[test]
+    fn test_archive_into_weather() {
+        let reading = read_fixture("open_meteo_archive.json");
+        assert!(!reading.is_forecast());
+
+        let readings = reading.get_weather().unwrap();
+
+        assert_eq!(readings.len(), 24, "every hour of an archive is a reading");
+        assert!(readings.iter().all(|(_, future)| future.is_empty()));
+
+        let (first, _) = &readings[0];
+        assert_eq!((first.year, first.day, first.hour), (2023, 365, 23), "local time is converted to UTC");
+        assert_eq!(first.icon, Icon::Fog);
+        assert_eq!(first.wind_speed, 3.5, "m/s is not converted");
+        assert_eq!(first.precip_probability, 0.);
+
+        let (snow, _) = &readings[22];
+        assert_eq!(snow.icon, Icon::Snow);
+        assert_eq!(snow.precip_type.as_deref(), Some("snow"));
+    }
+
+    /// Times are local to `timezone`, the offset changes part way through when the clocks go forward
+    #[test]
+    fn test_forecast_across_dst_change() {
+        let readings = read_fixture("open_meteo_forecast_dst.json").get_weather().unwrap();
+        let (current, future) = &readings[0];
+
+        assert_eq!((current.year, current.day, current.hour), (2024, 90, 14));
+        assert_eq!(future.len(), 48);
+        for (i, weather) in future.iter().enumerate() {
+            assert_eq!(weather.timestamp, current.timestamp + (i as i64 + 1) * 3600, "hour {} after the reading", i + 1);
+        }
+    }
+
+    #[test]
+    fn test_repeated_hour() {
+        let mut json = fixture_json("open_meteo_archive.json");
+        json["timezone"] = "Europe/London".into();
+        let reading: OpenMeteoReading = serde_json::from_value(json).unwrap();
+        let utc = |hour: i64| NaiveDateTime::parse_from_str("2024-10-27T00:00", TIME_FORMAT).unwrap() + Duration::hours(hour);
+
+        // Midnight BST is 23:00 UTC
+        let first = reading.to_utc("2024-10-27T01:00", Some(utc(-1))).unwrap();
+        let second = reading.to_utc("2024-10-27T01:00", Some(first)).unwrap();
+
+        assert_eq!((first, second), (utc(0), utc(1)), "1am BST then 1am GMT");
+        assert!(reading.to_utc("2024-03-31T01:00", None).is_err(), "skipped when clocks go forward");
+    }
+
+    #[test]
+    fn test_weather_codes() {
+        assert_eq!(icon_for_code(2), Icon::PartlyCloudy);
+        assert_eq!(icon_for_code(66), Icon::Sleet);
+        assert_eq!(icon_for_code(99), Icon::Hail);
+        assert_eq!(icon_for_code(200), Icon::Unknown);
+        assert_eq!(precip_type_for_code(3), None);
+        assert_eq!(precip_type_for_code(86), Some("snow"));
+    }
+}
@@ -0,0 +1,78 @@
+//! Temporary directories and fixtures shared by the tests of each module
+
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+
+/// Start of the current hour in the DarkSky fixture, the hourly data starts at this time
+pub const DARK_SKY_HOUR: i64 = 1574395200;
+
+/// Directory in the system temp directory, deleted with everything in it when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Empty directory, `name` must be unique to the test as tests run in parallel
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("weather_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        return TempDir(path);
+    }
+
+    pub fn path(&self) -> &Path {
+        return &self.0;
+    }
+
+    /// Write `contents` to `name` (relative to this directory), creating any parent directories
+    pub fn write(&self, name: &str, contents: &[u8]) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        return path;
+    }
+
+    /// Copy the fixture `name` into this directory
+    pub fn copy_fixture(&self, name: &str) -> PathBuf {
+        let path = self.0.join(name);
+        std::fs::copy(fixture_path(name), &path).unwrap();
+        return path;
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Path of `name` in `resources/test`
+pub fn fixture_path(name: &str) -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/test").join(name);
+}
+
+pub fn fixture_json(name: &str) -> Value {
+    return serde_json::from_str(&std::fs::read_to_string(fixture_path(name)).unwrap()).unwrap();
+}
+
+/// DarkSky fixture with every time moved so the current hour starts at `hour` (unix timestamp)
+pub fn dark_sky_at(hour: i64) -> Value {
+    let mut json = fixture_json("dark_sky_weather.json");
+    let offset = hour - DARK_SKY_HOUR;
+    json["currently"]["time"] = (json["currently"]["time"].as_i64().unwrap() + offset).into();
+    for data in json["hourly"]["data"].as_array_mut().unwrap() {
+        data["time"] = (data["time"].as_i64().unwrap() + offset).into();
+    }
+    return json;
+}
+
+/// DarkSky time machine response for `time` (unix timestamp), the hourly data is the 24 hours of that (UTC) day from midnight
+pub fn dark_sky_time_machine(time: i64) -> Value {
+    let mut json = fixture_json("dark_sky_weather.json");
+    let midnight = time - time.rem_euclid(24 * 3600);
+    json["currently"]["time"] = time.into();
+    let data = json["hourly"]["data"].as_array_mut().unwrap();
+    data.truncate(24);
+    for (i, data) in data.iter_mut().enumerate() {
+        data["time"] = (midnight + i as i64 * 3600).into();
+    }
+    return json;
+}
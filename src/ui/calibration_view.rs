@@ -0,0 +1,204 @@
+use crate::ui::screen::{Screen, Action, Task, TaskResult, task_output};
+use crate::ui::form::{Form, FormEvent, Focus, draw_with_results, RESULTS_PLACEHOLDER};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::text_input::{Prompt, PromptEvent};
+use crate::Error;
+use weather::app::WeatherApp;
+use weather::models::SimpleDate;
+use crate::ui::utils::{day_range_fields, rain_event_field, parse_rain_event, rain_event_text, form_day_range, range_text, viewing_line, save_csv};
+use weather::analysis::calibration::{LeadTimeCalibration, CalibrationCsv, ReliabilityBin};
+use weather::analysis::{RainEvent, MAX_HOUR_DIFF};
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
+
+const HEADER_COLOR: Color = Color::Cyan;
+const BAR_WIDTH: usize = 50;
+
+/// Options and output of a calculation
+struct CalibrationResults {
+    start: SimpleDate,
+    end: SimpleDate,
+    event: RainEvent,
+    calibrations: Vec<LeadTimeCalibration>,
+}
+
+pub struct CalibrationView {
+    form: Form,
+    focus: Focus,
+    results: Option<CalibrationResults>,
+    hour_diff: u8,
+    pane: ScrollPane,
+    /// Asking for a file to export to
+    prompt: Option<Prompt>,
+}
+
+impl CalibrationView {
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<CalibrationView, Error> {
+        let mut fields = day_range_fields(app)?;
+        fields.push(rain_event_field());
+
+        return Ok(CalibrationView {
+            form: Form::new(fields),
+            focus: Focus::Form,
+            results: None,
+            hour_diff: 1,
+            pane: ScrollPane::default(),
+            prompt: None,
+        });
+    }
+}
+
+impl CalibrationView {
+    fn bin_line(&self, bin: &ReliabilityBin) -> Line<'static> {
+        let mut spans = vec![Span::styled(format!("{: >3.0}-{: <3.0}%  ", bin.lower * 100., bin.upper * 100.), Style::new().fg(HEADER_COLOR))];
+
+        if bin.count == 0 {
+            spans.push(Span::raw(format!("{: <8}{: <10}{: <10}", 0, "-", "-")));
+            return Line::from(spans);
+        }
+
+        let error = (bin.observed_frequency - bin.mean_forecast).abs();
+        let color = if error <= 0.1 {
+            Color::Green
+        } else if error <= 0.2 {
+            Color::Yellow
+        } else {
+            Color::Red
+        };
+
+        let observed = (bin.observed_frequency * BAR_WIDTH as f64).round() as usize;
+        let forecast = ((bin.mean_forecast * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH - 1);
+        let bar: String = (0..BAR_WIDTH)
+            .map(|i| if i == forecast { '|' } else if i < observed { '█' } else { ' ' })
+            .collect();
+
+        spans.push(Span::raw(format!("{: <8}{: <10.1}", bin.count, bin.mean_forecast * 100.)));
+        spans.push(Span::styled(format!("{: <10.1}{}", bin.observed_frequency * 100., bar), Style::new().fg(color)));
+
+        return Line::from(spans);
+    }
+
+    fn calibration_lines(&self, calibration: &LeadTimeCalibration) -> Vec<Line<'static>> {
+        let mut lines = vec![
+            Line::raw(format!(
+                "Count {}  Brier {:.4}  Reliability {:.4}  Resolution {:.4}  Uncertainty {:.4}",
+                calibration.count,
+                calibration.brier,
+                calibration.reliability,
+                calibration.resolution,
+                calibration.uncertainty
+            )),
+            Line::default(),
+            Line::styled(format!("{: <11}{: <8}{: <10}{: <10}Observed (█) vs predicted (|)", "Predicted", "Count", "Mean %", "Obs %"), Style::new().fg(HEADER_COLOR)),
+        ];
+        lines.extend(calibration.bins.iter().map(|bin| self.bin_line(bin)));
+
+        return lines;
+    }
+
+    fn result_lines(&self) -> Vec<Line<'static>> {
+        let results = match &self.results {
+            Some(results) => results,
+            None => return vec![Line::styled(RESULTS_PLACEHOLDER, Style::new().fg(Color::Gray))]
+        };
+
+        let mut lines = vec![
+            viewing_line(format!("{}, {} hours before", range_text(results.start, results.end), self.hour_diff)),
+            Line::raw(rain_event_text(results.event)),
+            Line::default(),
+        ];
+
+        match results.calibrations.iter().find(|calibration| calibration.hour_diff == self.hour_diff) {
+            Some(calibration) => lines.extend(self.calibration_lines(calibration)),
+            None => lines.push(Line::styled("No predictions with readings for this lead time", Style::new().fg(Color::Red)))
+        }
+
+        return lines;
+    }
+
+    fn calculate(&self, app: &mut WeatherApp) -> Result<Action, Error> {
+        let (start, end) = form_day_range(app, &self.form)?;
+        let event = parse_rain_event(self.form.text("Rain"))?;
+        return Ok(Action::Run(Task::new("Calculating calibration", move |app, _| {
+            return Ok(CalibrationResults {
+                start,
+                end,
+                event,
+                calibrations: app.get_calibration(start, end, event)?,
+            });
+        })));
+    }
+}
+
+impl Screen for CalibrationView {
+    fn title(&self) -> String {
+        return String::from("Precipitation probability calibration");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return match self.focus {
+            Focus::Form => vec![("Tab", "next field"), ("Rain", "mm/h or 'type'"), ("Enter", "calculate"), ("Esc", "back")],
+            Focus::Results => vec![("←→", "lead time"), ("e", "export CSV"), ("PgUp/PgDn", "scroll"), ("Tab", "options"), ("Esc", "back")],
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let lines = self.result_lines();
+        draw_with_results(frame, area, &self.form, self.focus, &mut self.pane, Text::from(lines));
+
+        if let Some(prompt) = &self.prompt {
+            prompt.draw(frame, area);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        if let Some(prompt) = &mut self.prompt {
+            return match prompt.handle_key(key) {
+                PromptEvent::None => Ok(Action::None),
+                PromptEvent::Cancel => {
+                    self.prompt = None;
+                    Ok(Action::None)
+                }
+                PromptEvent::Submit(path) => {
+                    self.prompt = None;
+                    let calibrations = self.results.as_ref().map(|results| results.calibrations.as_slice()).unwrap_or_default();
+                    save_csv(&path, |out| CalibrationCsv(calibrations).write(out))
+                }
+            };
+        }
+
+        match self.focus {
+            Focus::Form => match self.form.handle_key(key) {
+                FormEvent::Submit => return self.calculate(app),
+                FormEvent::Leave => self.focus = Focus::Results,
+                FormEvent::Cancel => return Ok(Action::Pop),
+                FormEvent::None => {}
+            },
+            Focus::Results => match key.code {
+                KeyCode::Esc => return Ok(Action::Pop),
+                _ if self.form.enter(key) => self.focus = Focus::Form,
+                _ if self.pane.handle_key(key) => {}
+                KeyCode::Left => self.hour_diff = self.hour_diff.saturating_sub(1).max(1),
+                KeyCode::Right => self.hour_diff = (self.hour_diff + 1).min(MAX_HOUR_DIFF),
+                KeyCode::Char('e') if self.results.is_some() => self.prompt = Some(Prompt::new("Enter file to save CSV to")),
+                _ => {}
+            }
+        }
+
+        return Ok(Action::None);
+    }
+
+    fn task_finished(&mut self, result: TaskResult) -> Result<Action, Error> {
+        self.results = Some(task_output(result)?);
+        self.focus = Focus::Results;
+
+        return Ok(Action::None);
+    }
+}
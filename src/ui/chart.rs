@@ -0,0 +1,243 @@
+use ratatui::style::{Color, Style, Modifier};
+use ratatui::text::{Line, Span};
+
+const AXIS_COLOR: Color = Color::Cyan;
+/// Blocks for sparklines, from empty to full
+const LEVELS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Shown in sparklines for missing values
+const MISSING: char = '·';
+
+/// A line on a chart, `None` values leave a gap
+pub struct Series {
+    pub name: String,
+    pub color: Color,
+    pub values: Vec<Option<f64>>,
+}
+
+/// Line chart drawn with braille characters, each character is 2 points across and 4 points high
+///
+/// Value `i` is drawn in the middle of the `i`th of equal width slots across the chart,
+/// so a sparkline with `width / count` characters per value lines up underneath
+pub struct LineChart {
+    pub series: Vec<Series>,
+    /// Width of plot area in characters
+    pub width: usize,
+    /// Height of plot area in characters
+    pub height: usize,
+    /// Width of the y axis labels, should match the row titles of other rows on screen
+    pub label_width: usize,
+    /// Labels under the x axis, by index into values
+    pub x_labels: Vec<(usize, String)>,
+}
+
+/// A braille character being built, `dots` is the unicode braille bit pattern
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+struct Cell {
+    dots: u8,
+    color: Option<Color>,
+}
+
+impl LineChart {
+    fn count(&self) -> usize {
+        return self.series.iter().map(|series| series.values.len()).max().unwrap_or(0);
+    }
+
+    /// Lowest and highest value of every series, `None` if there are no values
+    fn range(&self) -> Option<(f64, f64)> {
+        let values = self.series.iter().flat_map(|series| series.values.iter().flatten());
+        let min = values.clone().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.cloned().fold(f64::NEG_INFINITY, f64::max);
+        if min > max {
+            return None;
+        }
+        if min == max {
+            return Some((min - 1., max + 1.));
+        }
+        return Some((min, max));
+    }
+
+    /// Point column of value `index`
+    fn x_point(&self, index: usize) -> usize {
+        return ((2 * index + 1) * self.width * 2) / (2 * self.count());
+    }
+
+    /// Point row of `value`, counted up from the bottom
+    fn y_point(&self, value: f64, (min, max): (f64, f64)) -> usize {
+        let top = (self.height * 4 - 1) as f64;
+        return ((value - min) / (max - min) * top).round().clamp(0., top) as usize;
+    }
+
+    /// Braille characters for the plot area, top row first
+    fn render(&self, range: (f64, f64)) -> Vec<Vec<Cell>> {
+        let mut cells = vec![vec![Cell::default(); self.width]; self.height];
+        let mut plot = |(x, y): (usize, usize), color: Color| {
+            let cell = &mut cells[self.height - 1 - y / 4][x / 2];
+            let dot_row = 3 - y % 4;
+            cell.dots |= match (x % 2, dot_row) {
+                (0, 3) => 0x40,
+                (1, 3) => 0x80,
+                (0, row) => 1 << row,
+                (_, row) => 1 << (row + 3),
+            };
+            cell.color = Some(color);
+        };
+
+        for series in &self.series {
+            let points: Vec<Option<(usize, usize)>> = series.values.iter()
+                .enumerate()
+                .map(|(i, value)| value.map(|value| (self.x_point(i), self.y_point(value, range))))
+                .collect();
+
+            for (i, point) in points.iter().enumerate() {
+                let (x1, y1) = match point {
+                    Some(point) => *point,
+                    None => continue
+                };
+                let (x0, y0) = match i.checked_sub(1).and_then(|prev| points[prev]) {
+                    Some(prev) => prev,
+                    None => (x1, y1)
+                };
+                let steps = x1.abs_diff(x0).max(y1.abs_diff(y0)).max(1);
+                for step in 0..=steps {
+                    let along = step as f64 / steps as f64;
+                    let x = x0 as f64 + (x1 as f64 - x0 as f64) * along;
+                    let y = y0 as f64 + (y1 as f64 - y0 as f64) * along;
+                    plot((x.round() as usize, y.round() as usize), series.color);
+                }
+            }
+        }
+
+        return cells;
+    }
+
+    /// Lines of the chart, its axes and a key of series names
+    pub fn lines(&self, title: &str) -> Vec<Line<'static>> {
+        let axis = Style::new().fg(AXIS_COLOR);
+        let mut lines = vec![Line::styled(title.to_owned(), axis.add_modifier(Modifier::BOLD))];
+
+        let range = match self.range() {
+            Some(range) => range,
+            None => {
+                lines.push(Line::styled("No data", Style::new().fg(Color::Red)));
+                return lines;
+            }
+        };
+
+        for (i, row) in self.render(range).iter().enumerate() {
+            let label = if i == 0 {
+                format!("{: >1$.1} ┤", range.1, self.label_width - 1)
+            } else if i == self.height - 1 {
+                format!("{: >1$.1} ┤", range.0, self.label_width - 1)
+            } else {
+                format!("{: >1$}│", "", self.label_width)
+            };
+
+            let mut spans = vec![Span::styled(label, axis)];
+            spans.extend(row.iter().map(|cell| Span::styled(
+                std::char::from_u32(0x2800 + cell.dots as u32).expect("Braille is valid unicode").to_string(),
+                Style::new().fg(cell.color.unwrap_or(Color::White)),
+            )));
+            lines.push(Line::from(spans));
+        }
+
+        let mut labels = vec![' '; self.width];
+        for (index, label) in &self.x_labels {
+            let start = index * self.width / self.count().max(1);
+            if labels.iter().skip(start.saturating_sub(1)).take(label.len() + 2).all(|chr| *chr == ' ') {
+                label.chars().enumerate()
+                    .filter(|(offset, _)| start + offset < self.width)
+                    .for_each(|(offset, chr)| labels[start + offset] = chr);
+            }
+        }
+        lines.push(Line::styled(format!("{: >2$}└{}", "", "─".repeat(self.width), self.label_width), axis));
+        lines.push(Line::styled(format!("{: >2$} {}", "", labels.into_iter().collect::<String>(), self.label_width), axis));
+
+        let mut key = vec![Span::raw(format!("{: >1$}", "", self.label_width + 1))];
+        for series in &self.series {
+            key.push(Span::styled("━ ", Style::new().fg(series.color)));
+            key.push(Span::raw(format!("{}   ", series.name)));
+        }
+        lines.push(Line::from(key));
+
+        return lines;
+    }
+}
+
+/// Width of the plot area of a chart with `count` values drawn inside a bordered block `area_width` wide
+///
+/// At least one character per value, wider charts are scrolled
+pub fn plot_width(area_width: u16, label_width: usize, count: usize) -> usize {
+    return (area_width as usize).saturating_sub(label_width + 3).max(count);
+}
+
+/// One block character per value scaled from 0 to `max`, repeated `width` times
+pub fn sparkline(values: &[Option<f64>], max: f64, width: usize) -> String {
+    return values.iter()
+        .map(|value| {
+            let chr = match value {
+                Some(value) if max > 0. => LEVELS[((value / max) * 8.).round().clamp(0., 8.) as usize],
+                Some(_) => LEVELS[0],
+                None => MISSING
+            };
+            std::iter::repeat_n(chr, width).collect::<String>()
+        })
+        .collect();
+}
+
+/// A titled sparkline row, lined up with a `LineChart` that has the same `label_width`
+pub fn sparkline_line(title: &str, label_width: usize, values: &[Option<f64>], max: f64, width: usize, color: Color) -> Line<'static> {
+    return Line::from(vec![
+        Span::styled(format!("{: <1$} ", title, label_width), Style::new().fg(AXIS_COLOR)),
+        Span::styled(sparkline(values, max, width), Style::new().fg(color)),
+    ]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let chart = LineChart {
+            series: vec![Series { name: String::new(), color: Color::White, values: vec![Some(0.), Some(1.)] }],
+            width: 2,
+            height: 1,
+            label_width: 8,
+            x_labels: vec![],
+        };
+
+        let cells = chart.render(chart.range().unwrap());
+
+        assert_eq!(cells[0][0].dots, 0x80, "starts at the bottom right of the first character");
+        assert_eq!(cells[0][1].dots, 0x04 | 0x02 | 0x08, "rises up the left column to the top right");
+        assert_eq!(cells[0][1].color, Some(Color::White));
+    }
+
+    #[test]
+    fn test_gaps_and_range() {
+        let chart = LineChart {
+            series: vec![Series { name: String::new(), color: Color::Red, values: vec![Some(5.), None, Some(5.)] }],
+            width: 3,
+            height: 1,
+            label_width: 8,
+            x_labels: vec![],
+        };
+
+        assert_eq!(chart.range(), Some((4., 6.)), "flat lines are centered");
+        let cells = chart.render(chart.range().unwrap());
+        assert_eq!(cells[0][1], Cell::default(), "missing value is a gap");
+        assert_ne!(cells[0][0].dots, 0);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[Some(0.), Some(50.), Some(100.), None], 100., 2), "  ▄▄██··");
+        assert_eq!(sparkline(&[Some(0.)], 0., 1), " ");
+    }
+
+    #[test]
+    fn test_plot_width() {
+        assert_eq!(plot_width(100, 8, 24), 89, "borders, labels and axis are taken from the area");
+        assert_eq!(plot_width(20, 8, 24), 24, "at least one character per value");
+    }
+}
@@ -0,0 +1,208 @@
+use crate::ui::screen::{Screen, Action, Task, TaskResult, task_output};
+use crate::ui::form::{Form, Field, FormEvent, Focus, draw_with_results, RESULTS_PLACEHOLDER};
+use crate::ui::text_input::TextInput;
+use crate::ui::scroll_pane::ScrollPane;
+use crate::Error;
+use weather::app::WeatherApp;
+use weather::models::{Icon, SimpleDate};
+use crate::ui::utils::{background, day_range_fields, form_day_range, range_text, viewing_line};
+use weather::analysis::confusion::ConfusionMatrix;
+use weather::analysis::MAX_HOUR_DIFF;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
+
+const HEADER_COLOR: Color = Color::Cyan;
+
+/// Options and output of a calculation
+struct ConfusionResults {
+    start: SimpleDate,
+    end: SimpleDate,
+    min_hour_diff: u8,
+    max_hour_diff: u8,
+    matrix: ConfusionMatrix,
+}
+
+pub struct ConfusionView {
+    form: Form,
+    focus: Focus,
+    results: Option<ConfusionResults>,
+    pane: ScrollPane,
+    hours_hint: String,
+}
+
+impl ConfusionView {
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<ConfusionView, Error> {
+        let mut fields = day_range_fields(app)?;
+        fields.push(("Hours from", Field::Text(TextInput::new("1"))));
+        fields.push(("Hours to", Field::Text(TextInput::new(&MAX_HOUR_DIFF.to_string()))));
+
+        return Ok(ConfusionView {
+            form: Form::new(fields),
+            focus: Focus::Form,
+            results: None,
+            pane: ScrollPane::default(),
+            hours_hint: format!("1 - {}", MAX_HOUR_DIFF),
+        });
+    }
+}
+
+impl ConfusionView {
+    fn short_name(icon: Icon) -> &'static str {
+        return match icon {
+            Icon::Rain => "rain",
+            Icon::Thunderstorm => "storm",
+            Icon::Fog => "fog",
+            Icon::Snow => "snow",
+            Icon::PartlyCloudy => "p-cldy",
+            Icon::Cloudy => "cloudy",
+            Icon::Hail => "hail",
+            Icon::Sleet => "sleet",
+            Icon::Clear => "clear",
+            Icon::Wind => "wind",
+            Icon::Unknown => "unknwn"
+        };
+    }
+
+    fn format_ratio(value: Option<f64>) -> String {
+        return match value {
+            Some(value) => format!("{: <7.0}", value * 100.),
+            None => format!("{: <7}", "-")
+        };
+    }
+
+    /// Background shade for a cell based on its share of the predicted row
+    ///
+    /// Matches are shaded green, mismatches red, and stronger colors mean a larger share
+    fn cell_color(matched: bool, share: f64) -> u8 {
+        let shades: [u8; 4] = if matched { [16, 22, 28, 34] } else { [16, 52, 88, 124] };
+        return match share {
+            s if s >= 0.5 => shades[3],
+            s if s >= 0.25 => shades[2],
+            s if s > 0. => shades[1],
+            _ => shades[0]
+        };
+    }
+
+    fn matrix_lines(&self, matrix: &ConfusionMatrix) -> Vec<Line<'static>> {
+        let header = Style::new().fg(HEADER_COLOR);
+
+        let mut titles = vec![Span::styled(format!("{: <9}", "Pred\\Obs"), header)];
+        titles.extend(Icon::ALL.iter().map(|observed| Span::styled(format!("{: <7}", ConfusionView::short_name(*observed)), header)));
+        titles.push(Span::styled("Precision %", header));
+        let mut lines = vec![Line::from(titles)];
+
+        for predicted in Icon::ALL.iter() {
+            let mut spans = vec![Span::styled(format!("{: <9}", ConfusionView::short_name(*predicted)), header)];
+            spans.extend(self.row_cells(matrix, *predicted));
+            spans.push(Span::raw(ConfusionView::format_ratio(matrix.precision(*predicted))));
+            lines.push(Line::from(spans));
+        }
+
+        let mut recall = vec![Span::styled(format!("{: <9}", "Recall %"), header)];
+        recall.extend(Icon::ALL.iter().map(|observed| Span::raw(ConfusionView::format_ratio(matrix.recall(*observed)))));
+        lines.push(Line::from(recall));
+
+        return lines;
+    }
+
+    fn row_cells(&self, matrix: &ConfusionMatrix, predicted: Icon) -> Vec<Span<'static>> {
+        let row_total = matrix.predicted_total(predicted);
+        return Icon::ALL.iter()
+            .flat_map(|observed| {
+                let count = matrix.count(predicted, *observed);
+                let share = if row_total == 0 { 0. } else { count as f64 / row_total as f64 };
+                vec![
+                    Span::styled(format!("{: <6}", count), background(ConfusionView::cell_color(predicted == *observed, share))),
+                    Span::raw(" "),
+                ]
+            })
+            .collect();
+    }
+
+    fn result_lines(&self) -> Vec<Line<'static>> {
+        let results = match &self.results {
+            Some(results) => results,
+            None => return vec![Line::styled(RESULTS_PLACEHOLDER, Style::new().fg(Color::Gray))]
+        };
+
+        let mut lines = vec![viewing_line(format!("{}, {} to {} hours before", range_text(results.start, results.end), results.min_hour_diff, results.max_hour_diff))];
+
+        if results.matrix.total() == 0 {
+            lines.push(Line::default());
+            lines.push(Line::styled("No predictions with readings in range", Style::new().fg(Color::Red)));
+        } else {
+            lines.push(Line::raw(format!("Predictions {}  Accuracy {:.1}%", results.matrix.total(), results.matrix.accuracy().unwrap_or(0.) * 100.)));
+            lines.push(Line::default());
+            lines.extend(self.matrix_lines(&results.matrix));
+        }
+
+        return lines;
+    }
+
+    fn calculate(&self, app: &mut WeatherApp) -> Result<Action, Error> {
+        let (start, end) = form_day_range(app, &self.form)?;
+        let min_hour_diff: u8 = self.form.text("Hours from").trim().parse()?;
+        let max_hour_diff: u8 = self.form.text("Hours to").trim().parse()?;
+        return Ok(Action::Run(Task::new("Calculating icon matrix", move |app, _| {
+            return Ok(ConfusionResults {
+                start,
+                end,
+                min_hour_diff,
+                max_hour_diff,
+                matrix: app.get_confusion_matrix(start, end, min_hour_diff, max_hour_diff)?,
+            });
+        })));
+    }
+}
+
+impl Screen for ConfusionView {
+    fn title(&self) -> String {
+        return String::from("Predicted against observed icons");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return match self.focus {
+            Focus::Form => vec![("Tab", "next field"), ("Hours", &self.hours_hint), ("Enter", "calculate"), ("Esc", "back")],
+            Focus::Results => vec![("PgUp/PgDn", "scroll"), ("Shift+←→", "scroll across"), ("Tab", "options"), ("Esc", "back")],
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let lines = self.result_lines();
+        draw_with_results(frame, area, &self.form, self.focus, &mut self.pane, Text::from(lines));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        match self.focus {
+            Focus::Form => match self.form.handle_key(key) {
+                FormEvent::Submit => return self.calculate(app),
+                FormEvent::Leave => self.focus = Focus::Results,
+                FormEvent::Cancel => return Ok(Action::Pop),
+                FormEvent::None => {}
+            },
+            Focus::Results => match key.code {
+                KeyCode::Esc => return Ok(Action::Pop),
+                _ if self.form.enter(key) => self.focus = Focus::Form,
+                _ => {
+                    self.pane.handle_key(key);
+                }
+            }
+        }
+
+        return Ok(Action::None);
+    }
+
+    fn task_finished(&mut self, result: TaskResult) -> Result<Action, Error> {
+        self.results = Some(task_output(result)?);
+        self.focus = Focus::Results;
+
+        return Ok(Action::None);
+    }
+}
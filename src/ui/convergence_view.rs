@@ -0,0 +1,181 @@
+use crate::ui::screen::{Screen, Action};
+use crate::Error;
+use weather::app::WeatherApp;
+use crate::ui::date_picker::{DatePicker, Precision};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::form::focus_block;
+use crate::ui::text_input::{Prompt, PromptEvent};
+use crate::ui::utils::{ReadingRange, error_message, error_lines, save_csv};
+use weather::analysis::convergence::{Convergence, ConvergenceCsv, ConvergenceTable, Variable};
+use weather::analysis::MAX_HOUR_DIFF;
+use weather::models::SimpleDate;
+use ratatui::Frame;
+use ratatui::layout::{Rect, Layout, Constraint};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::Paragraph;
+
+const HEADER_COLOR: Color = Color::Cyan;
+const OBSERVED_COLOR: Color = Color::Yellow;
+const PLOT_HEIGHT: usize = 12;
+
+pub struct ConvergenceView {
+    range: ReadingRange,
+    picker: DatePicker,
+    /// Convergence of every variable for the picked hour, or why it couldn't be calculated
+    data: Result<Vec<Convergence>, String>,
+    variable: Variable,
+    pane: ScrollPane,
+    /// Asking for a file to export to
+    prompt: Option<Prompt>,
+}
+
+impl ConvergenceView {
+    /// Starts at the latest reading
+    ///
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<ConvergenceView, Error> {
+        let range = ReadingRange::load(app)?;
+        let mut view = ConvergenceView {
+            picker: DatePicker::new(range.last.date(), Precision::Hour),
+            data: Err(String::new()),
+            range,
+            variable: Variable::Temp,
+            pane: ScrollPane::default(),
+            prompt: None,
+        };
+        view.load(app);
+        return Ok(view);
+    }
+
+    fn load(&mut self, app: &mut WeatherApp) {
+        let target = SimpleDate::from(self.picker.value());
+        self.data = app.get_convergence(target.year, target.day, target.hour).map_err(|err| error_message(&err));
+    }
+
+    /// Plot each forecast against how many hours before it was issued, ending at the reading
+    ///
+    /// Rows within tolerance of the reading are shaded so it's clear when the forecast settled
+    fn plot_lines(&self, convergence: &Convergence) -> Vec<Line<'static>> {
+        let header = Style::new().fg(HEADER_COLOR);
+        let values = convergence.forecasts.iter().map(|point| point.value).chain(std::iter::once(convergence.observed));
+        let min = values.clone().fold(f64::INFINITY, f64::min).min(convergence.observed - convergence.tolerance);
+        let max = values.fold(f64::NEG_INFINITY, f64::max).max(convergence.observed + convergence.tolerance);
+        let step = (max - min) / (PLOT_HEIGHT - 1) as f64;
+        let row_of = |value: f64| if step == 0. { 0 } else { ((value - min) / step).round() as usize };
+
+        let mut columns: Vec<Option<usize>> = vec![None; MAX_HOUR_DIFF as usize];
+        convergence.forecasts.iter()
+            .filter(|point| point.hour_diff >= 1 && point.hour_diff <= MAX_HOUR_DIFF)
+            .for_each(|point| columns[(MAX_HOUR_DIFF - point.hour_diff) as usize] = Some(row_of(point.value)));
+        let observed_row = row_of(convergence.observed);
+
+        let mut lines = vec![Line::styled(format!("{} (tolerance ±{})", convergence.variable.name(), convergence.tolerance), header.add_modifier(Modifier::BOLD))];
+
+        for row in (0..PLOT_HEIGHT).rev() {
+            let label = match row {
+                0 => format!("{: >8.2} ", min),
+                _ if row == PLOT_HEIGHT - 1 => format!("{: >8.2} ", max),
+                _ => String::from("         ")
+            };
+
+            let value = min + step * row as f64;
+            let within = (value - convergence.observed).abs() <= convergence.tolerance;
+            let line: String = columns.iter()
+                .map(|column| match column {
+                    Some(column) if *column == row => "● ",
+                    _ if within => "· ",
+                    _ => "  "
+                })
+                .collect();
+
+            lines.push(Line::from(vec![
+                Span::styled(format!("{}|", label), header),
+                Span::raw(line),
+                Span::styled(if row == observed_row { "◆" } else { " " }, Style::new().fg(OBSERVED_COLOR)),
+            ]));
+        }
+
+        lines.push(Line::styled(format!("         +{}", "-".repeat(MAX_HOUR_DIFF as usize * 2 + 1)), header));
+        lines.push(Line::styled(format!("    Hours {: <width$}1 0", MAX_HOUR_DIFF, width = MAX_HOUR_DIFF as usize * 2 - 2), header));
+
+        return lines;
+    }
+
+    fn result_lines(&self) -> Vec<Line<'static>> {
+        let results = match &self.data {
+            Ok(results) => results,
+            Err(message) => return error_lines(message)
+        };
+
+        let mut lines = match results.iter().find(|convergence| convergence.variable == self.variable) {
+            Some(convergence) => self.plot_lines(convergence),
+            None => vec![]
+        };
+        lines.push(Line::default());
+        lines.extend(ConvergenceTable(results).to_string().lines().map(|line| Line::raw(line.to_string())));
+
+        return lines;
+    }
+}
+
+impl Screen for ConvergenceView {
+    fn title(&self) -> String {
+        return String::from("Forecast convergence for hour");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return vec![("←→", "date part"), ("↑↓", "change"), ("0-9", "type"), ("Tab", "variable"), ("e", "export CSV"), ("PgUp/PgDn", "scroll"), ("Esc", "back")];
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let [picker_area, content_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+
+        frame.render_widget(Paragraph::new(vec![self.picker.line("Viewing", true), self.range.line()]), picker_area);
+
+        let lines = self.result_lines();
+        self.pane.draw(frame, content_area, Text::from(lines), focus_block("How the forecast changed before the hour", true));
+
+        if let Some(prompt) = &self.prompt {
+            prompt.draw(frame, area);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        if let Some(prompt) = &mut self.prompt {
+            return match prompt.handle_key(key) {
+                PromptEvent::None => Ok(Action::None),
+                PromptEvent::Cancel => {
+                    self.prompt = None;
+                    Ok(Action::None)
+                }
+                PromptEvent::Submit(path) => {
+                    self.prompt = None;
+                    let results = self.data.as_ref().map(|results| results.as_slice()).unwrap_or_default();
+                    save_csv(&path, |out| ConvergenceCsv(results).write(out))
+                }
+            };
+        }
+
+        match key.code {
+            KeyCode::Esc => return Ok(Action::Pop),
+            KeyCode::Tab | KeyCode::BackTab => {
+                let index = Variable::ALL.iter().position(|variable| *variable == self.variable).unwrap_or(0);
+                let next = if key.code == KeyCode::Tab { index + 1 } else { index + Variable::ALL.len() - 1 };
+                self.variable = Variable::ALL[next % Variable::ALL.len()];
+            }
+            KeyCode::Char('e') if self.data.is_ok() && !self.picker.is_typing() => self.prompt = Some(Prompt::new("Enter file to save CSV to")),
+            _ => {
+                if !self.pane.handle_key(key) && self.picker.handle_key(key) {
+                    self.load(app);
+                }
+            }
+        }
+
+        return Ok(Action::None);
+    }
+}
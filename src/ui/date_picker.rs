@@ -0,0 +1,238 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style, Modifier};
+use ratatui::text::{Line, Span};
+use chrono::{NaiveDateTime, NaiveDate, Datelike, Timelike, Months, Duration};
+use weather::extensions::days_in_month;
+
+const LABEL_COLOR: Color = Color::Cyan;
+const FOCUSED_COLOR: Color = Color::Yellow;
+
+/// Smallest part of the date that can be picked
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Precision {
+    Month,
+    Day,
+    Hour,
+}
+
+impl Precision {
+    fn part_count(self) -> usize {
+        return match self {
+            Precision::Month => 2,
+            Precision::Day => 3,
+            Precision::Hour => 4,
+        };
+    }
+}
+
+/// Date picked a part at a time (year, month, day, hour), shown on a single line
+///
+/// ←→ select a part, ↑↓ (or +/-) step it, and digits type a new value for it
+pub struct DatePicker {
+    value: NaiveDateTime,
+    precision: Precision,
+    /// Index of selected part, 0 is year
+    part: usize,
+    /// Digits typed into the selected part but not yet applied
+    typed: String,
+}
+
+impl DatePicker {
+    pub fn new(value: NaiveDateTime, precision: Precision) -> DatePicker {
+        let mut picker = DatePicker {
+            value,
+            precision,
+            part: precision.part_count() - 1,
+            typed: String::new(),
+        };
+        picker.set_value(value);
+        return picker;
+    }
+
+    pub fn value(&self) -> NaiveDateTime {
+        return self.value;
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        return self.value.date();
+    }
+
+    /// Parts smaller than the precision are cleared (i.e. day 1 and hour 0)
+    pub fn set_value(&mut self, value: NaiveDateTime) {
+        self.value = match self.precision {
+            Precision::Month => value.date().with_day(1).expect("Day 1 is valid").and_hms_opt(0, 0, 0).expect("Midnight is valid"),
+            Precision::Day => value.date().and_hms_opt(0, 0, 0).expect("Midnight is valid"),
+            Precision::Hour => value.with_minute(0).and_then(|value| value.with_second(0)).expect("Hour start is valid"),
+        };
+    }
+
+    /// # Returns
+    /// true if the value changed
+    ///
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let before = self.value;
+        match key.code {
+            KeyCode::Left => {
+                self.apply_typed();
+                self.part = self.part.saturating_sub(1);
+            }
+            KeyCode::Right => {
+                self.apply_typed();
+                self.part = (self.part + 1).min(self.precision.part_count() - 1);
+            }
+            KeyCode::Up | KeyCode::Char('+') => {
+                self.typed.clear();
+                self.step(1);
+            }
+            KeyCode::Down | KeyCode::Char('-') => {
+                self.typed.clear();
+                self.step(-1);
+            }
+            KeyCode::Backspace => {
+                self.typed.pop();
+            }
+            KeyCode::Enter => self.apply_typed(),
+            KeyCode::Char(chr) if chr.is_ascii_digit() => {
+                self.typed.push(chr);
+                if self.typed.len() == self.part_width() {
+                    self.apply_typed();
+                    self.part = (self.part + 1).min(self.precision.part_count() - 1);
+                }
+            }
+            _ => {}
+        }
+        return self.value != before;
+    }
+
+    /// If digits have been typed into the selected part and not yet applied
+    pub fn is_typing(&self) -> bool {
+        return !self.typed.is_empty();
+    }
+
+    fn part_width(&self) -> usize {
+        return if self.part == 0 { 4 } else { 2 };
+    }
+
+    /// Move the selected part by `amount`, months keep their day where possible and otherwise use the last day of the month
+    fn step(&mut self, amount: i32) {
+        let months = |count: u32| Months::new(count * amount.unsigned_abs());
+        let stepped = match self.part {
+            0 if amount > 0 => self.value.checked_add_months(months(12)),
+            0 => self.value.checked_sub_months(months(12)),
+            1 if amount > 0 => self.value.checked_add_months(months(1)),
+            1 => self.value.checked_sub_months(months(1)),
+            2 => self.value.checked_add_signed(Duration::days(amount as i64)),
+            _ => self.value.checked_add_signed(Duration::hours(amount as i64)),
+        };
+        if let Some(stepped) = stepped {
+            self.value = stepped;
+        }
+    }
+
+    /// Set the selected part to the typed digits, invalid values (e.g. month 13) are ignored
+    fn apply_typed(&mut self) {
+        let typed = match self.typed.parse::<u32>() {
+            Ok(typed) => typed,
+            Err(_) => return
+        };
+        self.typed.clear();
+
+        let (mut year, mut month, mut day, mut hour) = (self.value.year(), self.value.month(), self.value.day(), self.value.hour());
+        match self.part {
+            0 => year = typed as i32,
+            1 => month = typed,
+            2 => day = typed,
+            _ => hour = typed,
+        }
+        if !(1..=12).contains(&month) || day == 0 || (self.part == 2 && day > days_in_month(month as u8, year as u32) as u32) {
+            return;
+        }
+        day = day.min(days_in_month(month as u8, year as u32) as u32);
+
+        if let Some(value) = NaiveDate::from_ymd_opt(year, month, day).and_then(|date| date.and_hms_opt(hour, 0, 0)) {
+            self.value = value;
+        }
+    }
+
+    /// Line of `label` and each part of the date, the selected part is highlighted if `focused`
+    pub fn line(&self, label: &str, focused: bool) -> Line<'static> {
+        let label_style = if focused { Style::new().fg(FOCUSED_COLOR) } else { Style::new().fg(LABEL_COLOR) };
+        let parts = [
+            format!("{:04}", self.value.year()),
+            format!("{:02}", self.value.month()),
+            format!("{:02}", self.value.day()),
+            format!("{:02}", self.value.hour()),
+        ];
+        let separators = ["", "-", "-", " "];
+
+        let mut spans = vec![Span::styled(format!("{: <14}", label), label_style)];
+        for (i, part) in parts.iter().enumerate().take(self.precision.part_count()) {
+            spans.push(Span::raw(separators[i]));
+            if focused && i == self.part {
+                let text = if self.typed.is_empty() { part.clone() } else { format!("{:_<1$}", self.typed, self.part_width()) };
+                spans.push(Span::styled(text, Style::new().fg(Color::Black).bg(FOCUSED_COLOR)));
+            } else {
+                spans.push(Span::styled(part.clone(), Style::new().add_modifier(Modifier::BOLD)));
+            }
+        }
+        if self.precision == Precision::Hour {
+            spans.push(Span::raw(":00"));
+        }
+        if self.precision != Precision::Month {
+            spans.push(Span::styled(format!("  {}", self.value.format("%a")), Style::new().fg(Color::Gray)));
+        }
+        return Line::from(spans);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn date(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        return NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, 0, 0).unwrap();
+    }
+
+    fn press(picker: &mut DatePicker, code: KeyCode) -> bool {
+        return picker.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn test_step_months_clamps_day() {
+        let mut picker = DatePicker::new(date(2020, 1, 31, 5), Precision::Hour);
+        press(&mut picker, KeyCode::Left);
+        press(&mut picker, KeyCode::Left);
+
+        assert!(press(&mut picker, KeyCode::Up));
+        assert_eq!(picker.value(), date(2020, 2, 29, 5), "last day of february in a leap year");
+        assert!(press(&mut picker, KeyCode::Char('-')));
+        assert_eq!(picker.value(), date(2020, 1, 29, 5));
+    }
+
+    #[test]
+    fn test_typing() {
+        let mut picker = DatePicker::new(date(2020, 3, 15, 0), Precision::Day);
+        press(&mut picker, KeyCode::Left);
+
+        assert!(!press(&mut picker, KeyCode::Char('1')), "not applied until both digits are typed");
+        assert!(picker.is_typing());
+        assert!(press(&mut picker, KeyCode::Char('1')));
+        assert_eq!(picker.value(), date(2020, 11, 15, 0));
+
+        press(&mut picker, KeyCode::Char('3'));
+        assert!(!press(&mut picker, KeyCode::Char('1')), "november has 30 days");
+        assert_eq!(picker.value(), date(2020, 11, 15, 0));
+
+        press(&mut picker, KeyCode::Left);
+        press(&mut picker, KeyCode::Left);
+        "2019".chars().for_each(|chr| { press(&mut picker, KeyCode::Char(chr)); });
+        assert_eq!(picker.value(), date(2019, 11, 15, 0));
+    }
+
+    #[test]
+    fn test_precision() {
+        let picker = DatePicker::new(date(2020, 3, 15, 7), Precision::Month);
+        assert_eq!(picker.value(), date(2020, 3, 1, 0));
+    }
+}
@@ -1,174 +1,228 @@
-use crate::ui::ui_section::UiSection;
+use crate::ui::screen::{Screen, Action};
+use crate::ui::date_picker::{DatePicker, Precision};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::form::focus_block;
 use crate::Error;
-use crate::app::WeatherApp;
-use chrono::NaiveDateTime;
-use crate::extensions::Utils;
-use crate::ui::utils::{print_styled, print_first_last_reading, print_row_titles, TitlesOpt};
-use crossterm::style::{Color, SetBackgroundColor, Print};
-use std::io::stdout;
-use crossterm::ExecutableCommand;
-use std::convert::TryInto;
-use std::time::Duration;
-use crossterm::event::KeyCode;
-use crate::models::SimpleDate;
+use weather::app::WeatherApp;
+use chrono::{NaiveDateTime, Timelike};
+use crate::ui::utils::{ReadingRange, row, background, titles_line, format_or_blank, error_message, error_lines};
+use weather::models::Weather;
+use crate::ui::chart::{LineChart, Series, sparkline_line, plot_width};
+use ratatui::Frame;
+use ratatui::layout::{Rect, Layout, Constraint};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::Paragraph;
 
 const HEADER_COLOR: Color = Color::Cyan;
+/// Width of the row titles in chart mode
+const CHART_LABEL_WIDTH: usize = 8;
+
+/// Readings for each hour of a day, in the calendar they were loaded with
+struct DayData {
+    calendar: String,
+    hours: Vec<(NaiveDateTime, Option<Weather>)>,
+}
 
 pub struct DayView {
-    reset_pos: (u16, u16)
+    range: ReadingRange,
+    picker: DatePicker,
+    data: Result<DayData, String>,
+    pane: ScrollPane,
+    /// Show line charts instead of the grid
+    chart: bool,
 }
 
 impl DayView {
-    pub fn new(reset_pos: (u16, u16)) -> DayView {
-        return DayView {
-            reset_pos
+    /// Starts at the day of the latest reading
+    ///
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<DayView, Error> {
+        let range = ReadingRange::load(app)?;
+        let last_day = app.calendar()?.date_of(range.last.timestamp);
+        let mut view = DayView {
+            range,
+            picker: DatePicker::new(last_day.and_hms_opt(0, 0, 0).expect("Midnight is valid"), Precision::Day),
+            data: Err(String::new()),
+            pane: ScrollPane::default(),
+            chart: false,
         };
+        view.load(app);
+        return Ok(view);
     }
-}
 
-impl DayView {
-    fn print_temp_row(&self, data: Vec<f64>) -> Result<(), Error> {
-        self.print_row(
+    fn load(&mut self, app: &mut WeatherApp) {
+        let selected_date = self.picker.date();
+        let range = &self.range;
+        self.data = app.calendar()
+            .and_then(|calendar| {
+                if selected_date < calendar.date_of(range.first.timestamp) || selected_date > calendar.date_of(range.last.timestamp) {
+                    return Ok(Err(String::from("Outside of data range")));
+                }
+                return Ok(Ok(DayData {
+                    calendar: calendar.name().to_owned(),
+                    hours: app.get_readings_for_day(selected_date)?,
+                }));
+            })
+            .unwrap_or_else(|err| Err(error_message(&err)));
+    }
+
+    fn temp_row(&self, data: Vec<Option<f64>>) -> Line<'static> {
+        row(
             "Temp    ",
             HEADER_COLOR,
             data,
-            |val| format!("{: <3.0}   ", val),
-            |_| Ok(()),
+            |val| format_or_blank(val, 6, |val| format!("{: <3.0}   ", val)),
+            |_| Style::new(),
         )
     }
 
-    fn print_prob_row(&self, data: Vec<usize>) -> Result<(), Error> {
-        self.print_row(
+    fn prob_row(&self, data: Vec<Option<usize>>) -> Line<'static> {
+        row(
             "P. Prob ",
             HEADER_COLOR,
             data,
-            |val| format!("{: <3}   ", val),
-            |val| {
-                let ansi = match val {
-                    90..=100 => 21,
-                    70..=89 => 20,
-                    50..=69 => 19,
-                    30..=49 => 18,
-                    _ => 16
-                };
-                stdout().execute(SetBackgroundColor(Color::AnsiValue(ansi)))?;
-                Ok(())
-            },
+            |val| format_or_blank(val, 6, |val| format!("{: <3}   ", val)),
+            |val| background(match val {
+                Some(90..=100) => 21,
+                Some(70..=89) => 20,
+                Some(50..=69) => 19,
+                Some(30..=49) => 18,
+                _ => 16
+            }),
         )
     }
 
-    fn print_amt_row(&self, data: Vec<f64>) -> Result<(), Error> {
-        self.print_row(
+    fn amt_row(&self, data: Vec<Option<f64>>) -> Line<'static> {
+        row(
             "P. Amt  ",
             HEADER_COLOR,
             data,
-            |val| format!("{:.1}   ", val),
-            |val| {
-                let ansi = match val {
-                    d if d > &3.0 => 21,
-                    1.0..=2.999 => 20,
-                    0.3..=0.999 => 18,
-                    _ => 16
-                };
-                stdout().execute(SetBackgroundColor(Color::AnsiValue(ansi)))?;
-                Ok(())
-            },
+            |val| format_or_blank(val, 6, |val| format!("{:.1}   ", val)),
+            |val| background(match val {
+                Some(d) if d > &3.0 => 21,
+                Some(1.0..=2.999) => 20,
+                Some(0.3..=0.999) => 18,
+                _ => 16
+            }),
         )
     }
 
-    fn print_gust_row(&self, data: Vec<f64>) -> Result<(), Error> {
-        self.print_row(
+    fn gust_row(&self, data: Vec<Option<f64>>) -> Line<'static> {
+        row(
             "Wnd Gst ",
             HEADER_COLOR,
             data,
-            |val| format!("{: <3.0}   ", val),
-            |_| Ok(()),
+            |val| format_or_blank(val, 6, |val| format!("{: <3.0}   ", val)),
+            |_| Style::new(),
         )
     }
 
-    fn print_speed_row(&self, data: Vec<f64>) -> Result<(), Error> {
-        self.print_row(
+    fn speed_row(&self, data: Vec<Option<f64>>) -> Line<'static> {
+        row(
             "Wnd Spd ",
             HEADER_COLOR,
             data,
-            |val| format!("{: <3.0}   ", val),
-            |_| Ok(()),
+            |val| format_or_blank(val, 6, |val| format!("{: <3.0}   ", val)),
+            |_| Style::new(),
         )
     }
-}
-
-impl UiSection for DayView {
-    fn run(&mut self, app: &mut WeatherApp) -> Result<(), Error> {
-        self.reset(self.reset_pos)?;
-        self.reset_pos = crossterm::cursor::position()?;
-
-        let (first, last) = print_first_last_reading("View specific reading predictions\n", app)?;
-
-        let mut selected_date: NaiveDateTime = self.input_year_day()?.try_into()?;
-
-        loop {
-            if selected_date < first.date() || selected_date > last.date() {
-                print_styled("\n\nOutside of data range\n\n", Color::Red, false)?;
-
-                std::thread::sleep(Duration::from_millis(500));
-
-                self.wait_for_char("\n\nPress any key to continue\n")?;
 
-                return Ok(());
-            } else {
-                let start: SimpleDate = selected_date.into();
-                let mut end = start.clone();
-                end.hour = 23;
-
-                self.reset(self.reset_pos)?;
-
-                stdout()
-                    .execute(Print("\nViewing  "))?;
-
-                print_styled(&format!("{}", selected_date.format("%a %Y-%m-%d")), Color::White, true)?;
+    fn grid(&self, hours: &[u32], readings: &[Option<&Weather>]) -> Vec<Line<'static>> {
+        return vec![
+            titles_line(hours, 8, 4, HEADER_COLOR),
+            self.temp_row(readings.iter().map(|p| p.map(|p| p.temp)).collect()),
+            self.prob_row(readings.iter().map(|p| p.map(|p| (p.precip_probability * 100.) as usize)).collect()),
+            self.amt_row(readings.iter().map(|p| p.map(|p| p.precip_intensity)).collect()),
+            self.speed_row(readings.iter().map(|p| p.map(|p| p.wind_speed)).collect()),
+            self.gust_row(readings.iter().map(|p| p.map(|p| p.wind_gust)).collect()),
+        ];
+    }
 
-                let readings = app.get_readings_over_range(start, end)?;
+    /// Charts `area_width` wide
+    fn charts(&self, hours: &[u32], readings: &[Option<&Weather>], area_width: u16) -> Vec<Line<'static>> {
+        let values = |getter: fn(&Weather) -> f64| readings.iter().map(|weather| weather.map(getter)).collect::<Vec<Option<f64>>>();
+        let x_labels: Vec<(usize, String)> = hours.iter().enumerate()
+            .filter(|(_, hour)| *hour % 3 == 0)
+            .map(|(i, hour)| (i, hour.to_string()))
+            .collect();
+        let width = plot_width(area_width, CHART_LABEL_WIDTH, hours.len());
+        let value_width = width / hours.len().max(1);
+
+        let mut lines = LineChart {
+            series: vec![Series { name: String::from("Temp"), color: Color::Yellow, values: values(|weather| weather.temp) }],
+            width,
+            height: 8,
+            label_width: CHART_LABEL_WIDTH,
+            x_labels: x_labels.clone(),
+        }.lines("Temperature");
+
+        lines.push(Line::default());
+        lines.extend(LineChart {
+            series: vec![
+                Series { name: String::from("Wnd Spd"), color: Color::Green, values: values(|weather| weather.wind_speed) },
+                Series { name: String::from("Wnd Gst"), color: Color::Red, values: values(|weather| weather.wind_gust) },
+            ],
+            width,
+            height: 6,
+            label_width: CHART_LABEL_WIDTH,
+            x_labels,
+        }.lines("Wind"));
+
+        lines.push(Line::default());
+        lines.push(sparkline_line("P. Prob", CHART_LABEL_WIDTH, &values(|weather| weather.precip_probability), 1., value_width, Color::Blue));
+        lines.push(sparkline_line("P. Amt", CHART_LABEL_WIDTH, &values(|weather| weather.precip_intensity), 3., value_width, Color::Blue));
+
+        return lines;
+    }
+}
 
-                print_row_titles(TitlesOpt {
-                    start: 0,
-                    end: 23,
-                    newlines: 2,
-                    initial_padding: 8,
-                    between_padding: 4,
-                    color: HEADER_COLOR
-                })?;
+impl Screen for DayView {
+    fn title(&self) -> String {
+        return String::from("Reading for day");
+    }
 
-                let temps: Vec<f64> = readings.iter().map(|p| p.temp).collect();
-                let probs: Vec<usize> = readings.iter().map(|p| (p.precip_probability * 100.) as usize).collect();
-                let amts: Vec<f64> = readings.iter().map(|p| p.precip_intensity).collect();
-                let speeds: Vec<f64> = readings.iter().map(|p| p.wind_speed).collect();
-                let gusts: Vec<f64> = readings.iter().map(|p| p.wind_gust).collect();
+    fn hints(&self) -> Vec<(&str, &str)> {
+        let view = if self.chart { "grid" } else { "chart" };
+        return vec![("←→", "date part"), ("↑↓", "change"), ("0-9", "type"), ("t", "local time/UTC"), ("g", view), ("PgUp/PgDn", "scroll"), ("Esc", "back")];
+    }
 
-                self.print_temp_row(temps)?;
-                self.print_prob_row(probs)?;
-                self.print_amt_row(amts)?;
-                self.print_speed_row(speeds)?;
-                self.print_gust_row(gusts)?;
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let [picker_area, content_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
 
-                print_styled("\n\n(▲) Previous day\n(▼) Next day\n(esc) Go back", Color::Grey, false)?;
+        frame.render_widget(Paragraph::new(vec![self.picker.line("Viewing", true), self.range.line()]), picker_area);
 
-                loop {
-                    let char = self.wait_for_char_no_delay()?;
+        let (title, lines) = match &self.data {
+            Ok(data) => {
+                let hours: Vec<u32> = data.hours.iter().map(|(time, _)| time.hour()).collect();
+                let readings: Vec<Option<&Weather>> = data.hours.iter().map(|(_, weather)| weather.as_ref()).collect();
+                let lines = if self.chart { self.charts(&hours, &readings, content_area.width) } else { self.grid(&hours, &readings) };
+                (format!("{}, {} hours", data.calendar, hours.len()), lines)
+            }
+            Err(message) => (String::from("Readings"), error_lines(message))
+        };
+        self.pane.draw(frame, content_area, Text::from(lines), focus_block(&title, true));
+    }
 
-                    match char {
-                        KeyCode::Esc => return Ok(()),
-                        KeyCode::Up => {
-                            selected_date = selected_date.minus_one_day();
-                            break;
-                        }
-                        KeyCode::Down => {
-                            selected_date = selected_date.plus_one_day();
-                            break;
-                        }
-                        _ => {}
-                    }
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        match key.code {
+            KeyCode::Esc => return Ok(Action::Pop),
+            KeyCode::Char('t') => {
+                app.set_local_time(!app.is_local_time());
+                self.load(app);
+            }
+            KeyCode::Char('g') => self.chart = !self.chart,
+            _ => {
+                if !self.pane.handle_key(key) && self.picker.handle_key(key) {
+                    self.load(app);
                 }
             }
         }
+
+        return Ok(Action::None);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,208 @@
+use crate::ui::screen::{Screen, Action, Task, TaskResult, task_output};
+use crate::ui::form::{Form, FormEvent, Focus, draw_with_results, RESULTS_PLACEHOLDER};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::Error;
+use weather::app::WeatherApp;
+use weather::models::SimpleDate;
+use crate::ui::utils::{row, background, row_titles, TitlesOpt, day_range_fields, form_day_range, range_text, viewing_line};
+use weather::analysis::error_metrics::{LeadTimeErrors, ErrorMetrics};
+use weather::analysis::MAX_HOUR_DIFF;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Text};
+
+const HEADER_COLOR: Color = Color::Cyan;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Metric {
+    Mae,
+    Rmse,
+    Bias,
+}
+
+impl Metric {
+    fn name(self) -> &'static str {
+        return match self {
+            Metric::Mae => "Mean absolute error",
+            Metric::Rmse => "Root mean squared error",
+            Metric::Bias => "Bias (predicted - observed)",
+        };
+    }
+
+    fn value(self, metrics: &ErrorMetrics) -> f64 {
+        return match self {
+            Metric::Mae => metrics.mae,
+            Metric::Rmse => metrics.rmse,
+            Metric::Bias => metrics.bias,
+        };
+    }
+}
+
+pub struct ForecastErrors {
+    form: Form,
+    focus: Focus,
+    /// Start, end and errors of the last calculation
+    results: Option<(SimpleDate, SimpleDate, Vec<LeadTimeErrors>)>,
+    metric: Metric,
+    pane: ScrollPane,
+}
+
+impl ForecastErrors {
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<ForecastErrors, Error> {
+        return Ok(ForecastErrors {
+            form: Form::new(day_range_fields(app)?),
+            focus: Focus::Form,
+            results: None,
+            metric: Metric::Mae,
+            pane: ScrollPane::default(),
+        });
+    }
+}
+
+impl ForecastErrors {
+    fn count_row(&self, data: &[Option<&LeadTimeErrors>]) -> Line<'static> {
+        row(
+            "Count   ",
+            HEADER_COLOR,
+            data.iter().map(|errors| errors.map(|errors| errors.count)).collect(),
+            |val| match val {
+                Some(count) => format!("{: <5} ", count),
+                None => String::from("-     ")
+            },
+            |_| Style::new(),
+        )
+    }
+
+    /// Row of a single metric for one variable
+    ///
+    /// `scale` is applied to every value (to convert ratios to percentages)
+    /// `step` is the absolute value at which each shade of red starts
+    fn metric_row<F>(&self, title: &str, data: &[Option<&LeadTimeErrors>], scale: f64, step: f64, getter: F) -> Line<'static> where
+        F: Fn(&LeadTimeErrors) -> ErrorMetrics
+    {
+        let metric = self.metric;
+        row(
+            title,
+            HEADER_COLOR,
+            data.iter().map(|errors| errors.map(|errors| metric.value(&getter(errors)) * scale)).collect(),
+            move |val| match val {
+                Some(val) if metric == Metric::Bias => format!("{: <+5.1} ", val),
+                Some(val) => format!("{: <5.1} ", val),
+                None => String::from("-     ")
+            },
+            move |val| background(match val.map(|val| val.abs()) {
+                Some(err) if err >= step * 3. => 160,
+                Some(err) if err >= step * 2. => 124,
+                Some(err) if err >= step => 88,
+                _ => 16
+            }),
+        )
+    }
+
+    fn block(&self, data: &[Option<&LeadTimeErrors>], start: usize) -> Vec<Line<'static>> {
+        return vec![
+            Line::default(),
+            row_titles(TitlesOpt {
+                start,
+                end: start + data.len() - 1,
+                initial_padding: 8,
+                between_padding: 4,
+                color: HEADER_COLOR
+            }),
+            self.count_row(data),
+            self.metric_row("Temp    ", data, 1., 1., |errors| errors.temp),
+            self.metric_row("Wnd Spd ", data, 1., 2., |errors| errors.wind_speed),
+            self.metric_row("Wnd Gst ", data, 1., 2., |errors| errors.wind_gust),
+            self.metric_row("Humid   ", data, 100., 10., |errors| errors.humidity),
+            self.metric_row("P. Amt  ", data, 1., 0.3, |errors| errors.precip_intensity),
+        ];
+    }
+
+    fn result_lines(&self) -> Vec<Line<'static>> {
+        let (start, end, results) = match &self.results {
+            Some(results) => results,
+            None => return vec![Line::styled(RESULTS_PLACEHOLDER, Style::new().fg(Color::Gray))]
+        };
+
+        let mut lines = vec![
+            viewing_line(range_text(*start, *end)),
+            Line::raw(format!("{} by hours before", self.metric.name())),
+        ];
+
+        if results.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::styled("No predictions with readings in range", Style::new().fg(Color::Red)));
+            return lines;
+        }
+
+        let mut slots: Vec<Option<&LeadTimeErrors>> = vec![None; MAX_HOUR_DIFF as usize];
+        results.iter()
+            .for_each(|errors| slots[errors.hour_diff as usize - 1] = Some(errors));
+
+        lines.extend(self.block(&slots[0..24], 1));
+        lines.extend(self.block(&slots[24..MAX_HOUR_DIFF as usize], 25));
+
+        return lines;
+    }
+
+    fn calculate(&self, app: &mut WeatherApp) -> Result<Action, Error> {
+        let (start, end) = form_day_range(app, &self.form)?;
+        return Ok(Action::Run(Task::new("Calculating errors", move |app, _| {
+            return Ok((start, end, app.get_error_metrics(start, end)?));
+        })));
+    }
+}
+
+impl Screen for ForecastErrors {
+    fn title(&self) -> String {
+        return String::from("Errors by lead time");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return match self.focus {
+            Focus::Form => vec![("Tab", "next field"), ("←→", "date part"), ("↑↓", "change"), ("Enter", "calculate"), ("Esc", "back")],
+            Focus::Results => vec![("1", "MAE"), ("2", "RMSE"), ("3", "Bias"), ("PgUp/PgDn", "scroll"), ("Tab", "options"), ("Esc", "back")],
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let lines = self.result_lines();
+        draw_with_results(frame, area, &self.form, self.focus, &mut self.pane, Text::from(lines));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        match self.focus {
+            Focus::Form => match self.form.handle_key(key) {
+                FormEvent::Submit => return self.calculate(app),
+                FormEvent::Leave => self.focus = Focus::Results,
+                FormEvent::Cancel => return Ok(Action::Pop),
+                FormEvent::None => {}
+            },
+            Focus::Results => match key.code {
+                KeyCode::Esc => return Ok(Action::Pop),
+                _ if self.form.enter(key) => self.focus = Focus::Form,
+                KeyCode::Char('1') => self.metric = Metric::Mae,
+                KeyCode::Char('2') => self.metric = Metric::Rmse,
+                KeyCode::Char('3') => self.metric = Metric::Bias,
+                _ => {
+                    self.pane.handle_key(key);
+                }
+            }
+        }
+
+        return Ok(Action::None);
+    }
+
+    fn task_finished(&mut self, result: TaskResult) -> Result<Action, Error> {
+        self.results = Some(task_output(result)?);
+        self.focus = Focus::Results;
+
+        return Ok(Action::None);
+    }
+}
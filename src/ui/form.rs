@@ -0,0 +1,196 @@
+use crate::ui::date_picker::DatePicker;
+use crate::ui::text_input::TextInput;
+use crate::ui::scroll_pane::ScrollPane;
+use ratatui::Frame;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Rect, Layout, Constraint};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Paragraph};
+
+const LABEL_COLOR: Color = Color::Cyan;
+const FOCUSED_COLOR: Color = Color::Yellow;
+const BORDER_COLOR: Color = Color::DarkGray;
+
+/// One of a list of options, ←→ to change
+pub struct Choice {
+    options: Vec<String>,
+    selected: usize,
+}
+
+impl Choice {
+    pub fn new(options: Vec<String>) -> Choice {
+        return Choice {
+            options,
+            selected: 0,
+        };
+    }
+
+    /// Index of the selected option
+    pub fn selected(&self) -> usize {
+        return self.selected;
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Left => self.selected = self.selected.checked_sub(1).unwrap_or(self.options.len() - 1),
+            KeyCode::Right | KeyCode::Char(' ') => self.selected = (self.selected + 1) % self.options.len(),
+            _ => {}
+        }
+    }
+
+    fn line(&self, label: &str, focused: bool) -> Line<'static> {
+        let (label_style, value_style) = if focused {
+            (Style::new().fg(FOCUSED_COLOR), Style::new().fg(Color::Black).bg(FOCUSED_COLOR))
+        } else {
+            (Style::new().fg(LABEL_COLOR), Style::new())
+        };
+        return Line::from(vec![
+            Span::styled(format!("{: <14}", label), label_style),
+            Span::styled(format!("◂ {} ▸", self.options[self.selected]), value_style),
+            Span::styled(format!("  ({}/{})", self.selected + 1, self.options.len()), Style::new().fg(Color::Gray)),
+        ]);
+    }
+}
+
+pub enum Field {
+    Date(DatePicker),
+    Text(TextInput),
+    Choice(Choice),
+}
+
+pub enum FormEvent {
+    None,
+    /// Enter was pressed
+    Submit,
+    /// Tab was pressed on the last field
+    Leave,
+    /// Esc was pressed
+    Cancel,
+}
+
+/// Labelled fields, Tab and Shift+Tab move between them
+pub struct Form {
+    fields: Vec<(&'static str, Field)>,
+    focus: usize,
+}
+
+impl Form {
+    pub fn new(fields: Vec<(&'static str, Field)>) -> Form {
+        return Form {
+            fields,
+            focus: 0,
+        };
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> FormEvent {
+        match key.code {
+            KeyCode::Esc => return FormEvent::Cancel,
+            KeyCode::Tab if self.focus + 1 == self.fields.len() => return FormEvent::Leave,
+            KeyCode::Tab => self.focus += 1,
+            KeyCode::BackTab => self.focus = self.focus.saturating_sub(1),
+            KeyCode::Enter => {
+                //Finish anything typed into a date before submitting
+                if let Field::Date(picker) = &mut self.fields[self.focus].1 {
+                    picker.handle_key(key);
+                }
+                return FormEvent::Submit;
+            }
+            _ => match &mut self.fields[self.focus].1 {
+                Field::Date(picker) => {
+                    picker.handle_key(key);
+                }
+                Field::Text(input) => {
+                    input.handle_key(key);
+                }
+                Field::Choice(choice) => choice.handle_key(key),
+            }
+        }
+        return FormEvent::None;
+    }
+
+    /// Focus a field when coming back from the results, Tab focuses the first and Shift+Tab the last
+    ///
+    /// # Returns
+    /// true if the key was Tab or Shift+Tab
+    ///
+    pub fn enter(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Tab => self.focus = 0,
+            KeyCode::BackTab => self.focus = self.fields.len() - 1,
+            _ => return false
+        }
+        return true;
+    }
+
+    fn field(&self, label: &str) -> &Field {
+        return &self.fields.iter().find(|(field_label, _)| *field_label == label).expect("Form has field").1;
+    }
+
+    pub fn date(&self, label: &str) -> &DatePicker {
+        return match self.field(label) {
+            Field::Date(picker) => picker,
+            _ => panic!("{} is not a date", label)
+        };
+    }
+
+    pub fn text(&self, label: &str) -> &str {
+        return match self.field(label) {
+            Field::Text(input) => input.value(),
+            _ => panic!("{} is not text", label)
+        };
+    }
+
+    /// Index of the selected option
+    pub fn choice(&self, label: &str) -> usize {
+        return match self.field(label) {
+            Field::Choice(choice) => choice.selected(),
+            _ => panic!("{} is not a choice", label)
+        };
+    }
+
+    /// One line per field, the focused field is highlighted if `focused`
+    pub fn lines(&self, focused: bool) -> Vec<Line<'static>> {
+        return self.fields.iter()
+            .enumerate()
+            .map(|(i, (label, field))| {
+                let focused = focused && i == self.focus;
+                match field {
+                    Field::Date(picker) => picker.line(label, focused),
+                    Field::Text(input) => input.line(label, focused),
+                    Field::Choice(choice) => choice.line(label, focused),
+                }
+            })
+            .collect();
+    }
+
+    pub fn height(&self) -> u16 {
+        return self.fields.len() as u16 + 2;
+    }
+}
+
+/// Shown in the results pane before anything has been calculated
+pub const RESULTS_PLACEHOLDER: &str = "Choose options and press Enter to calculate";
+
+/// Whether keys go to the form or the results underneath
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Focus {
+    Form,
+    Results,
+}
+
+/// Block with a highlighted border if focused
+pub fn focus_block(title: &str, focused: bool) -> Block<'static> {
+    let color = if focused { FOCUSED_COLOR } else { BORDER_COLOR };
+    return Block::bordered()
+        .title(format!(" {} ", title))
+        .border_style(Style::new().fg(color));
+}
+
+/// Draw `form` with `results` in a scrolling pane underneath
+pub fn draw_with_results(frame: &mut Frame, area: Rect, form: &Form, focus: Focus, pane: &mut ScrollPane, results: Text) {
+    let [form_area, results_area] = Layout::vertical([Constraint::Length(form.height()), Constraint::Min(0)]).areas(area);
+
+    frame.render_widget(Paragraph::new(form.lines(focus == Focus::Form)).block(focus_block("Options", focus == Focus::Form)), form_area);
+    pane.draw(frame, results_area, results, focus_block("Results", focus == Focus::Results));
+}
@@ -1,37 +1,136 @@
-use crate::ui::ui_section::UiSection;
-use crate::app::WeatherApp;
+use crate::ui::screen::{Screen, Action, Task, TaskResult, task_output};
+use crate::ui::form::{Form, Field, FormEvent, Focus, Choice, draw_with_results};
+use crate::ui::text_input::TextInput;
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::utils::parse_globs;
+use weather::app::WeatherApp;
 use crate::Error;
-use std::io::stdout;
-use crossterm::style::{Print, Color};
-use crossterm::ExecutableCommand;
-use crate::ui::utils::print_styled;
+use weather::templates::importer_names;
+use weather::import::{ImportOptions, ImportReport};
+use weather::import::source::PathFilter;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Text};
 
 pub struct ImportData {
-    reset_pos: (u16, u16)
+    form: Form,
+    focus: Focus,
+    /// Ids of the locations in the location choice, after 'From coordinates in file'
+    location_ids: Vec<i64>,
+    report: Option<ImportReport>,
+    pane: ScrollPane,
 }
 
 impl ImportData {
-    pub fn new(reset_pos: (u16, u16)) -> ImportData {
-        return ImportData {
-            reset_pos
+    /// # Errors
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<ImportData, Error> {
+        let locations = app.get_locations()?;
+
+        let mut formats = vec![String::from("Detect automatically")];
+        formats.extend(importer_names().into_iter().map(String::from));
+        let mut location_names = vec![String::from("From coordinates in file")];
+        location_names.extend(locations.iter().map(|location| location.name.clone()));
+        let choice = |options: &[&str]| Field::Choice(Choice::new(options.iter().map(|option| option.to_string()).collect()));
+
+        return Ok(ImportData {
+            form: Form::new(vec![
+                ("Directory", Field::Text(TextInput::default())),
+                ("Format", Field::Choice(Choice::new(formats))),
+                ("Location", Field::Choice(Choice::new(location_names))),
+                ("Directories", choice(&["Top level only", "Include subdirectories"])),
+                ("Include", Field::Text(TextInput::default())),
+                ("Exclude", Field::Text(TextInput::default())),
+                ("Mode", choice(&["Import new and changed files", "Import everything", "Dry run (check files without saving)"])),
+            ]),
+            focus: Focus::Form,
+            location_ids: locations.iter().map(|location| location.id).collect(),
+            report: None,
+            pane: ScrollPane::default(),
+        });
+    }
+
+    fn import(&self) -> Result<Action, Error> {
+        let dir = self.form.text("Directory").trim().to_owned();
+        if dir.is_empty() {
+            return Err("Enter a directory to import from".into());
+        }
+
+        let include = parse_globs(self.form.text("Include"));
+        let exclude = parse_globs(self.form.text("Exclude"));
+        PathFilter::new(&include, &exclude)?;
+
+        let names = importer_names();
+        let mode = self.form.choice("Mode");
+        let options = ImportOptions {
+            format: self.form.choice("Format").checked_sub(1).map(|index| String::from(names[index])),
+            location_id: self.form.choice("Location").checked_sub(1).map(|index| self.location_ids[index]),
+            dry_run: mode == 2,
+            recursive: self.form.choice("Directories") == 1,
+            include,
+            exclude,
+            force: mode == 1,
         };
+
+        return Ok(Action::Run(Task::new("Importing", move |app, progress| {
+            return Ok(app.import_data_with_progress(dir, &options, |report| {
+                progress(format!("Read {} files ({} skipped, {} rejected)", report.files.len(), report.skipped(), report.rejected().len()));
+            })?);
+        })));
     }
 }
 
-impl UiSection for ImportData {
-    fn run(&mut self, app: &mut WeatherApp) -> Result<(), Error> {
-        self.reset(self.reset_pos)?;
+impl Screen for ImportData {
+    fn title(&self) -> String {
+        return String::from("Import data");
+    }
 
-        let dir = self.read_input("Enter a directory to import from\n")?;
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return match self.focus {
+            Focus::Form => vec![("Tab", "next field"), ("←→", "change"), ("Include/Exclude", "globs, comma separated"), ("Enter", "import"), ("Esc", "back")],
+            Focus::Results => vec![("PgUp/PgDn", "scroll"), ("Tab", "options"), ("Esc", "back")],
+        };
+    }
 
-        stdout().execute(Print("\nImporting\n"))?;
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let results = match &self.report {
+            Some(report) => {
+                let color = if report.rejected().is_empty() { Color::Green } else { Color::Red };
+                Text::styled(report.to_string(), Style::new().fg(color))
+            }
+            None => Text::from(Line::styled("Enter a directory and press Enter to import", Style::new().fg(Color::Gray)))
+        };
+        draw_with_results(frame, area, &self.form, self.focus, &mut self.pane, results);
+    }
 
-        app.import_data(dir)?;
+    fn handle_key(&mut self, key: KeyEvent, _app: &mut WeatherApp) -> Result<Action, Error> {
+        match self.focus {
+            Focus::Form => match self.form.handle_key(key) {
+                FormEvent::Submit => return self.import(),
+                FormEvent::Leave => self.focus = Focus::Results,
+                FormEvent::Cancel => return Ok(Action::Pop),
+                FormEvent::None => {}
+            },
+            Focus::Results => match key.code {
+                KeyCode::Esc => return Ok(Action::Pop),
+                _ if self.form.enter(key) => self.focus = Focus::Form,
+                _ => {
+                    self.pane.handle_key(key);
+                }
+            }
+        }
 
-        print_styled("\nDone", Color::Green, false)?;
+        return Ok(Action::None);
+    }
 
-        self.wait_for_char("\nPress any key to continue\n")?;
+    fn task_finished(&mut self, result: TaskResult) -> Result<Action, Error> {
+        self.report = Some(task_output(result)?);
+        self.focus = Focus::Results;
+        self.pane = ScrollPane::default();
 
-        Ok(())
+        return Ok(Action::None);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,64 @@
+use crate::ui::screen::{Screen, Action};
+use crate::ui::menu::Menu;
+use weather::app::WeatherApp;
+use weather::models::Location;
+use crate::Error;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+/// Choose which location every other screen shows
+pub struct LocationSelect {
+    locations: Vec<Location>,
+    menu: Menu,
+}
+
+impl LocationSelect {
+    /// # Errors
+    /// No locations
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<LocationSelect, Error> {
+        let locations = app.get_locations()?;
+        if locations.is_empty() {
+            return Err("There are no locations yet\nUse 'Import data' to add some".into());
+        }
+        let current = app.get_location()?.map(|location| location.id);
+
+        let options = locations.iter()
+            .map(|location| format!("{} {}", if Some(location.id) == current { "*" } else { " " }, location.name))
+            .collect();
+
+        return Ok(LocationSelect {
+            locations,
+            menu: Menu::new(options),
+        });
+    }
+}
+
+impl Screen for LocationSelect {
+    fn title(&self) -> String {
+        return String::from("Select location");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return vec![("↑↓", "select"), ("Enter", "use location"), ("Esc", "keep current (*)")];
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        self.menu.draw(frame, area, "");
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        if key.code == KeyCode::Esc {
+            return Ok(Action::Pop);
+        }
+
+        if let Some(location) = self.menu.handle_key(key).and_then(|index| self.locations.get(index)) {
+            app.set_location(location.id);
+            return Ok(Action::Pop);
+        }
+
+        return Ok(Action::None);
+    }
+}
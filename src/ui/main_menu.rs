@@ -1,57 +1,90 @@
-use crate::ui::ui_section::UiSection;
-use crate::app::WeatherApp;
+use crate::ui::screen::{Screen, Action, Task, TaskResult, task_output};
+use crate::ui::menu::Menu;
+use weather::app::WeatherApp;
 use crate::ui::weather_predictions::WeatherPredictions;
 use crate::ui::weather_view::WeatherView;
 use crate::Error;
 use crate::ui::import_data::ImportData;
 use crate::ui::missing_records::MissingRecords;
+use crate::ui::location_select::LocationSelect;
 use crate::ui::day_view::DayView;
 use crate::ui::month_view::MonthView;
 use crate::ui::weather_diff::WeatherDiff;
+use crate::ui::verification_menu::VerificationMenu;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use chrono::NaiveDateTime;
 
 pub struct MainMenu {
-    reset_pos: (u16, u16)
+    menu: Menu,
 }
 
 impl MainMenu {
-    pub fn new(reset_pos: (u16, u16)) -> MainMenu {
+    pub fn new(local_time: bool) -> MainMenu {
         return MainMenu {
-            reset_pos
+            menu: Menu::new(MainMenu::options(local_time)),
         };
     }
+
+    fn options(local_time: bool) -> Vec<String> {
+        let time_option = if local_time { "Show days in UTC" } else { "Show days in local time" };
+        return vec![
+            "Import data",
+            "Check for missing records",
+            "Reading for hour",
+            "Reading for day",
+            "Reading for month",
+            "Predictions for hour",
+            "Differences for hour",
+            "Prediction verification",
+            "Select location",
+            time_option,
+        ].into_iter().map(String::from).collect();
+    }
 }
 
+impl Screen for MainMenu {
+    fn title(&self) -> String {
+        return String::from("Main menu");
+    }
 
-impl UiSection for MainMenu {
-    fn run(&mut self, app: &mut WeatherApp) -> Result<(), Error> {
-        loop {
-            self.reset(self.reset_pos)?;
-
-            let menu_options = vec![
-                "Import data",
-                "Check for missing records",
-                "Reading for hour",
-                "Reading for day",
-                "Reading for month",
-                "Predictions for hour",
-                "Differences for hour",
-            ];
-
-            let input = self.menu(menu_options, true)?;
-
-            match input {
-                0 => break,
-                1 => ImportData::new(self.reset_pos).run(app)?,
-                2 => MissingRecords::new(self.reset_pos).run(app)?,
-                3 => WeatherView::new(self.reset_pos).run(app)?,
-                4 => DayView::new(self.reset_pos).run(app)?,
-                5 => MonthView::new(self.reset_pos).run(app)?,
-                6 => WeatherPredictions::new(self.reset_pos).run(app)?,
-                7 => WeatherDiff::new(self.reset_pos).run(app)?,
-                _ => {}
-            }
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return vec![("↑↓", "select"), ("Enter/1-9", "open"), ("Esc", "exit")];
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        self.menu.draw(frame, area, "");
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        if key.code == KeyCode::Esc {
+            return Ok(Action::Quit);
         }
 
-        Ok(())
+        let action = match self.menu.handle_key(key) {
+            Some(0) => Action::Push(Box::new(ImportData::new(app)?)),
+            Some(1) => Action::Run(Task::new("Searching for missing records", |app, _| app.check_for_missing_data().map_err(Error::from))),
+            Some(2) => Action::Push(Box::new(WeatherView::new(app)?)),
+            Some(3) => Action::Push(Box::new(DayView::new(app)?)),
+            Some(4) => Action::Push(Box::new(MonthView::new(app)?)),
+            Some(5) => Action::Push(Box::new(WeatherPredictions::new(app)?)),
+            Some(6) => Action::Push(Box::new(WeatherDiff::new(app)?)),
+            Some(7) => Action::Push(Box::new(VerificationMenu::new())),
+            Some(8) => Action::Push(Box::new(LocationSelect::new(app)?)),
+            Some(9) => {
+                app.set_local_time(!app.is_local_time());
+                Action::None
+            }
+            _ => Action::None
+        };
+        self.menu.set_options(MainMenu::options(app.is_local_time()));
+
+        return Ok(action);
     }
-}
\ No newline at end of file
+
+    fn task_finished(&mut self, result: TaskResult) -> Result<Action, Error> {
+        let missing: Vec<NaiveDateTime> = task_output(result)?;
+        return Ok(Action::Push(Box::new(MissingRecords::new(missing))));
+    }
+}
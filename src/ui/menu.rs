@@ -0,0 +1,102 @@
+use ratatui::Frame;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style, Modifier};
+use ratatui::widgets::{Block, List, ListState};
+
+const HEADER_COLOR: Color = Color::Cyan;
+const SELECTED_COLOR: Color = Color::Yellow;
+
+/// Number key for the option at `index`, 1 to 9 then 0
+fn shortcut(index: usize) -> Option<usize> {
+    return match index {
+        0..=8 => Some(index + 1),
+        9 => Some(0),
+        _ => None
+    };
+}
+
+/// Index of the option chosen with number key `num`
+fn shortcut_index(num: u32) -> usize {
+    return if num == 0 { 9 } else { num as usize - 1 };
+}
+
+/// List of options, ↑↓ and Enter or the option's number to choose (0 for the 10th)
+pub struct Menu {
+    options: Vec<String>,
+    state: ListState,
+}
+
+impl Menu {
+    pub fn new(options: Vec<String>) -> Menu {
+        return Menu {
+            options,
+            state: ListState::default().with_selected(Some(0)),
+        };
+    }
+
+    /// Replace the options, keeping the selection where possible
+    pub fn set_options(&mut self, options: Vec<String>) {
+        let selected = self.state.selected().unwrap_or(0).min(options.len().saturating_sub(1));
+        self.options = options;
+        self.state.select(Some(selected));
+    }
+
+    /// # Returns
+    /// Index of the chosen option
+    ///
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<usize> {
+        if self.options.is_empty() {
+            return None;
+        }
+        let selected = self.state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Up => self.state.select(Some(selected.checked_sub(1).unwrap_or(self.options.len() - 1))),
+            KeyCode::Down => self.state.select(Some((selected + 1) % self.options.len())),
+            KeyCode::Enter => return Some(selected),
+            KeyCode::Char(chr) => {
+                if let Some(index) = chr.to_digit(10).map(shortcut_index).filter(|index| *index < self.options.len()) {
+                    self.state.select(Some(index));
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+        return None;
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, title: &str) {
+        let items: Vec<String> = self.options.iter()
+            .enumerate()
+            .map(|(i, option)| match shortcut(i) {
+                Some(key) => format!("{}) {}", key, option),
+                None => format!("   {}", option)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::new().title(title.to_owned()).title_style(Style::new().fg(HEADER_COLOR)))
+            .highlight_style(Style::new().fg(SELECTED_COLOR).add_modifier(Modifier::BOLD))
+            .highlight_symbol("› ");
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shortcuts() {
+        let mut menu = Menu::new((1..=11).map(|i| i.to_string()).collect());
+        let press = |menu: &mut Menu, chr: char| menu.handle_key(KeyEvent::from(KeyCode::Char(chr)));
+
+        assert_eq!(press(&mut menu, '1'), Some(0));
+        assert_eq!(press(&mut menu, '9'), Some(8));
+        assert_eq!(press(&mut menu, '0'), Some(9), "0 is the 10th option");
+        assert_eq!(shortcut(9), Some(0));
+        assert_eq!(shortcut(10), None, "options after the 10th have no number");
+
+        let mut menu = Menu::new(vec![String::from("Only")]);
+        assert_eq!(press(&mut menu, '0'), None);
+    }
+}
@@ -1,49 +1,52 @@
-use crate::ui::ui_section::UiSection;
-use std::io::{stdout, Write};
+use crate::ui::screen::{Screen, Action};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::form::focus_block;
 use crate::Error;
-use crate::app::WeatherApp;
-use crossterm::style::Print;
-use crossterm::{ExecutableCommand, QueueableCommand};
+use weather::app::WeatherApp;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::text::{Line, Text};
+use chrono::NaiveDateTime;
 
 pub struct MissingRecords {
-    reset_pos: (u16, u16)
+    missing: Vec<NaiveDateTime>,
+    pane: ScrollPane,
 }
 
 impl MissingRecords {
-    pub fn new(reset_pos: (u16, u16)) -> MissingRecords {
+    pub fn new(missing: Vec<NaiveDateTime>) -> MissingRecords {
         return MissingRecords {
-            reset_pos
+            missing,
+            pane: ScrollPane::default(),
         };
     }
 }
 
-impl UiSection for MissingRecords {
-    fn run(&mut self, app: &mut WeatherApp) -> Result<(), Error> {
-        self.reset(self.reset_pos)?;
-
-        stdout()
-            .execute(Print("Searching..."))?;
-
-        let results = app.check_for_missing_data()?;
+impl Screen for MissingRecords {
+    fn title(&self) -> String {
+        return String::from("Missing records");
+    }
 
-        self.reset(self.reset_pos)?;
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return vec![("PgUp/PgDn", "scroll"), ("Esc", "back")];
+    }
 
-        stdout()
-            .execute(Print("Missing:\n"))?;
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = if self.missing.is_empty() {
+            vec![Line::raw("None")]
+        } else {
+            self.missing.iter().map(|hour| Line::raw(hour.format("%Y %j %H").to_string())).collect()
+        };
+        self.pane.draw(frame, area, Text::from(lines), focus_block(&format!("Missing ({})", self.missing.len()), true));
+    }
 
-        if results.is_empty() {
-            stdout()
-                .queue(Print("None"))?;
-        }
-        for result in results {
-            stdout()
-                .queue(Print(format!("{}", result.format("%Y %j %H"))))?;
+    fn handle_key(&mut self, key: KeyEvent, _app: &mut WeatherApp) -> Result<Action, Error> {
+        if key.code == KeyCode::Esc {
+            return Ok(Action::Pop);
         }
+        self.pane.handle_key(key);
 
-        stdout().flush()?;
-
-        self.wait_for_char("\n\n\nPress any key to continue\n")?;
-
-        Ok(())
+        return Ok(Action::None);
     }
-}
\ No newline at end of file
+}
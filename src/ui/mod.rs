@@ -1,57 +1,248 @@
-use crate::app::WeatherApp;
-use std::io::stdout;
-use crossterm::{ExecutableCommand, cursor};
-use crossterm::style::Color;
+use weather::app::WeatherApp;
 use crate::Error;
-use crossterm::terminal::{Clear, ClearType};
-use crossterm::cursor::MoveTo;
-use crate::ui::ui_section::UiSection;
-use crate::ui::utils::print_styled;
+use crate::ui::screen::{Screen, Action, Task, TaskResult};
+use crate::ui::utils::error_message;
 use crate::ui::main_menu::MainMenu;
+use ratatui::{DefaultTerminal, Frame};
+use ratatui::crossterm::event::{self, Event, KeyEvent, KeyEventKind, KeyCode, KeyModifiers};
+use ratatui::layout::{Layout, Constraint};
+use ratatui::style::{Color, Style, Modifier};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
-mod weather_predictions;
-mod ui_section;
+mod screen;
 mod utils;
+mod chart;
+mod date_picker;
+mod text_input;
+mod form;
+mod scroll_pane;
+mod menu;
+mod weather_predictions;
 mod weather_view;
 mod main_menu;
 mod import_data;
 mod missing_records;
+mod location_select;
 mod day_view;
 mod month_view;
 mod weather_diff;
+mod verification_menu;
+mod forecast_errors;
+mod calibration_view;
+mod confusion_view;
+mod skill_view;
+mod rain_events_view;
+mod convergence_view;
+
+/// How long to wait for input before redrawing, progress of tasks is updated this often
+const TICK: Duration = Duration::from_millis(100);
+const TITLE_COLOR: Color = Color::Cyan;
+
+enum TaskEvent {
+    Progress(String),
+    Finished(TaskResult),
+}
+
+/// A task running on another thread, it has the app until it finishes
+struct RunningTask {
+    name: String,
+    progress: String,
+    events: Receiver<TaskEvent>,
+    handle: JoinHandle<WeatherApp>,
+}
 
 pub struct Ui {
-    app: WeatherApp,
-    reset_pos: Option<(u16, u16)>,
+    /// `None` while a task is running
+    app: Option<WeatherApp>,
+    /// Open screens, the last is shown
+    screens: Vec<Box<dyn Screen>>,
+    /// Message and its color, shown in the status bar until the next key press
+    message: Option<(String, Color)>,
+    /// Location and calendar, shown in the status bar
+    context: String,
+    task: Option<RunningTask>,
 }
 
 impl Ui {
     pub fn new(app: WeatherApp) -> Ui {
+        let main_menu = MainMenu::new(app.is_local_time());
         return Ui {
-            app,
-            reset_pos: None,
+            app: Some(app),
+            screens: vec![Box::new(main_menu)],
+            message: None,
+            context: String::new(),
+            task: None,
         };
     }
 }
 
 impl Ui {
+    /// Take over the terminal and show the main menu until the user exits
+    ///
+    /// The terminal is restored even if an error stops the UI
+    ///
+    /// # Errors
+    /// Unable to draw or read events
+    ///
     pub fn run(&mut self) -> Result<(), Error> {
-        stdout()
-            .execute(cursor::Hide)?
-            .execute(crossterm::terminal::SetSize(160, 40))?
-            .execute(Clear(ClearType::All))?
-            .execute(MoveTo(0, 0))?;
+        let mut terminal = ratatui::try_init()?;
+        let result = self.event_loop(&mut terminal);
+        ratatui::try_restore()?;
+        return result;
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+        self.update_context();
+
+        while !self.screens.is_empty() {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            self.check_task()?;
+
+            if event::poll(TICK)? {
+                //Resizes need no handling as every loop redraws to the current size
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.handle_key(key)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Result<(), Error> {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.screens.clear();
+            return Ok(());
+        }
+
+        let app = match self.app.as_mut() {
+            Some(app) => app,
+            None => return Ok(()) //Keys are ignored while a task is running
+        };
+        let screen = match self.screens.last_mut() {
+            Some(screen) => screen,
+            None => return Ok(())
+        };
+
+        self.message = None;
+        let result = screen.handle_key(key, app);
+        self.apply(result)?;
+        self.update_context();
 
-        print_styled("Weather prediction examiner\n\n", Color::Cyan, true)?;
+        Ok(())
+    }
+
+    fn apply(&mut self, result: Result<Action, Error>) -> Result<(), Error> {
+        match result {
+            Ok(Action::None) => {}
+            Ok(Action::Push(screen)) => self.screens.push(screen),
+            Ok(Action::Pop) => {
+                self.screens.pop();
+            }
+            Ok(Action::Quit) => self.screens.clear(),
+            Ok(Action::Info(text)) => self.message = Some((text, Color::Green)),
+            Ok(Action::Run(task)) => self.start_task(task)?,
+            Err(err) => self.message = Some((error_message(err.as_ref()), Color::Red)),
+        }
+
+        Ok(())
+    }
+
+    /// Move the app to another thread and run `task` with it
+    fn start_task(&mut self, task: Task) -> Result<(), Error> {
+        let mut app = self.app.take().ok_or("A task is already running")?;
+        let (sender, events) = channel();
+        let Task { name, work } = task;
+
+        let handle = std::thread::spawn(move || {
+            let progress_sender = sender.clone();
+            // Sending only fails if the UI has stopped, in which case no one is waiting for the result
+            let result = work(&mut app, &mut |progress| { let _ = progress_sender.send(TaskEvent::Progress(progress)); });
+            let _ = sender.send(TaskEvent::Finished(result));
+            return app;
+        });
 
-        self.reset_pos = Some(crossterm::cursor::position()?);
-        let pos = self.reset_pos.expect("No reset found when starting main menu");
+        self.task = Some(RunningTask {
+            name,
+            progress: String::new(),
+            events,
+            handle,
+        });
+
+        Ok(())
+    }
 
-        MainMenu::new(pos).run(&mut self.app)?;
+    /// Update progress of the running task, and give the app back and send the result to the screen if it's finished
+    fn check_task(&mut self) -> Result<(), Error> {
+        let mut finished = None;
+        if let Some(task) = self.task.as_mut() {
+            while let Ok(event) = task.events.try_recv() {
+                match event {
+                    TaskEvent::Progress(progress) => task.progress = progress,
+                    TaskEvent::Finished(result) => finished = Some(result),
+                }
+            }
+        }
 
-        stdout()
-            .execute(cursor::Show)?;
+        if let Some(result) = finished {
+            let task = self.task.take().expect("Finished task is running");
+            self.app = Some(task.handle.join().map_err(|_| "Task panicked")?);
+            let action = match self.screens.last_mut() {
+                Some(screen) => screen.task_finished(result),
+                None => Ok(Action::None)
+            };
+            self.apply(action)?;
+            self.update_context();
+        }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Refresh the location and calendar shown in the status bar
+    fn update_context(&mut self) {
+        if let Some(app) = self.app.as_mut() {
+            let location = app.get_location().ok().flatten().map(|location| location.name).unwrap_or_else(|| String::from("-"));
+            let calendar = app.calendar().map(|calendar| calendar.name().to_owned()).unwrap_or_default();
+            self.context = format!(" Location: {}  Days in: {} ", location, calendar);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [title_area, body_area, status_area] = Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let titles: Vec<String> = self.screens.iter().map(|screen| screen.title()).collect();
+        let title = Line::from(vec![
+            Span::styled("Weather prediction examiner", Style::new().fg(TITLE_COLOR).add_modifier(Modifier::BOLD)),
+            Span::styled(titles.iter().skip(1).map(|title| format!(" › {}", title)).collect::<String>(), Style::new().fg(TITLE_COLOR)),
+        ]);
+        frame.render_widget(Paragraph::new(title), title_area);
+
+        if let Some(screen) = self.screens.last_mut() {
+            screen.draw(frame, body_area);
+        }
+
+        let [status_left, status_right] = Layout::horizontal([Constraint::Min(0), Constraint::Length(self.context.chars().count() as u16)]).areas(status_area);
+        let status = match (&self.task, &self.message) {
+            (Some(task), _) => Line::styled(format!(" {}: {}", task.name, task.progress), Style::new().fg(Color::Yellow)),
+            (None, Some((message, color))) => Line::styled(format!(" {}", message.replace('\n', " - ")), Style::new().fg(*color)),
+            (None, None) => Line::from(self.screens.last()
+                .map(|screen| screen.hints())
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|(key, description)| vec![
+                    Span::styled(format!(" {} ", key), Style::new().fg(Color::Black).bg(Color::Gray)),
+                    Span::styled(format!(" {}  ", description), Style::new().fg(Color::Gray)),
+                ])
+                .collect::<Vec<Span>>()),
+        };
+        frame.render_widget(Paragraph::new(status), status_left);
+        frame.render_widget(Paragraph::new(Line::styled(self.context.as_str(), Style::new().fg(Color::Black).bg(TITLE_COLOR))), status_right);
+    }
+}
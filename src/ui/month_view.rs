@@ -1,217 +1,248 @@
-use crate::ui::ui_section::UiSection;
+use crate::ui::screen::{Screen, Action};
+use crate::ui::date_picker::{DatePicker, Precision};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::form::focus_block;
 use crate::Error;
-use crate::app::WeatherApp;
-use std::io::stdout;
-use crossterm::style::{SetBackgroundColor, Color, Print};
-use crossterm::ExecutableCommand;
-use crate::ui::utils::{print_first_last_reading, print_styled, print_row_titles, TitlesOpt};
-use chrono::{NaiveDateTime, Datelike, NaiveDate, NaiveTime, Timelike};
-use std::time::Duration;
-use crate::models::SimpleDate;
-use crossterm::event::KeyCode;
-use crate::extensions::{Utils, days_in_month};
-use crate::min_max_avg::{avg_usize, max_usize, min_usize, avg_f64, min_f64, max_f64};
+use weather::app::WeatherApp;
+use crate::ui::utils::{ReadingRange, row, background, row_titles, TitlesOpt, format_or_blank, error_message, error_lines};
+use chrono::{Datelike, NaiveDate};
+use weather::extensions::days_in_month;
+use crate::ui::chart::{LineChart, Series, sparkline_line, plot_width};
+use ratatui::Frame;
+use ratatui::layout::{Rect, Layout, Constraint};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::Paragraph;
 
 const HEADER_COLOR: Color = Color::Cyan;
+/// Width of the row titles in chart mode
+const CHART_LABEL_WIDTH: usize = 8;
+/// Days in each block of the grid
+const BLOCK_DAYS: usize = 11;
+
+/// Min, avg and max of each day of a month, in the calendar they were loaded with
+struct MonthData {
+    calendar: String,
+    temps: Vec<Option<(f64, f64, f64)>>,
+    probs: Vec<Option<(usize, usize, usize)>>,
+    amts: Vec<Option<(f64, f64, f64)>>,
+}
 
 pub struct MonthView {
-    reset_pos: (u16, u16)
+    range: ReadingRange,
+    picker: DatePicker,
+    data: Result<MonthData, String>,
+    pane: ScrollPane,
+    /// Show line charts instead of the grid
+    chart: bool,
 }
 
 impl MonthView {
-    pub fn new(reset_pos: (u16, u16)) -> MonthView {
-        return MonthView {
-            reset_pos
+    /// Starts at the month of the latest reading
+    ///
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<MonthView, Error> {
+        let range = ReadingRange::load(app)?;
+        let last_day = app.calendar()?.date_of(range.last.timestamp);
+        let mut view = MonthView {
+            range,
+            picker: DatePicker::new(last_day.and_hms_opt(0, 0, 0).expect("Midnight is valid"), Precision::Month),
+            data: Err(String::new()),
+            pane: ScrollPane::default(),
+            chart: false,
         };
+        view.load(app);
+        return Ok(view);
     }
-}
 
-impl MonthView {
-    fn print_temp_row(&self, data: &Vec<(f64, f64, f64)>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn load(&mut self, app: &mut WeatherApp) {
+        let first_day = self.picker.date();
+        let range = &self.range;
+        self.data = app.calendar()
+            .and_then(|calendar| {
+                let last_day = first_day.with_day(days_in_month(first_day.month() as u8, first_day.year() as u32) as u32).expect("Invalid days_in_month");
+                if last_day < calendar.date_of(range.first.timestamp) || first_day > calendar.date_of(range.last.timestamp) {
+                    return Ok(Err(String::from("Outside of data range")));
+                }
+                let days = app.get_readings_by_day(first_day, last_day)?;
+                return Ok(Ok(MonthView::summarise(calendar.name(), &days)));
+            })
+            .unwrap_or_else(|err| Err(error_message(&err)));
+    }
+
+    fn summarise(calendar: &str, days: &[(NaiveDate, Vec<weather::models::Weather>)]) -> MonthData {
+        let mut data = MonthData {
+            calendar: calendar.to_owned(),
+            temps: vec![],
+            probs: vec![],
+            amts: vec![],
+        };
+
+        // Only called with non-empty lists, NaN values are ignored by min and max
+        let min_avg_max = |values: Vec<f64>| {
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            return (min, values.iter().sum::<f64>() / values.len() as f64, max);
+        };
+
+        for (_, readings) in days {
+            if readings.is_empty() {
+                data.temps.push(None);
+                data.probs.push(None);
+                data.amts.push(None);
+                continue;
+            }
+
+            data.temps.push(Some(min_avg_max(readings.iter().map(|p| p.temp).collect())));
+
+            let (min, avg, max) = min_avg_max(readings.iter().map(|p| (p.precip_probability * 100.).trunc()).collect());
+            data.probs.push(Some((min as usize, avg as usize, max as usize)));
+
+            data.amts.push(Some(min_avg_max(readings.iter().map(|p| p.precip_intensity).collect())));
+        }
+
+        return data;
+    }
+
+    fn temp_row(&self, data: &[Option<(f64, f64, f64)>], skip: usize, take: usize) -> Line<'static> {
+        row(
             "Temp    ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
-            |val| format!("{: <3.0}/{: <3.0}/{: <3.0}  ", val.0, val.1, val.2),
-            |_| Ok(()),
+            |val| format_or_blank(val, 13, |val| format!("{: <3.0}/{: <3.0}/{: <3.0}  ", val.0, val.1, val.2)),
+            |_| Style::new(),
         )
     }
 
-    fn print_prob_row(&self, data: &Vec<(usize, usize, usize)>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn prob_row(&self, data: &[Option<(usize, usize, usize)>], skip: usize, take: usize) -> Line<'static> {
+        row(
             "P. Prob ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
-            |val| format!("{: <3}/{: <3}/{: <3}  ", val.0, val.1, val.2),
-            |val| {
-                let ansi = match val.1 {
-                    90..=100 => 21,
-                    70..=89 => 20,
-                    50..=69 => 19,
-                    30..=49 => 18,
-                    _ => 16
-                };
-                stdout().execute(SetBackgroundColor(Color::AnsiValue(ansi)))?;
-                Ok(())
-            },
+            |val| format_or_blank(val, 13, |val| format!("{: <3}/{: <3}/{: <3}  ", val.0, val.1, val.2)),
+            |val| background(match val.map(|val| val.1) {
+                Some(90..=100) => 21,
+                Some(70..=89) => 20,
+                Some(50..=69) => 19,
+                Some(30..=49) => 18,
+                _ => 16
+            }),
         )
     }
 
-    fn print_amt_row(&self, data: &Vec<(f64, f64, f64)>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn amt_row(&self, data: &[Option<(f64, f64, f64)>], skip: usize, take: usize) -> Line<'static> {
+        row(
             "P. Amt  ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
-            |val| format!("{:.1}/{:.1}/{:.1}  ", val.0, val.1, val.2),
-            |val| {
-                let ansi = match val.1 {
-                    d if d > 3.0 => 21,
-                    1.0..=2.999 => 20,
-                    0.3..=0.999 => 18,
-                    _ => 16
-                };
-                stdout().execute(SetBackgroundColor(Color::AnsiValue(ansi)))?;
-                Ok(())
-            },
+            |val| format_or_blank(val, 13, |val| format!("{:.1}/{:.1}/{:.1}  ", val.0, val.1, val.2)),
+            |val| background(match val.map(|val| val.1) {
+                Some(d) if d > 3.0 => 21,
+                Some(1.0..=2.999) => 20,
+                Some(0.3..=0.999) => 18,
+                _ => 16
+            }),
         )
     }
+
+    /// Grid of the month in blocks of `BLOCK_DAYS` days
+    fn grid(&self, data: &MonthData) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::styled("(Min/Avg/Max)", Style::new().fg(Color::Gray))];
+        for skip in (0..data.temps.len()).step_by(BLOCK_DAYS) {
+            let take = BLOCK_DAYS.min(data.temps.len() - skip);
+            lines.push(Line::default());
+            lines.push(row_titles(TitlesOpt {
+                start: skip + 1,
+                end: skip + take,
+                initial_padding: 8,
+                between_padding: 11,
+                color: HEADER_COLOR
+            }));
+            lines.push(self.temp_row(&data.temps, skip, take));
+            lines.push(self.prob_row(&data.probs, skip, take));
+            lines.push(self.amt_row(&data.amts, skip, take));
+        }
+        return lines;
+    }
+
+    /// Charts `area_width` wide
+    fn charts(&self, data: &MonthData, area_width: u16) -> Vec<Line<'static>> {
+        let temp_series = |name: &str, color: Color, getter: fn(&(f64, f64, f64)) -> f64| Series {
+            name: String::from(name),
+            color,
+            values: data.temps.iter().map(|temp| temp.as_ref().map(getter)).collect(),
+        };
+        let x_labels = (0..data.temps.len())
+            .filter(|i| *i == 0 || (i + 1) % 5 == 0)
+            .map(|i| (i, (i + 1).to_string()))
+            .collect();
+        let width = plot_width(area_width, CHART_LABEL_WIDTH, data.temps.len());
+        let value_width = width / data.temps.len().max(1);
+
+        let mut lines = LineChart {
+            series: vec![
+                temp_series("Min", Color::Blue, |temp| temp.0),
+                temp_series("Avg", Color::White, |temp| temp.1),
+                temp_series("Max", Color::Red, |temp| temp.2),
+            ],
+            width,
+            height: 10,
+            label_width: CHART_LABEL_WIDTH,
+            x_labels,
+        }.lines("Temperature");
+
+        let avg_probs: Vec<Option<f64>> = data.probs.iter().map(|prob| prob.map(|prob| prob.1 as f64)).collect();
+        let max_amts: Vec<Option<f64>> = data.amts.iter().map(|amt| amt.map(|amt| amt.2)).collect();
+
+        lines.push(Line::default());
+        lines.push(sparkline_line("Avg Prob", CHART_LABEL_WIDTH, &avg_probs, 100., value_width, Color::Blue));
+        lines.push(sparkline_line("Max Amt", CHART_LABEL_WIDTH, &max_amts, 3., value_width, Color::Blue));
+
+        return lines;
+    }
 }
 
-impl UiSection for MonthView {
-    fn run(&mut self, app: &mut WeatherApp) -> Result<(), Error> {
-        self.reset(self.reset_pos)?;
-
-        self.reset_pos = crossterm::cursor::position()?;
-
-        let (first, last) = print_first_last_reading("View specific reading predictions\n", app)?;
-
-        let (year, month) = self.input_year_month()?;
-        let mut selected_date = NaiveDateTime::new(NaiveDate::from_ymd(year as i32, month as u32, 1), NaiveTime::from_hms(0,0,0));
-
-        loop {
-            if selected_date < first.date() || selected_date > last.date() {
-                print_styled("\n\nOutside of data range\n\n", Color::Red, false)?;
-
-                std::thread::sleep(Duration::from_millis(500));
-
-                self.wait_for_char("\n\nPress any key to continue\n")?;
-
-                return Ok(());
-            } else {
-                let days_in_month = days_in_month(selected_date.month() as u8, selected_date.year() as u32);
-
-                let start: SimpleDate = selected_date.into();
-                let end = selected_date
-                    .with_day(days_in_month as u32).expect("Invalid days_in_month")
-                    .with_hour(23).unwrap()
-                    .clone()
-                    .into();
-
-                self.reset(self.reset_pos)?;
-
-                stdout()
-                    .execute(Print("\nViewing  "))?;
-
-                print_styled(&format!("{}", selected_date.format("%Y %b")), Color::White, true)?;
-
-                stdout()
-                    .execute(Print("\n(Min/Avg/Max)"))?;
-
-                let readings = app.get_readings_over_range(start, end)?;
-
-                let mut daily_temps = vec![];
-                let mut daily_probs = vec![];
-                let mut daily_amts = vec![];
-
-                readings.iter().map(|p| p.temp)
-                    .collect::<Vec<f64>>()
-                    .chunks(24)
-                    .for_each(|temps| {
-                        daily_temps.push((
-                            min_f64(temps),
-                            avg_f64(temps),
-                            max_f64(temps)
-                            ));
-                    });
-
-                readings.iter().map(|p| (p.precip_probability * 100.) as usize)
-                    .collect::<Vec<usize>>()
-                    .chunks(24)
-                    .for_each(|probs| {
-                        daily_probs.push((
-                            min_usize(probs),
-                            avg_usize(probs),
-                            max_usize(probs)
-                        ));
-                    });
-
-                readings.iter().map(|p| p.precip_intensity)
-                    .collect::<Vec<f64>>()
-                    .chunks(24)
-                    .for_each(|amts| {
-                        daily_amts.push((
-                            min_f64(amts),
-                            avg_f64(amts),
-                            max_f64(amts)
-                        ));
-                    });
-
-                print_row_titles(TitlesOpt {
-                    start: 1,
-                    end: 11,
-                    newlines: 2,
-                    initial_padding: 8,
-                    between_padding: 11,
-                    color: HEADER_COLOR
-                })?;
-                self.print_temp_row(&daily_temps, 0, 11)?;
-                self.print_prob_row(&daily_probs, 0, 11)?;
-                self.print_amt_row(&daily_amts, 0, 11)?;
-
-                print_row_titles(TitlesOpt {
-                    start: 12,
-                    end: 22,
-                    newlines: 2,
-                    initial_padding: 8,
-                    between_padding: 11,
-                    color: HEADER_COLOR
-                })?;
-                self.print_temp_row(&daily_temps, 11, 11)?;
-                self.print_prob_row(&daily_probs, 11, 11)?;
-                self.print_amt_row(&daily_amts, 11, 11)?;
-
-                let count = (days_in_month - 22) as usize;
-                print_row_titles(TitlesOpt {
-                    start: 23,
-                    end: days_in_month as usize,
-                    newlines: 2,
-                    initial_padding: 8,
-                    between_padding: 11,
-                    color: HEADER_COLOR
-                })?;
-                self.print_temp_row(&daily_temps, 22, count)?;
-                self.print_prob_row(&daily_probs, 22, count)?;
-                self.print_amt_row(&daily_amts, 22, count)?;
-
-                print_styled("\n\n(▲) Previous month\n(▼) Next month\n(esc) Go back", Color::Grey, false)?;
-
-                loop {
-                    let char = self.wait_for_char_no_delay()?;
-
-                    match char {
-                        KeyCode::Esc => return Ok(()),
-                        KeyCode::Up => {
-                            selected_date = selected_date.minus_one_month();
-                            break;
-                        }
-                        KeyCode::Down => {
-                            selected_date = selected_date.plus_one_month();
-                            break;
-                        }
-                        _ => {}
-                    }
+impl Screen for MonthView {
+    fn title(&self) -> String {
+        return String::from("Reading for month");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        let view = if self.chart { "grid" } else { "chart" };
+        return vec![("←→", "date part"), ("↑↓", "change"), ("0-9", "type"), ("t", "local time/UTC"), ("g", view), ("PgUp/PgDn", "scroll"), ("Esc", "back")];
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let [picker_area, content_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+
+        frame.render_widget(Paragraph::new(vec![self.picker.line("Viewing", true), self.range.line()]), picker_area);
+
+        let (title, lines) = match &self.data {
+            Ok(data) => (data.calendar.clone(), if self.chart { self.charts(data, content_area.width) } else { self.grid(data) }),
+            Err(message) => (String::from("Readings"), error_lines(message))
+        };
+        self.pane.draw(frame, content_area, Text::from(lines), focus_block(&title, true));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        match key.code {
+            KeyCode::Esc => return Ok(Action::Pop),
+            KeyCode::Char('t') => {
+                app.set_local_time(!app.is_local_time());
+                self.load(app);
+            }
+            KeyCode::Char('g') => self.chart = !self.chart,
+            _ => {
+                if !self.pane.handle_key(key) && self.picker.handle_key(key) {
+                    self.load(app);
                 }
             }
         }
+
+        return Ok(Action::None);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,253 @@
+use crate::ui::screen::{Screen, Action, Task, TaskResult, task_output};
+use crate::ui::form::{Form, FormEvent, Focus, draw_with_results, RESULTS_PLACEHOLDER};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::text_input::{Prompt, PromptEvent};
+use crate::Error;
+use weather::app::WeatherApp;
+use weather::models::SimpleDate;
+use crate::ui::utils::{day_range_fields, rain_event_field, parse_rain_event, rain_event_text, form_day_range, range_text, viewing_line, save_csv};
+use weather::analysis::rain_events::{LeadTimeRainEvents, RainEventsCsv, probability_threshold, THRESHOLD_COUNT, DEFAULT_PROBABILITY_THRESHOLD};
+use weather::analysis::{RainEvent, MAX_HOUR_DIFF};
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
+
+const HEADER_COLOR: Color = Color::Cyan;
+const SELECTED_COLOR: Color = Color::Yellow;
+const ROC_WIDTH: usize = 40;
+const ROC_HEIGHT: usize = 10;
+
+fn format_score(score: Option<f64>) -> String {
+    return match score {
+        Some(score) => format!("{: <8.3}", score),
+        None => String::from("-       ")
+    };
+}
+
+/// Options and output of a calculation
+struct RainEventsResults {
+    start: SimpleDate,
+    end: SimpleDate,
+    event: RainEvent,
+    events: Vec<LeadTimeRainEvents>,
+}
+
+pub struct RainEventsView {
+    form: Form,
+    focus: Focus,
+    results: Option<RainEventsResults>,
+    hour_diff: u8,
+    /// Index of the selected probability threshold
+    selected: usize,
+    pane: ScrollPane,
+    /// Asking for a file to export to
+    prompt: Option<Prompt>,
+}
+
+impl RainEventsView {
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<RainEventsView, Error> {
+        let mut fields = day_range_fields(app)?;
+        fields.push(rain_event_field());
+
+        return Ok(RainEventsView {
+            form: Form::new(fields),
+            focus: Focus::Form,
+            results: None,
+            hour_diff: 1,
+            selected: (DEFAULT_PROBABILITY_THRESHOLD * (THRESHOLD_COUNT - 1) as f64).round() as usize,
+            pane: ScrollPane::default(),
+            prompt: None,
+        });
+    }
+}
+
+impl RainEventsView {
+    fn table_lines(&self, events: &LeadTimeRainEvents) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::styled(
+            format!("{: <11}{: <8}{: <8}{: <8}{: <8}{: <8}{: <8}{: <8}{: <8}", "Threshold", "Hits", "Misses", "F. Alm", "C. Neg", "POD", "FAR", "CSI", "ETS"),
+            Style::new().fg(HEADER_COLOR),
+        )];
+
+        lines.extend(events.tables.iter().enumerate().map(|(i, table)| {
+            let color = if i == self.selected { SELECTED_COLOR } else { Color::White };
+            Line::styled(format!(
+                "{: >4.0}%      {: <8}{: <8}{: <8}{: <8}{}{}{}{}",
+                probability_threshold(i) * 100.,
+                table.hits,
+                table.misses,
+                table.false_alarms,
+                table.correct_negatives,
+                format_score(table.pod()),
+                format_score(table.far()),
+                format_score(table.csi()),
+                format_score(table.ets())
+            ), Style::new().fg(color))
+        }));
+
+        return lines;
+    }
+
+    /// ROC curve, POD up and POFD across, the selected threshold is highlighted
+    ///
+    /// Points on the diagonal are no better than guessing, the closer to the top left the better
+    fn roc_lines(&self, events: &LeadTimeRainEvents) -> Vec<Line<'static>> {
+        let header = Style::new().fg(HEADER_COLOR);
+        let auc = match events.auc() {
+            Some(auc) => format!("{:.3}", auc),
+            None => String::from("- (needs both rainy and dry hours)")
+        };
+        let mut lines = vec![Line::styled(format!("ROC curve, area {}", auc), header)];
+
+        let cell = |(pofd, pod): (f64, f64)| ((pofd * ROC_WIDTH as f64).round() as usize, (pod * ROC_HEIGHT as f64).round() as usize);
+        let points: Vec<(usize, usize)> = events.roc().into_iter().map(cell).collect();
+        let selected_table = &events.tables[self.selected];
+        let selected_point = selected_table.pofd().zip(selected_table.pod()).map(cell);
+
+        for row in (0..=ROC_HEIGHT).rev() {
+            let label = match row {
+                ROC_HEIGHT => "POD 1 ",
+                0 => "    0 ",
+                _ => "      "
+            };
+
+            let line: String = (0..=ROC_WIDTH)
+                .map(|col| if points.contains(&(col, row)) {
+                    '●'
+                } else if col * ROC_HEIGHT == row * ROC_WIDTH {
+                    '·'
+                } else {
+                    ' '
+                })
+                .collect();
+
+            let mut spans = vec![Span::styled(format!("{}|", label), header)];
+            match selected_point.filter(|(_, selected_row)| *selected_row == row) {
+                Some((col, _)) => {
+                    spans.push(Span::raw(line.chars().take(col).collect::<String>()));
+                    spans.push(Span::styled("◆", Style::new().fg(SELECTED_COLOR)));
+                    spans.push(Span::raw(line.chars().skip(col + 1).collect::<String>()));
+                }
+                None => spans.push(Span::raw(line))
+            }
+            lines.push(Line::from(spans));
+        }
+
+        lines.push(Line::styled(format!("      +{}", "-".repeat(ROC_WIDTH + 1)), header));
+        lines.push(Line::styled(format!("       0{: >width$}", "1 POFD", width = ROC_WIDTH + 1), header));
+
+        return lines;
+    }
+
+    fn result_lines(&self) -> Vec<Line<'static>> {
+        let results = match &self.results {
+            Some(results) => results,
+            None => return vec![Line::styled(RESULTS_PLACEHOLDER, Style::new().fg(Color::Gray))]
+        };
+
+        let mut lines = vec![
+            viewing_line(format!("{}, {} hours before", range_text(results.start, results.end), self.hour_diff)),
+            Line::raw(format!("{}, predicted when probability is at least the threshold", rain_event_text(results.event))),
+            Line::default(),
+        ];
+
+        match results.events.iter().find(|events| events.hour_diff == self.hour_diff) {
+            Some(events) => {
+                lines.push(Line::raw(format!("Count {}", events.count)));
+                lines.push(Line::default());
+                lines.extend(self.table_lines(events));
+                lines.push(Line::default());
+                lines.extend(self.roc_lines(events));
+            }
+            None => lines.push(Line::styled("No predictions with readings for this lead time", Style::new().fg(Color::Red)))
+        }
+
+        return lines;
+    }
+
+    fn calculate(&self, app: &mut WeatherApp) -> Result<Action, Error> {
+        let (start, end) = form_day_range(app, &self.form)?;
+        let event = parse_rain_event(self.form.text("Rain"))?;
+        return Ok(Action::Run(Task::new("Calculating rain detection", move |app, _| {
+            return Ok(RainEventsResults {
+                start,
+                end,
+                event,
+                events: app.get_rain_events(start, end, event)?,
+            });
+        })));
+    }
+}
+
+impl Screen for RainEventsView {
+    fn title(&self) -> String {
+        return String::from("Rain detection and ROC curve");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return match self.focus {
+            Focus::Form => vec![("Tab", "next field"), ("Rain", "mm/h or 'type'"), ("Enter", "calculate"), ("Esc", "back")],
+            Focus::Results => vec![("←→", "lead time"), ("↑↓", "threshold"), ("e", "export CSV"), ("PgUp/PgDn", "scroll"), ("Tab", "options"), ("Esc", "back")],
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let lines = self.result_lines();
+        draw_with_results(frame, area, &self.form, self.focus, &mut self.pane, Text::from(lines));
+
+        if let Some(prompt) = &self.prompt {
+            prompt.draw(frame, area);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        if let Some(prompt) = &mut self.prompt {
+            return match prompt.handle_key(key) {
+                PromptEvent::None => Ok(Action::None),
+                PromptEvent::Cancel => {
+                    self.prompt = None;
+                    Ok(Action::None)
+                }
+                PromptEvent::Submit(path) => {
+                    self.prompt = None;
+                    let events = self.results.as_ref().map(|results| results.events.as_slice()).unwrap_or_default();
+                    save_csv(&path, |out| RainEventsCsv(events).write(out))
+                }
+            };
+        }
+
+        match self.focus {
+            Focus::Form => match self.form.handle_key(key) {
+                FormEvent::Submit => return self.calculate(app),
+                FormEvent::Leave => self.focus = Focus::Results,
+                FormEvent::Cancel => return Ok(Action::Pop),
+                FormEvent::None => {}
+            },
+            Focus::Results => match key.code {
+                KeyCode::Esc => return Ok(Action::Pop),
+                _ if self.form.enter(key) => self.focus = Focus::Form,
+                _ if self.pane.handle_key(key) => {}
+                KeyCode::Left => self.hour_diff = self.hour_diff.saturating_sub(1).max(1),
+                KeyCode::Right => self.hour_diff = (self.hour_diff + 1).min(MAX_HOUR_DIFF),
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => self.selected = (self.selected + 1).min(THRESHOLD_COUNT - 1),
+                KeyCode::Char('e') if self.results.is_some() => self.prompt = Some(Prompt::new("Enter file to save CSV to")),
+                _ => {}
+            }
+        }
+
+        return Ok(Action::None);
+    }
+
+    fn task_finished(&mut self, result: TaskResult) -> Result<Action, Error> {
+        self.results = Some(task_output(result)?);
+        self.focus = Focus::Results;
+
+        return Ok(Action::None);
+    }
+}
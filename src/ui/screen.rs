@@ -0,0 +1,92 @@
+use crate::Error;
+use crate::ui::utils::error_message;
+use weather::app::WeatherApp;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::KeyEvent;
+use std::any::Any;
+
+/// Result of a `Task`, errors are converted to text as they have to be sent between threads
+pub type TaskResult = Result<Box<dyn Any + Send>, String>;
+
+/// Work done by a `Task`, given the app and a callback to report progress
+pub type TaskWork = Box<dyn FnOnce(&mut WeatherApp, &mut dyn FnMut(String)) -> TaskResult + Send>;
+
+/// Work that is too slow to do between frames (e.g. importing), it's run on another thread with the app
+///
+/// The work is given a callback to report progress, the latest progress is shown in the status bar
+pub struct Task {
+    /// Shown in the status bar while running
+    pub name: String,
+    pub work: TaskWork,
+}
+
+impl Task {
+    /// Task that runs `work`, its output is given to `Screen::task_finished` (use `task_output` to get it back)
+    pub fn new<T, F>(name: &str, work: F) -> Task where
+        T: Any + Send,
+        F: FnOnce(&mut WeatherApp, &mut dyn FnMut(String)) -> Result<T, Error> + Send + 'static
+    {
+        return Task {
+            name: name.to_owned(),
+            work: Box::new(move |app, progress| work(app, progress)
+                .map(|output| Box::new(output) as Box<dyn Any + Send>)
+                .map_err(|err| error_message(err.as_ref()))),
+        };
+    }
+}
+
+/// Output of a task made by `Task::new`
+///
+/// # Errors
+/// The task failed
+/// The task had a different output type
+///
+pub fn task_output<T: Any>(result: TaskResult) -> Result<T, Error> {
+    let output = result?.downcast::<T>().map_err(|_| "Unexpected task output")?;
+    return Ok(*output);
+}
+
+/// What the event loop should do after a screen handles a key
+pub enum Action {
+    /// Nothing other than redraw
+    None,
+    /// Open a screen on top of this one
+    Push(Box<dyn Screen>),
+    /// Close this screen and go back to the one underneath
+    Pop,
+    /// Close every screen and exit
+    Quit,
+    /// Show a message in the status bar until the next key press
+    Info(String),
+    /// Run work in the background, the result is sent to `Screen::task_finished`
+    Run(Task),
+}
+
+/// A full screen view, drawn between the title bar and status bar
+///
+/// Screens keep everything they need to draw, the app is only available when handling keys,
+/// so any data should be loaded when the screen is made or when a key changes what is shown
+pub trait Screen {
+    /// Shown in the title bar
+    fn title(&self) -> String;
+
+    /// Keys shown in the status bar as (key, description)
+    fn hints(&self) -> Vec<(&str, &str)>;
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect);
+
+    /// # Errors
+    /// Any error is shown in the status bar and the screen stays open
+    ///
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error>;
+
+    /// Called when a task started by this screen has finished
+    ///
+    /// # Errors
+    /// Any error is shown in the status bar and the screen stays open
+    ///
+    fn task_finished(&mut self, _result: TaskResult) -> Result<Action, Error> {
+        return Ok(Action::None);
+    }
+}
@@ -0,0 +1,67 @@
+use ratatui::Frame;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Rect, Margin};
+use ratatui::text::Text;
+use ratatui::widgets::{Block, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState};
+
+/// Columns moved by each horizontal scroll
+const COLUMN_STEP: u16 = 6;
+
+/// Scroll position of text bigger than its area
+///
+/// PgUp/PgDn and Home/End move up and down, Shift+arrows move one line (or a few columns) at a time
+#[derive(Default)]
+pub struct ScrollPane {
+    x: u16,
+    y: u16,
+    /// Lines visible when last drawn, how far a page moves
+    height: u16,
+}
+
+impl ScrollPane {
+    /// # Returns
+    /// true if the key was a scroll key
+    ///
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        match key.code {
+            KeyCode::PageUp => self.y = self.y.saturating_sub(self.height.max(1)),
+            KeyCode::PageDown => self.y = self.y.saturating_add(self.height.max(1)),
+            KeyCode::Home => {
+                self.x = 0;
+                self.y = 0;
+            }
+            KeyCode::End => self.y = u16::MAX,
+            KeyCode::Up if shift => self.y = self.y.saturating_sub(1),
+            KeyCode::Down if shift => self.y = self.y.saturating_add(1),
+            KeyCode::Left if shift => self.x = self.x.saturating_sub(COLUMN_STEP),
+            KeyCode::Right if shift => self.x = self.x.saturating_add(COLUMN_STEP),
+            _ => return false
+        }
+        return true;
+    }
+
+    /// Render `text` inside `block` at the scroll position, clamped so the end of the text stays at the edge of the area
+    ///
+    /// Scrollbars are shown on the edges of the block if the text doesn't fit
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, text: Text, block: Block) {
+        let inner = block.inner(area);
+        let (width, height) = (text.width() as u16, text.height() as u16);
+        let max_x = width.saturating_sub(inner.width);
+        let max_y = height.saturating_sub(inner.height);
+        self.x = self.x.min(max_x);
+        self.y = self.y.min(max_y);
+        self.height = inner.height;
+
+        frame.render_widget(Paragraph::new(text).block(block).scroll((self.y, self.x)), area);
+
+        if max_y > 0 {
+            let mut state = ScrollbarState::new(max_y as usize).position(self.y as usize);
+            frame.render_stateful_widget(Scrollbar::new(ScrollbarOrientation::VerticalRight), area.inner(Margin { horizontal: 0, vertical: 1 }), &mut state);
+        }
+        if max_x > 0 {
+            let mut state = ScrollbarState::new(max_x as usize).position(self.x as usize);
+            frame.render_stateful_widget(Scrollbar::new(ScrollbarOrientation::HorizontalBottom).thumb_symbol("■"), area.inner(Margin { horizontal: 1, vertical: 0 }), &mut state);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::ui::screen::{Screen, Action, Task, TaskResult, task_output};
+use crate::ui::form::{Form, FormEvent, Focus, draw_with_results, RESULTS_PLACEHOLDER};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::Error;
+use weather::app::WeatherApp;
+use weather::models::SimpleDate;
+use crate::ui::utils::{row, background, row_titles, TitlesOpt, day_range_fields, form_day_range, range_text, viewing_line};
+use weather::analysis::skill::{LeadTimeSkill, VariableSkill, Skill, Baseline};
+use weather::analysis::MAX_HOUR_DIFF;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Text};
+
+const HEADER_COLOR: Color = Color::Cyan;
+
+/// Lowest score shown, anything below is much worse than the baseline
+const MIN_SCORE: f64 = -9.99;
+
+fn baseline_name(baseline: Baseline) -> &'static str {
+    return match baseline {
+        Baseline::Persistence => "Skill against persistence (reading when predicted)",
+        Baseline::Climatology => "Skill against climatology (mean of previous years)",
+    };
+}
+
+pub struct SkillView {
+    form: Form,
+    focus: Focus,
+    /// Start, end and scores of the last calculation
+    results: Option<(SimpleDate, SimpleDate, Vec<LeadTimeSkill>)>,
+    baseline: Baseline,
+    pane: ScrollPane,
+}
+
+impl SkillView {
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<SkillView, Error> {
+        return Ok(SkillView {
+            form: Form::new(day_range_fields(app)?),
+            focus: Focus::Form,
+            results: None,
+            baseline: Baseline::Persistence,
+            pane: ScrollPane::default(),
+        });
+    }
+}
+
+impl SkillView {
+    fn count_row(&self, data: &[Option<&LeadTimeSkill>]) -> Line<'static> {
+        let baseline = self.baseline;
+        row(
+            "Count   ",
+            HEADER_COLOR,
+            data.iter().map(|skill| skill.map(|skill| skill.against(baseline).temp.count)).collect(),
+            |val| match val {
+                Some(count) => format!("{: <5} ", count),
+                None => String::from("-     ")
+            },
+            |_| Style::new(),
+        )
+    }
+
+    /// Row of skill scores for one variable, green is better than the baseline and red is worse
+    fn score_row<F>(&self, title: &str, data: &[Option<&LeadTimeSkill>], getter: F) -> Line<'static> where
+        F: Fn(&VariableSkill) -> Skill
+    {
+        let baseline = self.baseline;
+        row(
+            title,
+            HEADER_COLOR,
+            data.iter().map(|skill| skill.and_then(|skill| getter(skill.against(baseline)).score())).collect(),
+            |val| match val {
+                Some(score) => format!("{: <+5.2} ", score.max(MIN_SCORE)),
+                None => String::from("-     ")
+            },
+            |val| background(match val {
+                Some(score) if *score >= 0.5 => 28,
+                Some(score) if *score >= 0.2 => 22,
+                Some(score) if *score < 0. => 88,
+                _ => 16
+            }),
+        )
+    }
+
+    fn block(&self, data: &[Option<&LeadTimeSkill>], start: usize) -> Vec<Line<'static>> {
+        return vec![
+            Line::default(),
+            row_titles(TitlesOpt {
+                start,
+                end: start + data.len() - 1,
+                initial_padding: 8,
+                between_padding: 4,
+                color: HEADER_COLOR
+            }),
+            self.count_row(data),
+            self.score_row("Temp    ", data, |skill| skill.temp),
+            self.score_row("Wnd Spd ", data, |skill| skill.wind_speed),
+            self.score_row("Wnd Gst ", data, |skill| skill.wind_gust),
+            self.score_row("Humid   ", data, |skill| skill.humidity),
+            self.score_row("P. Amt  ", data, |skill| skill.precip_intensity),
+        ];
+    }
+
+    fn result_lines(&self) -> Vec<Line<'static>> {
+        let (start, end, results) = match &self.results {
+            Some(results) => results,
+            None => return vec![Line::styled(RESULTS_PLACEHOLDER, Style::new().fg(Color::Gray))]
+        };
+
+        let mut lines = vec![
+            viewing_line(range_text(*start, *end)),
+            Line::raw(format!("{} by hours before (1 is perfect, 0 is no better, below 0 is worse)", baseline_name(self.baseline))),
+        ];
+
+        if results.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::styled("No predictions with readings in range", Style::new().fg(Color::Red)));
+            return lines;
+        }
+
+        let mut slots: Vec<Option<&LeadTimeSkill>> = vec![None; MAX_HOUR_DIFF as usize];
+        results.iter()
+            .for_each(|skill| slots[skill.hour_diff as usize - 1] = Some(skill));
+
+        lines.extend(self.block(&slots[0..24], 1));
+        lines.extend(self.block(&slots[24..MAX_HOUR_DIFF as usize], 25));
+
+        return lines;
+    }
+
+    fn calculate(&self, app: &mut WeatherApp) -> Result<Action, Error> {
+        let (start, end) = form_day_range(app, &self.form)?;
+        return Ok(Action::Run(Task::new("Calculating skill", move |app, _| {
+            return Ok((start, end, app.get_skill_scores(start, end)?));
+        })));
+    }
+}
+
+impl Screen for SkillView {
+    fn title(&self) -> String {
+        return String::from("Skill against persistence and climatology");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return match self.focus {
+            Focus::Form => vec![("Tab", "next field"), ("←→", "date part"), ("↑↓", "change"), ("Enter", "calculate"), ("Esc", "back")],
+            Focus::Results => vec![("1", "Persistence"), ("2", "Climatology"), ("PgUp/PgDn", "scroll"), ("Tab", "options"), ("Esc", "back")],
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let lines = self.result_lines();
+        draw_with_results(frame, area, &self.form, self.focus, &mut self.pane, Text::from(lines));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        match self.focus {
+            Focus::Form => match self.form.handle_key(key) {
+                FormEvent::Submit => return self.calculate(app),
+                FormEvent::Leave => self.focus = Focus::Results,
+                FormEvent::Cancel => return Ok(Action::Pop),
+                FormEvent::None => {}
+            },
+            Focus::Results => match key.code {
+                KeyCode::Esc => return Ok(Action::Pop),
+                _ if self.form.enter(key) => self.focus = Focus::Form,
+                KeyCode::Char('1') => self.baseline = Baseline::Persistence,
+                KeyCode::Char('2') => self.baseline = Baseline::Climatology,
+                _ => {
+                    self.pane.handle_key(key);
+                }
+            }
+        }
+
+        return Ok(Action::None);
+    }
+
+    fn task_finished(&mut self, result: TaskResult) -> Result<Action, Error> {
+        self.results = Some(task_output(result)?);
+        self.focus = Focus::Results;
+
+        return Ok(Action::None);
+    }
+}
@@ -0,0 +1,126 @@
+use ratatui::Frame;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::{Rect, Layout, Constraint, Flex};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph};
+
+const LABEL_COLOR: Color = Color::Cyan;
+const FOCUSED_COLOR: Color = Color::Yellow;
+
+/// Single line of editable text
+#[derive(Default)]
+pub struct TextInput {
+    value: String,
+    /// Position of the cursor in chars
+    cursor: usize,
+}
+
+impl TextInput {
+    pub fn new(value: &str) -> TextInput {
+        return TextInput {
+            value: value.to_owned(),
+            cursor: value.chars().count(),
+        };
+    }
+
+    pub fn value(&self) -> &str {
+        return &self.value;
+    }
+
+    /// # Returns
+    /// true if the value changed
+    ///
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let byte_index = |text: &str, cursor: usize| text.char_indices().nth(cursor).map_or(text.len(), |(index, _)| index);
+        match key.code {
+            KeyCode::Char(chr) => {
+                self.value.insert(byte_index(&self.value, self.cursor), chr);
+                self.cursor += 1;
+                return true;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.value.remove(byte_index(&self.value, self.cursor));
+                return true;
+            }
+            KeyCode::Delete if self.cursor < self.value.chars().count() => {
+                self.value.remove(byte_index(&self.value, self.cursor));
+                return true;
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.value.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.chars().count(),
+            _ => {}
+        }
+        return false;
+    }
+
+    /// Text with the cursor shown if `focused`
+    pub fn spans(&self, focused: bool) -> Vec<Span<'static>> {
+        if !focused {
+            return vec![Span::raw(self.value.clone())];
+        }
+        let before: String = self.value.chars().take(self.cursor).collect();
+        let at: String = self.value.chars().nth(self.cursor).map_or_else(|| String::from(" "), String::from);
+        let after: String = self.value.chars().skip(self.cursor + 1).collect();
+        return vec![
+            Span::raw(before),
+            Span::styled(at, Style::new().fg(Color::Black).bg(FOCUSED_COLOR)),
+            Span::raw(after),
+        ];
+    }
+
+    pub fn line(&self, label: &str, focused: bool) -> Line<'static> {
+        let label_style = if focused { Style::new().fg(FOCUSED_COLOR) } else { Style::new().fg(LABEL_COLOR) };
+        let mut spans = vec![Span::styled(format!("{: <14}", label), label_style)];
+        spans.extend(self.spans(focused));
+        return Line::from(spans);
+    }
+}
+
+pub enum PromptEvent {
+    None,
+    Cancel,
+    Submit(String),
+}
+
+/// Popup asking for a line of text (e.g. a file path), drawn over the screen
+pub struct Prompt {
+    title: String,
+    input: TextInput,
+}
+
+impl Prompt {
+    pub fn new(title: &str) -> Prompt {
+        return Prompt {
+            title: title.to_owned(),
+            input: TextInput::default(),
+        };
+    }
+
+    /// Enter submits (if anything has been entered) and Esc cancels
+    pub fn handle_key(&mut self, key: KeyEvent) -> PromptEvent {
+        return match key.code {
+            KeyCode::Esc => PromptEvent::Cancel,
+            KeyCode::Enter if !self.input.value().trim().is_empty() => PromptEvent::Submit(self.input.value().trim().to_owned()),
+            _ => {
+                self.input.handle_key(key);
+                PromptEvent::None
+            }
+        };
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let [popup] = Layout::vertical([Constraint::Length(3)]).flex(Flex::Center).areas(area);
+        let [popup] = Layout::horizontal([Constraint::Percentage(60)]).flex(Flex::Center).areas(popup);
+
+        let block = Block::bordered()
+            .title(format!(" {} ", self.title))
+            .title_bottom(" Enter save  Esc cancel ")
+            .border_style(Style::new().fg(FOCUSED_COLOR));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(Paragraph::new(Line::from(self.input.spans(true))).block(block), popup);
+    }
+}
@@ -1,89 +1,224 @@
-use crossterm::style::{Color, SetAttribute, SetForegroundColor, Print, Attribute, SetBackgroundColor};
-use std::io::stdout;
 use crate::Error;
-use crossterm::ExecutableCommand;
-use crate::models::Weather;
-use crate::app::WeatherApp;
-use std::time::Duration;
-use crate::extensions::MapToUnit;
-use std::any::Any;
-
-pub fn print_styled(msg: &str, color: Color, bold: bool) -> Result<(), Error> {
-    if bold {
-        stdout().execute(SetAttribute(Attribute::Bold))?;
-    }
-    stdout().execute(SetForegroundColor(color))?
-        .execute(Print(msg))?
-        .execute(SetAttribute(Attribute::NormalIntensity))?
-        .execute(SetForegroundColor(Color::White))?;
-
-    Ok(())
-}
+use crate::ui::screen::Action;
+use crate::ui::form::{Form, Field};
+use crate::ui::date_picker::{DatePicker, Precision};
+use crate::ui::text_input::TextInput;
+use weather::models::{Weather, SimpleDate};
+use weather::app::WeatherApp;
+use weather::analysis::{RainEvent, DEFAULT_RAIN_THRESHOLD};
+use ratatui::style::{Color, Style, Modifier};
+use ratatui::text::{Line, Span};
+use chrono::NaiveDateTime;
+use std::num::{ParseIntError, ParseFloatError};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufWriter;
 
-pub fn print_styled_list<D, F, S>(data: Vec<D>, formatter: F, styler: S) -> Result<(), Error> where
-    D: Any,
+/// Row of cells after a title, `styler` gives the style of each cell
+pub fn row<D, F, S>(title: &str, header_color: Color, data: Vec<D>, formatter: F, styler: S) -> Line<'static> where
     F: Fn(D) -> String,
-    S: Fn(&D) -> Result<(), Error>
+    S: Fn(&D) -> Style
 {
-    data.into_iter()
-        .try_for_each(|item| {
-            styler(&item)?;
-            stdout()
-                .execute(Print(formatter(item)))?
-                .execute(SetAttribute(Attribute::NormalIntensity))?
-                .execute(SetForegroundColor(Color::White))?
-                .execute(SetBackgroundColor(Color::Black))
-                .map_err(|e| Error::from(e))
-                .map_to_unit()
-        })
+    let mut spans = vec![Span::styled(title.to_owned(), Style::new().fg(header_color))];
+    spans.extend(data.into_iter()
+        .map(|item| {
+            let style = styler(&item);
+            Span::styled(formatter(item), style)
+        }));
+    return Line::from(spans);
 }
 
-pub fn print_first_last_reading(msg: &str, app: &mut WeatherApp) -> Result<(Weather, Weather), Error> {
-    let first = app.get_first_reading()?;
-    let last = app.get_last_reading()?;
+/// Style with the background set to an ANSI color, used to shade cells
+pub fn background(ansi: u8) -> Style {
+    return Style::new().bg(Color::Indexed(ansi));
+}
 
-    print_styled(msg, Color::Cyan, false)?;
+/// Message to show for `err`, with a hint on how to fix it where possible
+pub fn error_message(err: &(dyn std::error::Error + 'static)) -> String {
+    if err.is::<ParseIntError>() || err.is::<ParseFloatError>() {
+        return String::from("Not a number, enter digits only");
+    }
+    return match err.downcast_ref::<weather::Error>() {
+        Some(weather::Error::EmptyDatabase) => String::from("There are no readings for this location yet\nUse 'Import data' from the main menu to add some"),
+        Some(weather::Error::MissingReading { year, day, hour }) => format!("There is no reading for {} day {} hour {}\nUse 'Check for missing records' to see which hours are missing", year, day, hour),
+        Some(err @ weather::Error::Parse { .. }) => format!("{}\nCheck the file is json from a supported provider", err),
+        Some(weather::Error::SchemaVersion { found, supported }) => format!("This database was made by a newer version of this program (version {}, this program supports up to {})\nPlease update", found, supported),
+        Some(weather::Error::Io(err)) => format!("Unable to read or write file: {}", err),
+        _ => err.to_string()
+    };
+}
+
+/// First and last reading for the current location, screens load this when opened to pick starting dates
+pub struct ReadingRange {
+    pub first: Weather,
+    pub last: Weather,
+}
 
-    stdout()
-        .execute(Print(format!("Earliest: {} {: >3} {: >2}\n", first.year, first.day, first.hour)))?
-        .execute(Print(format!("Latest:   {} {: >3} {: >2}\n", last.year, last.day, last.hour)))?;
+impl ReadingRange {
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn load(app: &mut WeatherApp) -> Result<ReadingRange, Error> {
+        return Ok(ReadingRange {
+            first: app.get_first_reading()?,
+            last: app.get_last_reading()?,
+        });
+    }
 
-    std::thread::sleep(Duration::from_millis(300));
+    /// If there is a reading for the hour of `date` or on either side of it
+    pub fn contains(&self, date: NaiveDateTime) -> bool {
+        return date >= self.first.date() && date <= self.last.date();
+    }
 
-    return Ok((first, last));
+    pub fn line(&self) -> Line<'static> {
+        return Line::styled(
+            format!("Readings from {} {: >3} {: >2} to {} {: >3} {: >2}", self.first.year, self.first.day, self.first.hour, self.last.year, self.last.day, self.last.hour),
+            Style::new().fg(Color::Gray),
+        );
+    }
 }
 
 #[derive(Debug)]
 pub struct TitlesOpt {
     pub start: usize,
     pub end: usize,
-    pub newlines: usize,
     pub initial_padding: usize,
     pub between_padding: usize,
     pub color: Color
 }
 
-pub fn print_row_titles(opts: TitlesOpt) -> Result<(), Error> {
-    let text = (opts.start..=opts.end).map(|num| format!("{: <2}{: <2$}", num, "", opts.between_padding)).collect::<Vec<String>>().join("");
-    stdout()
-        .execute(SetForegroundColor(opts.color))?;
+pub fn row_titles(opts: TitlesOpt) -> Line<'static> {
+    let titles: Vec<usize> = (opts.start..=opts.end).collect();
+    return titles_line(&titles, opts.initial_padding, opts.between_padding, opts.color);
+}
+
+/// Row of column titles, each is two characters wide followed by `between_padding` spaces
+pub fn titles_line<T: Display>(titles: &[T], initial_padding: usize, between_padding: usize, color: Color) -> Line<'static> {
+    let text = titles.iter().map(|title| format!("{: <2}{: <2$}", title, "", between_padding)).collect::<Vec<String>>().join("");
+    return Line::styled(format!("{: <1$}{2}", "", initial_padding, text), Style::new().fg(color));
+}
+
+/// `formatter(val)`, or `-` padded to `width` if there is no value
+pub fn format_or_blank<T, F: Fn(T) -> String>(val: Option<T>, width: usize, formatter: F) -> String {
+    return match val {
+        Some(val) => formatter(val),
+        None => format!("{: <1$}", "-", width)
+    };
+}
+
+/// Lines for an error shown in place of a screen's content
+pub fn error_lines(message: &str) -> Vec<Line<'static>> {
+    return message.lines().map(|line| Line::styled(line.to_owned(), Style::new().fg(Color::Red))).collect();
+}
 
-    for _ in 0..opts.newlines {
-        stdout().execute(Print("\n"))?;
+/// What counts as rain in an hour, a precipitation threshold in mm/h or `type` for any precipitation type
+///
+/// # Errors
+/// Not a valid number
+///
+pub fn parse_rain_event(input: &str) -> Result<RainEvent, Error> {
+    let input = input.trim();
+    if input == "type" {
+        return Ok(RainEvent::PrecipType);
     }
+    return Ok(RainEvent::Intensity(input.parse()?));
+}
 
-    stdout()
-        .execute(Print(format!("{: <1$}", "", opts.initial_padding)))?
-        .execute(Print(format!("{}", text)))?
-        .execute(SetForegroundColor(Color::White))?;
+/// Comma separated globs, commas inside `{}` alternatives don't separate
+pub fn parse_globs(input: &str) -> Vec<String> {
+    let mut globs = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    for chr in input.chars() {
+        match chr {
+            '{' => depth += 1,
+            '}' => depth = (depth - 1).max(0),
+            ',' if depth == 0 => {
+                globs.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(chr);
+    }
+    globs.push(current);
+    return globs.into_iter()
+        .map(|glob| glob.trim().to_owned())
+        .filter(|glob| !glob.is_empty())
+        .collect();
+}
+
+/// `Rain` field for `parse_rain_event`, starting at the default threshold
+pub fn rain_event_field() -> (&'static str, Field) {
+    return ("Rain", Field::Text(TextInput::new(&DEFAULT_RAIN_THRESHOLD.to_string())));
+}
+
+/// Description of `event` for result headings
+pub fn rain_event_text(event: RainEvent) -> String {
+    return match event {
+        RainEvent::Intensity(threshold) => format!("Rain is precipitation above {} mm/h", threshold),
+        RainEvent::PrecipType => String::from("Rain is any precipitation type"),
+    };
+}
+
+/// `From` and `To` day fields, starting at the days of the first and last reading in the app's calendar
+///
+/// # Errors
+/// No readings for the location
+/// Database errors
+///
+pub fn day_range_fields(app: &mut WeatherApp) -> Result<Vec<(&'static str, Field)>, Error> {
+    let range = ReadingRange::load(app)?;
+    let calendar = app.calendar()?;
+    let day = |timestamp: i64| Field::Date(DatePicker::new(calendar.date_of(timestamp).and_hms_opt(0, 0, 0).expect("Midnight is valid"), Precision::Day));
+    return Ok(vec![("From", day(range.first.timestamp)), ("To", day(range.last.timestamp))]);
+}
 
-    Ok(())
+/// Start (first hour of the `From` day) and end (last hour of the `To` day) of a form, in the app's calendar (see `WeatherApp::calendar`)
+///
+/// # Errors
+/// Database errors
+///
+pub fn form_day_range(app: &mut WeatherApp, form: &Form) -> Result<(SimpleDate, SimpleDate), Error> {
+    return Ok(app.day_range(form.date("From").date(), form.date("To").date())?);
 }
 
-pub fn consume_all_input() -> Result<(), Error> {
-    let duration = Duration::from_millis(10);
-    while crossterm::event::poll(duration)? {
-        crossterm::event::read()?;
+/// Text for a day range, as shown at the top of results
+pub fn range_text(start: SimpleDate, end: SimpleDate) -> String {
+    return format!("{} {: >3} to {} {: >3}", start.year, start.day, end.year, end.day);
+}
+
+/// First line of results, what they are for
+pub fn viewing_line(text: String) -> Line<'static> {
+    return Line::from(vec![
+        Span::raw("Viewing  "),
+        Span::styled(text, Style::new().add_modifier(Modifier::BOLD)),
+    ]);
+}
+
+/// Create `path` and pass it to `write`
+///
+/// # Errors
+/// Unable to create or write the file
+///
+/// # Returns
+/// Message confirming where it was saved
+///
+pub fn save_csv<F: FnOnce(BufWriter<File>) -> Result<(), weather::Error>>(path: &str, write: F) -> Result<Action, Error> {
+    let file = File::create(path).map_err(|err| format!("Failed to save: {}", err))?;
+    write(BufWriter::new(file)).map_err(|err| format!("Failed to save: {}", err))?;
+    return Ok(Action::Info(format!("Saved to {}", path)));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_globs() {
+        assert_eq!(parse_globs("2019/**, *.zip ,"), vec!["2019/**", "*.zip"]);
+        assert_eq!(parse_globs("{2019,2020}/*.json,old/*"), vec!["{2019,2020}/*.json", "old/*"], "commas in alternatives are kept");
+        assert!(parse_globs("  ").is_empty());
     }
-    Ok(())
-}
\ No newline at end of file
+}
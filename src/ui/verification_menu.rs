@@ -0,0 +1,63 @@
+use crate::ui::screen::{Screen, Action};
+use crate::ui::menu::Menu;
+use weather::app::WeatherApp;
+use crate::Error;
+use crate::ui::forecast_errors::ForecastErrors;
+use crate::ui::calibration_view::CalibrationView;
+use crate::ui::confusion_view::ConfusionView;
+use crate::ui::skill_view::SkillView;
+use crate::ui::rain_events_view::RainEventsView;
+use crate::ui::convergence_view::ConvergenceView;
+use ratatui::Frame;
+use ratatui::layout::Rect;
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+pub struct VerificationMenu {
+    menu: Menu,
+}
+
+impl VerificationMenu {
+    pub fn new() -> VerificationMenu {
+        let options = vec![
+            "Errors by lead time",
+            "Precipitation probability calibration",
+            "Predicted against observed icons",
+            "Skill against persistence and climatology",
+            "Rain detection and ROC curve",
+            "Forecast convergence for an hour",
+        ];
+        return VerificationMenu {
+            menu: Menu::new(options.into_iter().map(String::from).collect()),
+        };
+    }
+}
+
+impl Screen for VerificationMenu {
+    fn title(&self) -> String {
+        return String::from("Prediction verification");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return vec![("↑↓", "select"), ("Enter/1-6", "open"), ("Esc", "back")];
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        self.menu.draw(frame, area, "");
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        if key.code == KeyCode::Esc {
+            return Ok(Action::Pop);
+        }
+
+        return Ok(match self.menu.handle_key(key) {
+            Some(0) => Action::Push(Box::new(ForecastErrors::new(app)?)),
+            Some(1) => Action::Push(Box::new(CalibrationView::new(app)?)),
+            Some(2) => Action::Push(Box::new(ConfusionView::new(app)?)),
+            Some(3) => Action::Push(Box::new(SkillView::new(app)?)),
+            Some(4) => Action::Push(Box::new(RainEventsView::new(app)?)),
+            Some(5) => Action::Push(Box::new(ConvergenceView::new(app)?)),
+            _ => Action::None
+        });
+    }
+}
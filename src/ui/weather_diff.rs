@@ -1,95 +1,240 @@
-use crate::ui::ui_section::UiSection;
+use crate::ui::screen::{Screen, Action};
+use crate::ui::date_picker::{DatePicker, Precision};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::form::focus_block;
 use crate::Error;
-use crate::app::WeatherApp;
-use chrono::NaiveDateTime;
-use crate::extensions::Utils;
-use crate::ui::utils::{print_styled, print_first_last_reading};
-use crossterm::style::{Color, Print};
-use std::io::stdout;
-use crossterm::ExecutableCommand;
-use std::convert::TryInto;
-use std::time::Duration;
-use crossterm::event::KeyCode;
-use crate::models::SimpleDate;
+use weather::app::WeatherApp;
+use crate::ui::utils::{ReadingRange, row, background, row_titles, TitlesOpt, error_message, error_lines};
+use weather::models::{PredictionDiff, Weather, SimpleDate};
+use weather::analysis::MAX_HOUR_DIFF;
+use ratatui::Frame;
+use ratatui::layout::{Rect, Layout, Constraint};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::Paragraph;
 
 const HEADER_COLOR: Color = Color::Cyan;
 
 pub struct WeatherDiff {
-    reset_pos: (u16, u16)
+    range: ReadingRange,
+    picker: DatePicker,
+    /// Reading and the difference of each prediction for the picked hour, or why they couldn't be shown
+    data: Result<(Weather, Vec<PredictionDiff>), String>,
+    pane: ScrollPane,
 }
 
 impl WeatherDiff {
-    pub fn new(reset_pos: (u16, u16)) -> WeatherDiff {
-        return WeatherDiff {
-            reset_pos
+    /// Starts at the latest reading
+    ///
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<WeatherDiff, Error> {
+        let range = ReadingRange::load(app)?;
+        let mut view = WeatherDiff {
+            picker: DatePicker::new(range.last.date(), Precision::Hour),
+            data: Err(String::new()),
+            range,
+            pane: ScrollPane::default(),
+        };
+        view.load(app);
+        return Ok(view);
+    }
+
+    fn load(&mut self, app: &mut WeatherApp) {
+        let selected_date = self.picker.value();
+        self.data = if self.range.contains(selected_date) {
+            let date = SimpleDate::from(selected_date);
+            app.get_reading_with_prediction_diffs(date.year, date.day, date.hour).map_err(|err| error_message(&err))
+        } else {
+            Err(String::from("Outside of data range"))
         };
     }
 }
 
-impl WeatherDiff {}
+impl WeatherDiff {
+    /// Background color of a cell based on how far off the prediction was
+    ///
+    /// `small`, `medium` and `large` are the absolute error thresholds for each shade of red
+    fn error_style(value: &Option<f64>, small: f64, medium: f64, large: f64) -> Style {
+        return background(match value.map(|val| val.abs()) {
+            Some(err) if err >= large => 160,
+            Some(err) if err >= medium => 124,
+            Some(err) if err >= small => 88,
+            _ => 16
+        });
+    }
 
-impl UiSection for WeatherDiff {
-    fn run(&mut self, app: &mut WeatherApp) -> Result<(), Error> {
-        self.reset(self.reset_pos)?;
+    fn format_value(value: Option<f64>, precision: usize) -> String {
+        return match value {
+            Some(val) => format!("{: <+5.1$} ", val, precision),
+            None => String::from("-     ")
+        };
+    }
 
-        self.reset_pos = crossterm::cursor::position()?;
+    fn temp_row(&self, data: &[Option<PredictionDiff>]) -> Line<'static> {
+        row(
+            "Temp    ",
+            HEADER_COLOR,
+            data.iter().map(|diff| diff.map(|diff| diff.temp)).collect(),
+            |val| WeatherDiff::format_value(val, 1),
+            |val| WeatherDiff::error_style(val, 1., 2., 4.),
+        )
+    }
+
+    fn prob_row(&self, data: &[Option<PredictionDiff>]) -> Line<'static> {
+        row(
+            "P. Prob ",
+            HEADER_COLOR,
+            data.iter().map(|diff| diff.map(|diff| (diff.precip_probability * 100.).round())).collect(),
+            |val| WeatherDiff::format_value(val, 0),
+            |val| WeatherDiff::error_style(val, 20., 40., 60.),
+        )
+    }
 
-        let (first, last) = print_first_last_reading("View specific reading predictions\n", app)?;
+    fn amt_row(&self, data: &[Option<PredictionDiff>]) -> Line<'static> {
+        row(
+            "P. Amt  ",
+            HEADER_COLOR,
+            data.iter().map(|diff| diff.map(|diff| diff.precip_intensity)).collect(),
+            |val| WeatherDiff::format_value(val, 1),
+            |val| WeatherDiff::error_style(val, 0.3, 1., 3.),
+        )
+    }
 
-        let mut selected_date: NaiveDateTime = self.input_year_day_hour()?.try_into()?;
+    fn speed_row(&self, data: &[Option<PredictionDiff>]) -> Line<'static> {
+        row(
+            "Wnd Spd ",
+            HEADER_COLOR,
+            data.iter().map(|diff| diff.map(|diff| diff.wind_speed)).collect(),
+            |val| WeatherDiff::format_value(val, 1),
+            |val| WeatherDiff::error_style(val, 2., 4., 8.),
+        )
+    }
 
-        loop {
-            if selected_date < first.date() || selected_date > last.date() {
-                print_styled("\n\nOutside of data range\n\n", Color::Red, false)?;
+    fn gust_row(&self, data: &[Option<PredictionDiff>]) -> Line<'static> {
+        row(
+            "Wnd Gst ",
+            HEADER_COLOR,
+            data.iter().map(|diff| diff.map(|diff| diff.wind_gust)).collect(),
+            |val| WeatherDiff::format_value(val, 1),
+            |val| WeatherDiff::error_style(val, 2., 4., 8.),
+        )
+    }
 
-                std::thread::sleep(Duration::from_millis(500));
+    fn humidity_row(&self, data: &[Option<PredictionDiff>]) -> Line<'static> {
+        row(
+            "Humid   ",
+            HEADER_COLOR,
+            data.iter().map(|diff| diff.map(|diff| (diff.humidity * 100.).round())).collect(),
+            |val| WeatherDiff::format_value(val, 0),
+            |val| WeatherDiff::error_style(val, 10., 20., 30.),
+        )
+    }
 
-                self.wait_for_char("\n\nPress any key to continue\n")?;
+    fn icon_row(&self, data: &[Option<PredictionDiff>]) -> Line<'static> {
+        row(
+            "Icon    ",
+            HEADER_COLOR,
+            data.iter().map(|diff| diff.map(|diff| diff.icon_matched)).collect(),
+            |val| match val {
+                Some(true) => String::from("Yes   "),
+                Some(false) => String::from("No    "),
+                None => String::from("-     ")
+            },
+            |val| background(match val {
+                Some(false) => 124,
+                _ => 16
+            }),
+        )
+    }
 
-                return Ok(());
-            } else {
-                let start: SimpleDate = selected_date.into();
-                let mut end = start.clone();
-                end.hour = 23;
+    fn block(&self, data: &[Option<PredictionDiff>], start: usize) -> Vec<Line<'static>> {
+        return vec![
+            row_titles(TitlesOpt {
+                start,
+                end: start + data.len() - 1,
+                initial_padding: 8,
+                between_padding: 4,
+                color: HEADER_COLOR
+            }),
+            self.temp_row(data),
+            self.prob_row(data),
+            self.amt_row(data),
+            self.speed_row(data),
+            self.gust_row(data),
+            self.humidity_row(data),
+            self.icon_row(data),
+        ];
+    }
 
-                self.reset(self.reset_pos)?;
+    fn observed(&self, weather: &Weather) -> Line<'static> {
+        return Line::from(vec![
+            Span::styled("Observed  ", Style::new().fg(HEADER_COLOR)),
+            Span::raw(format!(
+                "Temp {:.1}°C  P. Prob {:.0}%  P. Amt {:.1} mm/h  Wnd Spd {:.1} m/s  Wnd Gst {:.1} m/s  Humid {:.0}%  Icon {}",
+                weather.temp,
+                weather.precip_probability * 100.,
+                weather.precip_intensity,
+                weather.wind_speed,
+                weather.wind_gust,
+                weather.humidity * 100.,
+                weather.icon.to_str()
+            )),
+        ]);
+    }
 
-                stdout()
-                    .execute(Print("\nViewing  "))?;
+    fn diff_lines(&self, weather: &Weather, diffs: &[PredictionDiff]) -> Vec<Line<'static>> {
+        let mut slots: Vec<Option<PredictionDiff>> = vec![None; MAX_HOUR_DIFF as usize];
+        diffs.iter()
+            .filter(|diff| diff.hour_diff >= 1 && diff.hour_diff <= MAX_HOUR_DIFF)
+            .for_each(|diff| slots[diff.hour_diff as usize - 1] = Some(*diff));
+
+        let mut lines = vec![
+            Line::styled("(Predicted - Observed, by hours before)", Style::new().fg(Color::Gray)),
+            Line::default(),
+            self.observed(weather),
+            Line::default(),
+        ];
+        lines.extend(self.block(&slots[0..24], 1));
+        lines.push(Line::default());
+        lines.extend(self.block(&slots[24..MAX_HOUR_DIFF as usize], 25));
+
+        return lines;
+    }
+}
 
-                print_styled(&format!("{}", selected_date.format("%a %Y-%m-%d")), Color::White, true)?;
+impl Screen for WeatherDiff {
+    fn title(&self) -> String {
+        return String::from("Differences for hour");
+    }
 
-                let readings = app.get_readings_over_range(start, end)?;
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return vec![("←→", "date part"), ("↑↓", "change"), ("0-9", "type"), ("PgUp/PgDn", "scroll"), ("Shift+←→", "scroll across"), ("Esc", "back")];
+    }
 
-                
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let [picker_area, content_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
 
-                print_styled("\n\n(◄) Previous hour\n(►) Next hour\n(▲) Previous day\n(▼) Next day\n(esc) Go back", Color::Grey, false)?;
+        frame.render_widget(Paragraph::new(vec![self.picker.line("Viewing", true), self.range.line()]), picker_area);
 
-                loop {
-                    let char = self.wait_for_char_no_delay()?;
+        let lines = match &self.data {
+            Ok((weather, diffs)) => self.diff_lines(weather, diffs),
+            Err(message) => error_lines(message)
+        };
+        self.pane.draw(frame, content_area, Text::from(lines), focus_block("Differences", true));
+    }
 
-                    match char {
-                        KeyCode::Esc => return Ok(()),
-                        KeyCode::Up => {
-                            selected_date = selected_date.minus_one_day();
-                            break;
-                        }
-                        KeyCode::Down => {
-                            selected_date = selected_date.plus_one_day();
-                            break;
-                        }
-                        KeyCode::Left => {
-                            selected_date = selected_date.minus_one_hour();
-                            break;
-                        }
-                        KeyCode::Right => {
-                            selected_date = selected_date.plus_one_hour();
-                            break;
-                        }
-                        _ => {}
-                    }
-                }
-            }
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        if key.code == KeyCode::Esc {
+            return Ok(Action::Pop);
         }
+        if !self.pane.handle_key(key) && self.picker.handle_key(key) {
+            self.load(app);
+        }
+
+        return Ok(Action::None);
     }
-}
\ No newline at end of file
+}
@@ -1,216 +1,331 @@
-use crate::app::WeatherApp;
+use weather::app::WeatherApp;
 use crate::Error;
-use std::io::stdout;
-use crossterm::ExecutableCommand;
-use crossterm::style::{Print, Color, SetBackgroundColor};
-use crossterm::event::KeyCode;
-use crate::ui::ui_section::UiSection;
-use crate::ui::utils::{print_styled, print_first_last_reading, print_row_titles, TitlesOpt};
-use std::convert::TryInto;
-use chrono::{NaiveDateTime, Datelike, Timelike};
-use crate::extensions::Utils;
-use std::time::Duration;
+use crate::ui::screen::{Screen, Action};
+use crate::ui::date_picker::{DatePicker, Precision};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::form::focus_block;
+use crate::ui::utils::{ReadingRange, row, background, row_titles, TitlesOpt, error_message, error_lines};
+use weather::models::{Weather, Prediction, WeatherDetails, SimpleDate};
+use weather::analysis::MAX_HOUR_DIFF;
+use crate::ui::chart::{LineChart, Series, sparkline_line};
+use ratatui::Frame;
+use ratatui::layout::{Rect, Layout, Constraint};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::Paragraph;
 
 const HEADER_COLOR: Color = Color::Cyan;
+/// Characters across for each lead hour in chart mode
+const CHART_HOUR_WIDTH: usize = 2;
 
 pub struct WeatherPredictions {
-    reset_pos: (u16, u16)
+    range: ReadingRange,
+    picker: DatePicker,
+    /// Reading and its predictions for the picked hour, or why they couldn't be shown
+    data: Result<(Weather, Vec<Prediction>), String>,
+    pane: ScrollPane,
+    /// Show line charts instead of the grid
+    chart: bool,
 }
 
 impl WeatherPredictions {
-    pub fn new(reset_pos: (u16, u16)) -> WeatherPredictions {
-        return WeatherPredictions {
-            reset_pos
+    /// Starts at the latest reading
+    ///
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<WeatherPredictions, Error> {
+        let range = ReadingRange::load(app)?;
+        let mut view = WeatherPredictions {
+            picker: DatePicker::new(range.last.date(), Precision::Hour),
+            data: Err(String::new()),
+            range,
+            pane: ScrollPane::default(),
+            chart: false,
         };
+        view.load(app);
+        return Ok(view);
     }
-}
 
-impl WeatherPredictions {
+    fn load(&mut self, app: &mut WeatherApp) {
+        let selected_date = self.picker.value();
+        self.data = if self.range.contains(selected_date) {
+            let date = SimpleDate::from(selected_date);
+            app.get_reading_with_predictions(date.year, date.day, date.hour).map_err(|err| error_message(&err))
+        } else {
+            Err(String::from("Outside of data range"))
+        };
+    }
 
-    fn print_temp_row(&self, data: &Vec<f64>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn temp_row(&self, data: &[f64], skip: usize, take: usize) -> Line<'static> {
+        row(
             "Temp    ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
             |val| format!("{: <3.0}   ", val),
-            |_| Ok(()),
+            |_| Style::new(),
         )
     }
 
-    fn print_prob_row(&self, data: &Vec<usize>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn prob_row(&self, data: &[usize], skip: usize, take: usize) -> Line<'static> {
+        row(
             "P. Prob ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
             |val| format!("{: <3}   ", val),
-            |val| {
-                let ansi = match val {
-                    90..=100 => 21,
-                    70..=89 => 20,
-                    50..=69 => 19,
-                    30..=49 => 18,
-                    _ => 16
-                };
-                stdout().execute(SetBackgroundColor(Color::AnsiValue(ansi)))?;
-                Ok(())
-            },
+            |val| background(match val {
+                90..=100 => 21,
+                70..=89 => 20,
+                50..=69 => 19,
+                30..=49 => 18,
+                _ => 16
+            }),
         )
     }
 
-    fn print_amt_row(&self, data: &Vec<f64>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn amt_row(&self, data: &[f64], skip: usize, take: usize) -> Line<'static> {
+        row(
             "P. Amt  ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
             |val| format!("{:.1}   ", val),
-            |val| {
-                let ansi = match val {
-                    d if d > &3.0 => 21,
-                    1.0..=2.999 => 20,
-                    0.3..=0.999 => 18,
-                    _ => 16
-                };
-                stdout().execute(SetBackgroundColor(Color::AnsiValue(ansi)))?;
-                Ok(())
-            },
+            |val| background(match val {
+                d if d > &3.0 => 21,
+                1.0..=2.999 => 20,
+                0.3..=0.999 => 18,
+                _ => 16
+            }),
         )
     }
 
-    fn print_type_row(&self, data: &Vec<String>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn type_row(&self, data: &[String], skip: usize, take: usize) -> Line<'static> {
+        row(
             "Precip  ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
             |val| format!("{: <5} ", val),
-            |_| Ok(()),
+            |_| Style::new(),
         )
     }
 
-    fn print_gust_row(&self, data: &Vec<f64>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn gust_row(&self, data: &[f64], skip: usize, take: usize) -> Line<'static> {
+        row(
             "Wnd Gst ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
             |val| format!("{: <3.0}   ", val),
-            |_| Ok(()),
+            |_| Style::new(),
         )
     }
 
-    fn print_speed_row(&self, data: &Vec<f64>, skip: usize, take: usize) -> Result<(), Error> {
-        self.print_row(
+    fn speed_row(&self, data: &[f64], skip: usize, take: usize) -> Line<'static> {
+        row(
             "Wnd Spd ",
             HEADER_COLOR,
             data.iter().skip(skip).take(take).cloned().collect(),
             |val| format!("{: <3.0}   ", val),
-            |_| Ok(()),
+            |_| Style::new(),
+        )
+    }
+
+    fn direction_row(&self, data: &[&str], skip: usize, take: usize) -> Line<'static> {
+        row(
+            "Wnd Dir ",
+            HEADER_COLOR,
+            data.iter().skip(skip).take(take).map(|val| val.to_string()).collect(),
+            |val| format!("{: <3}   ", val),
+            |_| Style::new(),
+        )
+    }
+
+    /// Row for a detail that might be missing, `-` is shown for missing values
+    fn detail_row(&self, title: &str, data: &[Option<f64>], skip: usize, take: usize) -> Line<'static> {
+        row(
+            title,
+            HEADER_COLOR,
+            data.iter().skip(skip).take(take).cloned().collect(),
+            |val| match val {
+                Some(val) => format!("{: <4.0}  ", val),
+                None => String::from("-     ")
+            },
+            |_| Style::new(),
         )
     }
+
+    fn block(&self, data: &GridData, skip: usize) -> Vec<Line<'static>> {
+        return vec![
+            self.temp_row(&data.temps, skip, 24),
+            self.detail_row("Air Tmp ", &data.air_temps, skip, 24),
+            self.detail_row("Dew Pt  ", &data.dew_points, skip, 24),
+            self.prob_row(&data.probs, skip, 24),
+            self.amt_row(&data.amts, skip, 24),
+            self.type_row(&data.types, skip, 24),
+            self.speed_row(&data.speeds, skip, 24),
+            self.gust_row(&data.gusts, skip, 24),
+            self.direction_row(&data.directions, skip, 24),
+            self.detail_row("Cloud % ", &data.clouds, skip, 24),
+            self.detail_row("Pressure", &data.pressures, skip, 24),
+        ];
+    }
+
+    /// The reading then each prediction by how many hours before it was made, in two blocks of 24
+    fn grid(&self, reading: &Weather, predictions: &[Prediction]) -> Vec<Line<'static>> {
+        let data = GridData::new(reading, predictions);
+
+        let mut first_titles = row_titles(TitlesOpt {
+            start: 1,
+            end: 23,
+            initial_padding: 0,
+            between_padding: 4,
+            color: HEADER_COLOR
+        });
+        first_titles.spans.insert(0, Span::styled("        Time  ", Style::new().fg(HEADER_COLOR)));
+
+        let mut lines = vec![first_titles];
+        lines.extend(self.block(&data, 0));
+        lines.push(Line::default());
+        lines.push(row_titles(TitlesOpt {
+            start: 24,
+            end: 47,
+            initial_padding: 8,
+            between_padding: 4,
+            color: HEADER_COLOR
+        }));
+        lines.extend(self.block(&data, 24));
+
+        return lines;
+    }
+
+    /// Chart each prediction against the reading, by how many hours before the prediction was made
+    fn charts(&self, reading: &Weather, predictions: &[Prediction]) -> Vec<Line<'static>> {
+        let forecasts = |getter: fn(&Prediction) -> f64| {
+            let mut values = vec![None; MAX_HOUR_DIFF as usize];
+            predictions.iter()
+                .filter(|prediction| prediction.hour_diff >= 1 && prediction.hour_diff <= MAX_HOUR_DIFF)
+                .for_each(|prediction| values[prediction.hour_diff as usize - 1] = Some(getter(prediction)));
+            return values;
+        };
+        let observed = |value: f64| vec![Some(value); MAX_HOUR_DIFF as usize];
+        let x_labels = (1..=MAX_HOUR_DIFF as usize)
+            .filter(|hour_diff| *hour_diff == 1 || hour_diff % 6 == 0)
+            .map(|hour_diff| (hour_diff - 1, hour_diff.to_string()))
+            .collect();
+
+        let mut lines = LineChart {
+            series: vec![
+                Series { name: String::from("Predicted"), color: Color::Yellow, values: forecasts(|prediction| prediction.temp) },
+                Series { name: String::from("Observed"), color: Color::White, values: observed(reading.temp) },
+            ],
+            width: MAX_HOUR_DIFF as usize * CHART_HOUR_WIDTH,
+            height: 10,
+            label_width: 8,
+            x_labels,
+        }.lines("Temperature by hours before");
+
+        let mut prob = sparkline_line("P. Prob", 8, &forecasts(|prediction| prediction.precip_probability), 1., CHART_HOUR_WIDTH, Color::Blue);
+        prob.spans.push(Span::raw(format!("  {:.0}% observed", reading.precip_probability * 100.)));
+        let mut amt = sparkline_line("P. Amt", 8, &forecasts(|prediction| prediction.precip_intensity), 3., CHART_HOUR_WIDTH, Color::Blue);
+        amt.spans.push(Span::raw(format!("  {:.1} observed", reading.precip_intensity)));
+
+        lines.push(Line::default());
+        lines.push(prob);
+        lines.push(amt);
+
+        return lines;
+    }
 }
 
-impl UiSection for WeatherPredictions {
-    fn run(&mut self, app: &mut WeatherApp) -> Result<(), Error> {
-        self.reset(self.reset_pos)?;
-        self.reset_pos = crossterm::cursor::position()?;
-
-        let (first, last) = print_first_last_reading("View specific reading predictions\n", app)?;
-
-        let mut selected_date: NaiveDateTime = self.input_year_day_hour()?.try_into()?;
-
-        loop {
-            if selected_date < first.date() || selected_date > last.date() {
-                print_styled("\n\nOutside of data range\n\n", Color::Red, false)?;
-
-                std::thread::sleep(Duration::from_millis(500));
-
-                self.wait_for_char("\n\nPress any key to continue\n")?;
-
-                return Ok(());
-            } else {
-                let reading = app.get_reading_with_predictions(selected_date.year() as u16, selected_date.ordinal() as u16, selected_date.hour() as u8)?;
-
-                self.reset(self.reset_pos)?;
-
-                stdout()
-                    .execute(Print("\nViewing  "))?;
-
-                print_styled(&format!("{}", selected_date.format("%a %Y-%m-%d %H:00")), Color::White, true)?;
-
-                let mut temps: Vec<f64> = reading.1.iter().map(|p| p.temp).collect();
-                temps.insert(0, reading.0.temp);
-
-                let mut probs: Vec<usize> = reading.1.iter().map(|p| (p.precip_probability * 100.) as usize).collect();
-                probs.insert(0, (reading.0.precip_probability * 100.) as usize);
-
-                let mut amts: Vec<f64> = reading.1.iter().map(|p| p.precip_intensity).collect();
-                amts.insert(0, reading.0.precip_intensity);
-
-                let mut types: Vec<String> = reading.1.iter().map(|p| p.precip_type.as_ref().unwrap_or(&String::from("-")).clone()).collect();
-                types.insert(0, reading.0.precip_type.as_ref().unwrap_or(&String::from("-")).clone());
-
-                let mut speeds: Vec<f64> = reading.1.iter().map(|p| p.wind_speed).collect();
-                speeds.insert(0, reading.0.wind_speed);
-
-                let mut gusts: Vec<f64> = reading.1.iter().map(|p| p.wind_gust).collect();
-                gusts.insert(0, reading.0.wind_gust);
-
-                print_styled("\n\n        Time  ", HEADER_COLOR, false)?;
-                print_row_titles(TitlesOpt {
-                    start: 1,
-                    end: 23,
-                    newlines: 0,
-                    initial_padding: 0,
-                    between_padding: 4,
-                    color: HEADER_COLOR
-                })?;
-                self.print_temp_row(&temps, 0, 24)?;
-                self.print_prob_row(&probs, 0, 24)?;
-                self.print_amt_row(&amts, 0, 24)?;
-                self.print_type_row(&types, 0, 24)?;
-                self.print_speed_row(&speeds, 0, 24)?;
-                self.print_gust_row(&gusts, 0, 24)?;
-
-                print_row_titles(TitlesOpt {
-                    start: 24,
-                    end: 47,
-                    newlines: 2,
-                    initial_padding: 8,
-                    between_padding: 4,
-                    color: HEADER_COLOR
-                })?;
-                self.print_temp_row(&temps, 24, 24)?;
-                self.print_prob_row(&probs, 24, 24)?;
-                self.print_amt_row(&amts, 24, 24)?;
-                self.print_type_row(&types, 24, 24)?;
-                self.print_speed_row(&speeds, 24, 24)?;
-                self.print_gust_row(&gusts, 24, 24)?;
-
-                print_styled("\n\n(◄) Previous hour\n(►) Next hour\n(▲) Previous day\n(▼) Next day\n(esc) Go back", Color::Grey, false)?;
-
-                loop {
-                    let char = self.wait_for_char_no_delay()?;
-
-                    match char {
-                        KeyCode::Esc => return Ok(()),
-                        KeyCode::Up => {
-                            selected_date = selected_date.minus_one_day();
-                            break;
-                        }
-                        KeyCode::Down => {
-                            selected_date = selected_date.plus_one_day();
-                            break;
-                        }
-                        KeyCode::Left => {
-                            selected_date = selected_date.minus_one_hour();
-                            break;
-                        }
-                        KeyCode::Right => {
-                            selected_date = selected_date.plus_one_hour();
-                            break;
-                        }
-                        _ => {}
-                    }
+/// Each row of the grid, the first entry is the reading and the rest are its predictions
+struct GridData {
+    temps: Vec<f64>,
+    air_temps: Vec<Option<f64>>,
+    dew_points: Vec<Option<f64>>,
+    probs: Vec<usize>,
+    amts: Vec<f64>,
+    types: Vec<String>,
+    speeds: Vec<f64>,
+    gusts: Vec<f64>,
+    directions: Vec<&'static str>,
+    clouds: Vec<Option<f64>>,
+    pressures: Vec<Option<f64>>,
+}
+
+impl GridData {
+    fn new(reading: &Weather, predictions: &[Prediction]) -> GridData {
+        let details: Vec<&WeatherDetails> = std::iter::once(&reading.details)
+            .chain(predictions.iter().map(|p| &p.details))
+            .collect();
+
+        let mut temps: Vec<f64> = predictions.iter().map(|p| p.temp).collect();
+        temps.insert(0, reading.temp);
+
+        let mut probs: Vec<usize> = predictions.iter().map(|p| (p.precip_probability * 100.) as usize).collect();
+        probs.insert(0, (reading.precip_probability * 100.) as usize);
+
+        let mut amts: Vec<f64> = predictions.iter().map(|p| p.precip_intensity).collect();
+        amts.insert(0, reading.precip_intensity);
+
+        let mut types: Vec<String> = predictions.iter().map(|p| p.precip_type.as_ref().unwrap_or(&String::from("-")).clone()).collect();
+        types.insert(0, reading.precip_type.as_ref().unwrap_or(&String::from("-")).clone());
+
+        let mut speeds: Vec<f64> = predictions.iter().map(|p| p.wind_speed).collect();
+        speeds.insert(0, reading.wind_speed);
+
+        let mut gusts: Vec<f64> = predictions.iter().map(|p| p.wind_gust).collect();
+        gusts.insert(0, reading.wind_gust);
+
+        return GridData {
+            temps,
+            air_temps: details.iter().map(|d| d.air_temp).collect(),
+            dew_points: details.iter().map(|d| d.dew_point).collect(),
+            probs,
+            amts,
+            types,
+            speeds,
+            gusts,
+            directions: details.iter().map(|d| d.wind_direction().unwrap_or("-")).collect(),
+            clouds: details.iter().map(|d| d.cloud_cover.map(|cover| cover * 100.)).collect(),
+            pressures: details.iter().map(|d| d.pressure).collect(),
+        };
+    }
+}
+
+impl Screen for WeatherPredictions {
+    fn title(&self) -> String {
+        return String::from("Predictions for hour");
+    }
+
+    fn hints(&self) -> Vec<(&str, &str)> {
+        let view = if self.chart { "grid" } else { "chart" };
+        return vec![("←→", "date part"), ("↑↓", "change"), ("0-9", "type"), ("g", view), ("PgUp/PgDn", "scroll"), ("Shift+←→", "scroll across"), ("Esc", "back")];
+    }
+
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let [picker_area, content_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+
+        frame.render_widget(Paragraph::new(vec![self.picker.line("Viewing", true), self.range.line()]), picker_area);
+
+        let lines = match &self.data {
+            Ok((reading, predictions)) if self.chart => self.charts(reading, predictions),
+            Ok((reading, predictions)) => self.grid(reading, predictions),
+            Err(message) => error_lines(message)
+        };
+        self.pane.draw(frame, content_area, Text::from(lines), focus_block("Predictions by hours before", true));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        match key.code {
+            KeyCode::Esc => return Ok(Action::Pop),
+            KeyCode::Char('g') => self.chart = !self.chart,
+            _ => {
+                if !self.pane.handle_key(key) && self.picker.handle_key(key) {
+                    self.load(app);
                 }
             }
         }
+
+        return Ok(Action::None);
     }
-}
\ No newline at end of file
+}
@@ -1,47 +1,84 @@
-use crate::ui::ui_section::UiSection;
-use crate::app::WeatherApp;
+use crate::ui::screen::{Screen, Action};
+use crate::ui::date_picker::{DatePicker, Precision};
+use crate::ui::scroll_pane::ScrollPane;
+use crate::ui::form::focus_block;
+use crate::ui::utils::{ReadingRange, error_message, error_lines};
+use weather::app::WeatherApp;
+use weather::models::{Weather, SimpleDate};
 use crate::Error;
-use std::io::stdout;
-use crossterm::style::{Color, Print};
-use crossterm::ExecutableCommand;
-use chrono::{NaiveDateTime, Datelike, Timelike};
-use crate::ui::utils::{print_first_last_reading, print_styled};
-use std::convert::TryInto;
+use ratatui::Frame;
+use ratatui::layout::{Rect, Layout, Constraint};
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::text::{Line, Text};
+use ratatui::widgets::Paragraph;
 
 pub struct WeatherView {
-    reset_pos: (u16, u16)
+    range: ReadingRange,
+    picker: DatePicker,
+    /// Reading for the picked hour, or why it couldn't be shown
+    reading: Result<Weather, String>,
+    pane: ScrollPane,
 }
 
 impl WeatherView {
-    pub fn new(reset_pos: (u16, u16)) -> WeatherView {
-        return WeatherView {
-            reset_pos
+    /// Starts at the latest reading
+    ///
+    /// # Errors
+    /// No readings for the location
+    /// Database errors
+    ///
+    pub fn new(app: &mut WeatherApp) -> Result<WeatherView, Error> {
+        let range = ReadingRange::load(app)?;
+        let mut view = WeatherView {
+            picker: DatePicker::new(range.last.date(), Precision::Hour),
+            reading: Err(String::new()),
+            range,
+            pane: ScrollPane::default(),
+        };
+        view.load(app);
+        return Ok(view);
+    }
+
+    fn load(&mut self, app: &mut WeatherApp) {
+        let selected_date = self.picker.value();
+        self.reading = if self.range.contains(selected_date) {
+            let date = SimpleDate::from(selected_date);
+            app.get_reading(date.year, date.day, date.hour).map_err(|err| error_message(&err))
+        } else {
+            Err(String::from("Outside of data range"))
         };
     }
 }
 
-impl UiSection for WeatherView {
-    fn run(&mut self, app: &mut WeatherApp) -> Result<(), Error> {
-        self.reset(self.reset_pos)?;
-        self.reset_pos = crossterm::cursor::position()?;
+impl Screen for WeatherView {
+    fn title(&self) -> String {
+        return String::from("Reading for hour");
+    }
 
-        let (first, last) = print_first_last_reading("View specific reading\n", app)?;
+    fn hints(&self) -> Vec<(&str, &str)> {
+        return vec![("←→", "date part"), ("↑↓", "change"), ("0-9", "type"), ("PgUp/PgDn", "scroll"), ("Esc", "back")];
+    }
 
-        let selected_date: NaiveDateTime = self.input_year_day_hour()?.try_into()?;
+    fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let [picker_area, reading_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
 
-        if selected_date < first.date() || selected_date > last.date() {
-            print_styled("Outside of data range", Color::Red, false)?;
-        } else {
-            let reading = app.get_reading(selected_date.year() as u16, selected_date.ordinal() as u16, selected_date.hour() as u8)?;
+        frame.render_widget(Paragraph::new(vec![self.picker.line("Viewing", true), self.range.line()]), picker_area);
 
-            self.reset(self.reset_pos)?;
+        let lines = match &self.reading {
+            Ok(reading) => reading.to_string().lines().map(|line| Line::raw(line.to_owned())).collect(),
+            Err(message) => error_lines(message)
+        };
+        self.pane.draw(frame, reading_area, Text::from(lines), focus_block("Reading", true));
+    }
 
-            stdout()
-                .execute(Print(format!("{}", reading)))?;
+    fn handle_key(&mut self, key: KeyEvent, app: &mut WeatherApp) -> Result<Action, Error> {
+        if key.code == KeyCode::Esc {
+            return Ok(Action::Pop);
+        }
+        if !self.pane.handle_key(key) && self.picker.handle_key(key) {
+            self.load(app);
         }
 
-        self.wait_for_char("\n\nPress any key to continue\n")?;
-
-        Ok(())
+        return Ok(Action::None);
     }
-}
\ No newline at end of file
+}